@@ -5,10 +5,182 @@ const axios = require("axios");
 const fs = require("fs");
 const yaml = require("js-yaml");
 const {schedule} = require("firebase-functions/v1/pubsub");
+const crypto = require("crypto");
 
 admin.initializeApp();
 const db = admin.firestore();
 
+// Firestore batches hold at most 500 writes.
+const BATCH_SIZE = 500;
+// A saved session logs the client in again until it expires or the user logs out.
+const SESSION_DAYS = 30;
+
+function loadConfig() {
+  const configFile = fs.readFileSync("config.yaml", "utf8");
+  return yaml.load(configFile);
+}
+
+// The opaque key stored in ranking entries instead of the email address.
+// It is stable for an email, so later logins and the migration agree on it.
+function getUserKey(email, secret) {
+  return crypto.createHmac("sha256", secret).update(email).digest("hex");
+}
+
+// Rekeys the entries of one email address, naming them after the user if
+// they have chosen a name already.
+async function migrateEntries(email, key, name) {
+  const snapshot = await db.collection("ranking").where("id", "==", email).get();
+  for (let i = 0; i < snapshot.docs.length; i += BATCH_SIZE) {
+    const batch = db.batch();
+    snapshot.docs.slice(i, i + BATCH_SIZE).forEach((doc) => {
+      batch.update(doc.ref, {id: key, name: name || ""});
+    });
+    await batch.commit();
+  }
+  return snapshot.docs.length;
+}
+
+// Google comes from the top level settings, which predate the other
+// providers. The others are listed by id under `providers`.
+function getProvider(config, id) {
+  if (!id || id === "google") {
+    return {
+      kind: "google",
+      client_id: config.settings.client_id,
+      client_secret: config.settings.client_secret,
+      native_client_id: config.settings.native_client_id,
+      native_client_secret: config.settings.native_client_secret,
+      token_endpoint: "https://oauth2.googleapis.com/token",
+      userinfo_endpoint: "https://www.googleapis.com/oauth2/v3/userinfo",
+    };
+  }
+  const provider = (config.settings.providers || {})[id];
+  if (!provider) {
+    throw new Error(`Unknown provider: ${id}`);
+  }
+  if (provider.kind === "github") {
+    return {
+      token_endpoint: "https://github.com/login/oauth/access_token",
+      ...provider,
+    };
+  }
+  return provider;
+}
+
+// The client starts its state with the provider id, e.g. `github.<uuid>`.
+// States without one come from clients that only knew Google.
+function getProviderId(state) {
+  const index = state.indexOf(".");
+  return index < 0 ? "google" : state.slice(0, index);
+}
+
+// GitHub answers with a form unless asked for JSON, and reports errors with
+// status 200.
+async function exchangeCode(provider, code, redirectUri, extra = {}) {
+  const response = await axios.post(provider.token_endpoint, new URLSearchParams({
+    code: code,
+    client_id: provider.client_id,
+    client_secret: provider.client_secret,
+    redirect_uri: redirectUri,
+    grant_type: "authorization_code",
+    ...extra,
+  }), {
+    headers: {"Accept": "application/json"},
+  });
+  if (!response.data.access_token) {
+    throw new Error(`No access token: ${response.data.error}`);
+  }
+  return response.data.access_token;
+}
+
+// Google and GitHub want the secret of the native client even with PKCE, so
+// native builds send the code and the verifier here instead of exchanging it.
+async function exchangeNativeCode(provider, code, verifier, redirectUri) {
+  return await exchangeCode({
+    ...provider,
+    client_id: provider.native_client_id || provider.client_id,
+    client_secret: provider.native_client_secret || provider.client_secret,
+  }, code, redirectUri, {code_verifier: verifier});
+}
+
+// Reads who the access token belongs to. Google users are keyed by email so
+// that their keys and entries from before the other providers stay theirs.
+async function getSubject(id, provider, accessToken) {
+  if (provider.kind === "github") {
+    const user = await axios.get("https://api.github.com/user", {
+      headers: {"Authorization": `Bearer ${accessToken}`},
+    });
+    return `github:${user.data.id}`;
+  }
+  const userInfo = await axios.get(provider.userinfo_endpoint, {
+    headers: {"Authorization": `Bearer ${accessToken}`},
+  });
+  if (provider.kind === "google") {
+    return userInfo.data.email;
+  }
+  return `${id}:${userInfo.data.sub}`;
+}
+
+// Checks that a token sent by a native build was issued to the native client
+// of the provider, and reads its subject.
+async function verifyNativeToken(id, provider, accessToken) {
+  if (provider.kind === "google") {
+    const tokenInfo = await axios.get("https://oauth2.googleapis.com/tokeninfo", {
+      params: {access_token: accessToken},
+    });
+    const {aud, email} = tokenInfo.data;
+    return aud === provider.native_client_id && email ? email : null;
+  }
+  if (provider.kind === "github") {
+    // Only answers for tokens of this app, and with the user.
+    const clientId = provider.native_client_id || provider.client_id;
+    const check = await axios.post(`https://api.github.com/applications/${clientId}/token`, {
+      access_token: accessToken,
+    }, {
+      auth: {username: clientId, password: provider.native_client_secret || provider.client_secret},
+      headers: {"Accept": "application/vnd.github+json"},
+    });
+    return `github:${check.data.user.id}`;
+  }
+  // The user info alone does not tell who the token was issued to.
+  if (!provider.introspection_endpoint) {
+    return null;
+  }
+  const introspection = await axios.post(provider.introspection_endpoint, new URLSearchParams({
+    token: accessToken,
+  }), {
+    auth: {username: provider.client_id, password: provider.client_secret},
+  });
+  const {active, client_id: clientId} = introspection.data;
+  if (!active || clientId !== provider.native_client_id) {
+    return null;
+  }
+  return await getSubject(id, provider, accessToken);
+}
+
+// Keys the user, migrates their old entries and opens a session. The email
+// address or provider subject never leaves the functions.
+async function startSession(subject, config) {
+  const key = getUserKey(subject, config.settings.user_key_secret);
+  const userDoc = await db.collection("users").doc(key).get();
+  const name = userDoc.exists ? userDoc.data().name : null;
+  // Only Google users have entries from before the keys.
+  if (subject.includes("@")) {
+    await migrateEntries(subject, key, name);
+  }
+
+  // The token is the document id and the rules allow no listing, so only its
+  // holder can read the session.
+  const session = crypto.randomBytes(32).toString("hex");
+  const expiresAt = new Date(Date.now() + SESSION_DAYS * 24 * 60 * 60 * 1000);
+  await db.collection("sessions").doc(session).set({
+    user_id: key,
+    created_at: Timestamp.now(),
+    expires_at: Timestamp.fromDate(expiresAt),
+  });
+  return {user_id: key, name: name, session: session};
+}
+
 exports.oauth = onRequest(async (request, response) => {
   const state = request.query.state;
   if (!state) {
@@ -23,31 +195,17 @@ exports.oauth = onRequest(async (request, response) => {
   }
 
   try {
-    const configFile = fs.readFileSync("config.yaml", "utf8");
-    const config = yaml.load(configFile);
-
-    const clientId = config.settings.client_id;
-    const clientSecret = config.settings.client_secret;
-    const redirectUri = config.settings.redirect_uri;
-
-    const tokenResponse = await axios.post("https://oauth2.googleapis.com/token", {
-      code: code,
-      client_id: clientId,
-      client_secret: clientSecret,
-      redirect_uri: redirectUri,
-      grant_type: "authorization_code",
-    });
-    const accessToken = tokenResponse.data.access_token;
-    const userInfoResponse = await axios.get("https://www.googleapis.com/oauth2/v3/userinfo", {
-      headers: {
-        "Authorization": `Bearer ${accessToken}`,
-      },
-    });
+    const config = loadConfig();
+    const id = getProviderId(state);
+    const provider = getProvider(config, id);
+
+    const accessToken = await exchangeCode(provider, code, config.settings.redirect_uri);
+    const subject = await getSubject(id, provider, accessToken);
+    const user = await startSession(subject, config);
 
-    const email = userInfoResponse.data.email;
     const docRef = admin.firestore().collection("login").doc(state);
     await docRef.set({
-      user_id: email,
+      ...user,
       created_at: Timestamp.now(),
     });
     response.send("Successfully login. Please close this window.");
@@ -57,6 +215,142 @@ exports.oauth = onRequest(async (request, response) => {
   }
 });
 
+// The same rule as `validate_name` of the client.
+const NAME_PATTERN = /^[A-Za-z0-9_-]{3,16}$/;
+
+// Gives the user the name, unless another user holds it. Clients cannot write
+// `names` or `users`, so this is the only way to take a name.
+async function claimName(userId, name, res) {
+  if (typeof name !== "string" || !NAME_PATTERN.test(name)) {
+    res.status(400).send({error: "invalid_name"});
+    return;
+  }
+  const nameRef = db.collection("names").doc(name.toLowerCase());
+  const userRef = db.collection("users").doc(userId);
+  const isClaimed = await db.runTransaction(async (transaction) => {
+    const nameDoc = await transaction.get(nameRef);
+    if (nameDoc.exists && nameDoc.data().user_id !== userId) {
+      return false;
+    }
+    if (!nameDoc.exists) {
+      transaction.set(nameRef, {user_id: userId});
+    }
+    transaction.set(userRef, {name: name}, {merge: true});
+    return true;
+  });
+  if (!isClaimed) {
+    res.status(409).send({error: "name_taken"});
+    return;
+  }
+  res.status(200).send({user_id: userId, name: name});
+}
+
+// Requests carrying a session act on its user: `name` chooses a display
+// name, `restore` and `end` serve native builds that cannot read `sessions`
+// without a service account key.
+async function handleSession(body, res) {
+  const sessionDoc = await db.collection("sessions").doc(String(body.session)).get();
+  if (!sessionDoc.exists || sessionDoc.data().expires_at.toMillis() <= Date.now()) {
+    res.status(401).send({error: "invalid_session"});
+    return;
+  }
+  const userId = sessionDoc.data().user_id;
+  switch (body.action) {
+    case "name":
+      await claimName(userId, body.name, res);
+      return;
+    case "restore": {
+      const userDoc = await db.collection("users").doc(userId).get();
+      const name = userDoc.exists ? userDoc.data().name : null;
+      res.status(200).send({user_id: userId, name: name || null, session: body.session});
+      return;
+    }
+    case "end":
+      await sessionDoc.ref.delete();
+      res.status(200).send({});
+      return;
+    default:
+      res.status(400).send({error: "unknown_action"});
+  }
+}
+
+// Native builds exchange the code themselves with PKCE and send the access
+// token here with the provider id. It must have been issued to the native
+// client, or any app the user has signed in to could log in as them. For
+// Google and GitHub they send the code and its verifier, which are exchanged
+// here with the secret. Requests with a `session` and an `action` act on a
+// session instead.
+exports.session = onRequest({cors: true}, async (req, res) => {
+  if (req.method === "POST" && req.body && req.body.session) {
+    try {
+      await handleSession(req.body, res);
+    } catch (error) {
+      console.error("Error handling session:", error);
+      res.status(500).send({error: "internal"});
+    }
+    return;
+  }
+  if (req.method === "POST" && req.body && req.body.code) {
+    try {
+      const config = loadConfig();
+      const id = req.body.provider || "google";
+      const provider = getProvider(config, id);
+      const accessToken = await exchangeNativeCode(provider, req.body.code,
+          req.body.code_verifier, req.body.redirect_uri);
+      const subject = await getSubject(id, provider, accessToken);
+      res.status(200).send(await startSession(subject, config));
+    } catch (error) {
+      console.error("Error exchanging code:", error);
+      res.status(401).send({error: "invalid_grant"});
+    }
+    return;
+  }
+  if (req.method !== "POST" || !req.body || !req.body.access_token) {
+    res.status(400).send({error: "Missing access_token"});
+    return;
+  }
+
+  try {
+    const config = loadConfig();
+    const id = req.body.provider || "google";
+    const provider = getProvider(config, id);
+    const subject = await verifyNativeToken(id, provider, req.body.access_token);
+    if (!subject) {
+      res.status(401).send({error: "invalid_token"});
+      return;
+    }
+    res.status(200).send(await startSession(subject, config));
+  } catch (error) {
+    console.error("Error starting session:", error);
+    res.status(401).send({error: "invalid_token"});
+  }
+});
+
+// Rekeys every entry still holding an email address, for users who do not
+// log in again. Entries without a name show a pseudonym in the client.
+exports.migrateUserKeys = onRequest(async (req, res) => {
+  const config = loadConfig();
+  if (!config.settings.admin_token ||
+      req.get("Authorization") !== `Bearer ${config.settings.admin_token}`) {
+    res.status(403).send("Forbidden");
+    return;
+  }
+
+  const snapshot = await db.collection("ranking").get();
+  const emails = new Set(snapshot.docs
+      .map((doc) => doc.data().id)
+      .filter((id) => typeof id === "string" && id.includes("@")));
+
+  let migrated = 0;
+  for (const email of emails) {
+    const key = getUserKey(email, config.settings.user_key_secret);
+    const userDoc = await db.collection("users").doc(key).get();
+    const name = userDoc.exists ? userDoc.data().name : null;
+    migrated += await migrateEntries(email, key, name);
+  }
+  res.status(200).send({users: emails.size, entries: migrated});
+});
+
 exports.deleteOldItems = schedule("every 60 minutes").onRun(async (context) => {
   const oneHourAgo = new Date(Date.now() - 60 * 60 * 1000);
 
@@ -64,7 +358,14 @@ exports.deleteOldItems = schedule("every 60 minutes").onRun(async (context) => {
       .collection("login")
       .where("created_at", "<", oneHourAgo);
   const snapshot = await oldItemsQuery.get();
-  const deletions = snapshot.docs.map((doc) => doc.ref.delete());
+
+  const expiredSessionsQuery = admin.firestore()
+      .collection("sessions")
+      .where("expires_at", "<", Timestamp.now());
+  const expiredSessions = await expiredSessionsQuery.get();
+
+  const deletions = snapshot.docs.concat(expiredSessions.docs)
+      .map((doc) => doc.ref.delete());
   return Promise.all(deletions);
 });
 
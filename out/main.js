@@ -1,5 +1,7 @@
 import initSync from './minesweeper.js';
-import { firebaseConfig } from './config.js';
+import * as config from './config.js';
+
+const { firebaseConfig } = config;
 
 // Initialize Firebase
 const app = firebase.initializeApp(firebaseConfig);
@@ -17,10 +19,18 @@ async function main() {
     }
 }
 
+// config.js의 gameConfig를 게임 설정으로 넘김 (없으면 기본값 사용)
+window.load_config_js = function() {
+    return config.gameConfig;
+}
+
 window.addEventListener('contextmenu', function(e) {
     e.preventDefault();
 }, false);
 
+// 로그인 대기 중인 state별 취소 함수
+const loginListeners = {};
+
 window.listen_login_js = function(uuid) {
     return new Promise((resolve, reject) => {
         const loginDocRef = db.collection('login').doc(uuid);
@@ -31,8 +41,12 @@ window.listen_login_js = function(uuid) {
                 const userId = userData.user_id;
                 console.log("User ID from updated document:", userId);
                 unsubscribe(); // 문서가 감지되면 리스너를 해제
+                delete loginListeners[uuid];
+                // 세션이 담겨 있으므로 읽은 뒤 바로 삭제
+                loginDocRef.delete().catch((error) => console.error("Error deleting login document:", error));
 
-                resolve(userId); // 문서가 존재하면 userId로 resolve
+                // 닉네임을 아직 정하지 않았으면 name은 null
+                resolve({ user_id: userId, name: userData.name || null, session: userData.session || null });
             } else {
                 console.log("Document does not exist yet."); 
                 // 아직 문서가 없으므로 대기하면서 계속 리스너를 유지
@@ -40,22 +54,146 @@ window.listen_login_js = function(uuid) {
         }, (error) => {
             console.error("Error listening to document:", error);
             unsubscribe(); // 에러 발생 시 리스너를 해제
+            delete loginListeners[uuid];
             reject(new Error("Error listening to document")); // 오류가 발생했기 때문에 reject
         });
+
+        // 시간 초과나 취소 시 리스너를 해제하고 reject
+        loginListeners[uuid] = () => {
+            unsubscribe();
+            reject(new Error("Login cancelled"));
+        };
+    });
+}
+
+window.cancel_login_js = function(uuid) {
+    const cancel = loginListeners[uuid];
+    if (cancel) {
+        delete loginListeners[uuid];
+        cancel();
+    }
+}
+
+// 저장해 둔 세션으로 로그인 복원, 만료되었거나 없으면 null로 resolve
+window.restore_session_js = async function(token) {
+    try {
+        const sessionDoc = await db.collection("sessions").doc(token).get();
+        if (!sessionDoc.exists) {
+            return null;
+        }
+        const session = sessionDoc.data();
+        if (session.expires_at.toMillis() <= Date.now()) {
+            return null;
+        }
+        // 다른 기기에서 닉네임을 정했을 수도 있으므로 users에서 다시 읽음
+        const userDoc = await db.collection("users").doc(session.user_id).get();
+        const name = userDoc.exists ? userDoc.data().name : null;
+        return { user_id: session.user_id, name: name || null, session: token };
+    } catch (e) {
+        console.error("Error restoring session: ", e);
+        throw new Error("Error restoring session: " + e.message);
+    }
+}
+
+window.end_session_js = function(token) {
+    return db.collection("sessions").doc(token).delete().catch((e) => {
+        console.error("Error ending session: ", e);
+        throw new Error("Error ending session: " + e.message);
+    });
+}
+
+// 클라이언트는 names/users에 쓸 수 없으므로 session 함수가 세션을 확인하고 닉네임을 선점
+// 다른 사용자가 이미 쓰고 있으면(409) false로 resolve
+window.claim_name_js = async function(sessionEndpoint, session, name) {
+    let response;
+    try {
+        response = await fetch(sessionEndpoint, {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ session: session, action: "name", name: name }),
+        });
+    } catch (e) {
+        console.error("Error claiming name: ", e);
+        throw new Error("Error claiming name: " + e.message);
+    }
+    if (response.status === 409) {
+        return false;
+    }
+    if (!response.ok) {
+        const error = new Error("Error claiming name: " + response.status + " " + await response.text());
+        console.error(error);
+        throw error;
+    }
+    return true;
+}
+
+// guests/{게스트 id}에 토큰을 저장. 읽기는 막혀 있고, 같은 토큰으로 다시 등록하는 것만 허용
+// 다른 토큰으로 이미 등록되어 있으면 false로 resolve
+window.register_guest_js = function(guestId, token) {
+    return db.collection("guests").doc(guestId).set({ token: token }).then(() => {
+        return true;
+    }).catch((e) => {
+        if (e.code === "permission-denied") {
+            return false;
+        }
+        console.error("Error registering guest: ", e);
+        throw new Error("Error registering guest: " + e.message);
     });
 }
 
-window.add_ranking_js = function(id, time, difficulty) {
+// 토큰을 증거로 게스트를 로그인한 사용자에게 넘긴 뒤, 게스트의 기록을 사용자 id로 옮김
+// 옮긴 기록 수로 resolve하고, 토큰이 틀리면 null로 resolve
+window.merge_guest_js = async function(guestId, token, userId, name) {
+    const guestRef = db.collection("guests").doc(guestId);
+    try {
+        await guestRef.set({ token: token, claimed_by: userId, proof: token });
+    } catch (e) {
+        if (e.code === "permission-denied") {
+            return null;
+        }
+        console.error("Error claiming guest: ", e);
+        throw new Error("Error claiming guest: " + e.message);
+    }
+
+    try {
+        const snapshot = await db.collection("ranking").where("id", "==", guestId).get();
+        // 배치 하나에 최대 500개까지 쓸 수 있음
+        for (let i = 0; i < snapshot.docs.length; i += 500) {
+            const batch = db.batch();
+            snapshot.docs.slice(i, i + 500).forEach((doc) => {
+                batch.update(doc.ref, { id: userId, name: name, guest: false });
+            });
+            await batch.commit();
+        }
+        return snapshot.docs.length;
+    } catch (e) {
+        console.error("Error merging guest: ", e);
+        throw new Error("Error merging guest: " + e.message);
+    }
+}
+
+window.add_ranking_js = function(id, name, isGuest, time, difficulty, seed, moves) {
     return new Promise((resolve, reject) => {
         try {
             db.collection("ranking").add({
                 id: id,
+                name: name,
+                guest: isGuest,
                 time: time,
                 difficulty: difficulty,
                 created_at: firebase.firestore.FieldValue.serverTimestamp(),
+                seed: seed,
+                moves: moves,
+                verified: false,
+            }).then(() => {
+                resolve();
+            }).catch((e) => {
+                console.error("Error adding document: ", e);
+                // 규칙에 막힌 요청은 다시 보내지 않도록 Firebase 오류 코드를 함께 넘김
+                const error = new Error("Error adding document: " + e.message);
+                error.code = e.code;
+                reject(error);
             });
-
-            resolve();
         } catch (e) {
             console.error("Error adding document: ", e);
             reject(new Error("Error adding document"));
@@ -79,28 +217,54 @@ function isValidObject(data) {
     return isIdString && isTimeFloat && isDifficultyString && isCreatedAtTimestamp;
 }
 
-window.read_ranking_js = function() {
+// 기록과 초가 같은 기록은 문서 id 순서로 정렬
+function toRankingData(doc) {
+    const data = doc.data();
+    return {
+        id: data.id,
+        name: data.name || "",
+        time: data.time,
+        difficulty: data.difficulty,
+        created_at: data.created_at.seconds,
+        verified: data.verified === true,
+        guest: data.guest === true,
+        doc_id: doc.id
+    };
+}
+
+// 난이도별 랭킹 쿼리 (verifiedOnly면 검증된 기록만)
+function rankingQuery(difficulty, verifiedOnly) {
+    let query = db.collection("ranking").where("difficulty", "==", difficulty);
+    if (verifiedOnly) {
+        query = query.where("verified", "==", true);
+    }
+    return query;
+}
+
+window.read_ranking_page_js = function(difficulty, verifiedOnly, afterTime, afterCreatedAt, afterDocId, limit) {
     return new Promise((resolve, reject) => {
-        const result = [];
         try {
-            db.collection("ranking").get().then((querySnapshot) => {
+            let query = rankingQuery(difficulty, verifiedOnly).orderBy("time").orderBy("created_at").orderBy(firebase.firestore.FieldPath.documentId());
+            if (afterTime !== undefined && afterTime !== null) {
+                query = query.startAfter(afterTime, new firebase.firestore.Timestamp(afterCreatedAt, 0), afterDocId);
+            }
+            // 하나 더 읽어서 다음 페이지가 있는지 확인
+            query.limit(limit + 1).get().then((querySnapshot) => {
+                const entries = [];
                 querySnapshot.forEach((doc) => {
                     const data = doc.data();
-                    const currentSeconds = Math.floor(Date.now() / 1000);
                     if (!isValidObject(data)) {
                         console.error("Invalid object:", data);
                         return;
                     }
-                    const obj = {
-                        id: data.id,
-                        time: data.time,
-                        difficulty: data.difficulty,
-                        created_at: data.created_at ? data.created_at.seconds : currentSeconds
-                    };
-                    result.push(obj);
+                    entries.push(toRankingData(doc));
                 });
 
-                resolve(result);
+                const isLast = querySnapshot.size <= limit;
+                resolve({ entries: entries.slice(0, limit), is_last: isLast });
+            }).catch((e) => {
+                console.error("Error read document: ", e);
+                reject(new Error("Error read document: " + e.message));
             });
         } catch (e) {
             console.error("Error read document: ", e);
@@ -109,5 +273,202 @@ window.read_ranking_js = function() {
     })
 }
 
+// 기간 (start 이상 end 미만, 초 단위) 안의 기록 전체. created_at 범위 조건이 있으면 time으로 정렬할 수 없어서 정렬은 Rust에서 한다
+// 기간 랭킹은 최근 limit개까지만 읽고, 더 있으면 is_truncated로 알린다
+window.read_ranking_period_js = function(difficulty, verifiedOnly, start, end, limit) {
+    return new Promise((resolve, reject) => {
+        try {
+            rankingQuery(difficulty, verifiedOnly)
+                .where("created_at", ">=", new firebase.firestore.Timestamp(start, 0))
+                .where("created_at", "<", new firebase.firestore.Timestamp(end, 0))
+                .orderBy("created_at", "desc")
+                .limit(limit + 1)
+                .get().then((querySnapshot) => {
+                    const entries = [];
+                    querySnapshot.docs.slice(0, limit).forEach((doc) => {
+                        const data = doc.data();
+                        if (isValidObject(data)) {
+                            entries.push(toRankingData(doc));
+                        }
+                    });
+                    resolve({ entries: entries, is_truncated: querySnapshot.size > limit });
+                }).catch((e) => {
+                    console.error("Error read document: ", e);
+                    reject(new Error("Error read document: " + e.message));
+                });
+        } catch (e) {
+            console.error("Error read document: ", e);
+            reject(new Error("Error read document"));
+        }
+    })
+}
+
+// 한 사용자의 최근 기록 최대 500개 (오래된 순)
+window.read_ranking_user_js = function(id) {
+    return new Promise((resolve, reject) => {
+        try {
+            // 최신 순으로 읽어야 limit에서 오래된 기록이 빠진다
+            db.collection("ranking").where("id", "==", id).orderBy("created_at", "desc").limit(500).get().then((querySnapshot) => {
+                const entries = [];
+                querySnapshot.forEach((doc) => {
+                    const data = doc.data();
+                    if (isValidObject(data)) {
+                        entries.push(toRankingData(doc));
+                    }
+                });
+                resolve(entries.reverse());
+            }).catch((e) => {
+                console.error("Error read document: ", e);
+                reject(new Error("Error read document: " + e.message));
+            });
+        } catch (e) {
+            console.error("Error read document: ", e);
+            reject(new Error("Error read document"));
+        }
+    })
+}
+
+// 더 빠른 기록의 개수를 서버에서 세고, count()가 없는 SDK에서는 직접 읽어서 센다
+function countFaster(query) {
+    if (typeof query.count === 'function') {
+        return query.count().get().then((snapshot) => snapshot.data().count);
+    }
+    return query.get().then((querySnapshot) => querySnapshot.size);
+}
+
+window.read_ranking_best_js = function(difficulty, verifiedOnly, id) {
+    return new Promise((resolve, reject) => {
+        try {
+            rankingQuery(difficulty, verifiedOnly).where("id", "==", id).orderBy("time").orderBy("created_at").orderBy(firebase.firestore.FieldPath.documentId()).limit(1).get().then((querySnapshot) => {
+                const doc = querySnapshot.docs.find((doc) => isValidObject(doc.data()));
+                if (!doc) {
+                    resolve(null);
+                    return;
+                }
+
+                const entry = toRankingData(doc);
+                // 같은 기록은 페이지와 같이 먼저 등록된 기록이, 같은 초면 문서 id가 작은 기록이 앞선다
+                const createdAt = new firebase.firestore.Timestamp(entry.created_at, 0);
+                const tied = rankingQuery(difficulty, verifiedOnly).where("time", "==", entry.time);
+                return Promise.all([
+                    countFaster(rankingQuery(difficulty, verifiedOnly).where("time", "<", entry.time)),
+                    countFaster(tied.where("created_at", "<", createdAt)),
+                    countFaster(tied.where("created_at", "==", createdAt).where(firebase.firestore.FieldPath.documentId(), "<", doc.id)),
+                ]).then(([faster, earlier, sameSecond]) => {
+                    resolve({ entry: entry, rank: faster + earlier + sameSecond + 1 });
+                });
+            }).catch((e) => {
+                console.error("Error read document: ", e);
+                reject(new Error("Error read document: " + e.message));
+            });
+        } catch (e) {
+            console.error("Error read document: ", e);
+            reject(new Error("Error read document"));
+        }
+    })
+}
+
+
+// 랭킹 창이 열려 있는 동안의 리스너 (listenId => 해제 함수)
+const rankingListeners = {};
+
+window.listen_ranking_js = function(listenId, difficulty, verifiedOnly, onChange) {
+    return new Promise((resolve, reject) => {
+        try {
+            // 지금부터 추가되는 기록만 받는다
+            const since = firebase.firestore.Timestamp.now();
+            const unsubscribe = rankingQuery(difficulty, verifiedOnly).where("created_at", ">=", since).onSnapshot((querySnapshot) => {
+                querySnapshot.docChanges().forEach((change) => {
+                    if (change.type === "removed") {
+                        return;
+                    }
+                    // 서버 시간이 아직 없는 로컬 쓰기는 확정된 뒤 modified로 다시 온다
+                    const data = change.doc.data();
+                    if (isValidObject(data)) {
+                        onChange(toRankingData(change.doc));
+                    }
+                });
+            }, (error) => {
+                console.error("Error listening to ranking:", error);
+                delete rankingListeners[listenId];
+                reject(new Error("Error listening to ranking: " + error.message));
+            });
+            rankingListeners[listenId] = () => {
+                unsubscribe();
+                resolve();
+            };
+        } catch (e) {
+            console.error("Error listening to ranking:", e);
+            reject(new Error("Error listening to ranking"));
+        }
+    });
+}
+
+window.unlisten_ranking_js = function(listenId) {
+    const stop = rankingListeners[listenId];
+    if (stop) {
+        delete rankingListeners[listenId];
+        stop();
+    }
+}
+
+
+window.load_storage_js = function(key) {
+    return window.localStorage.getItem(key);
+}
+
+window.save_storage_js = function(key, value) {
+    try {
+        window.localStorage.setItem(key, value);
+        return true;
+    } catch (e) {
+        console.error("Error write storage: ", e);
+        return false;
+    }
+}
+
+window.remove_storage_js = function(key) {
+    window.localStorage.removeItem(key);
+}
+
+
+const sockets = new Map();
+let nextSocketHandle = 1;
+
+window.socket_connect_js = function(url, onOpen, onMessage, onClose) {
+    const handle = nextSocketHandle++;
+    const socket = new WebSocket(url);
+    socket.onopen = () => onOpen();
+    socket.onmessage = (event) => onMessage(event.data);
+    socket.onclose = () => {
+        sockets.delete(handle);
+        onClose();
+    };
+    socket.onerror = (error) => {
+        console.error("WebSocket error:", error);
+    };
+    sockets.set(handle, socket);
+    return handle;
+}
+
+window.socket_send_js = function(handle, text) {
+    const socket = sockets.get(handle);
+    if (socket && socket.readyState === WebSocket.OPEN) {
+        socket.send(text);
+    }
+}
+
+window.socket_close_js = function(handle) {
+    const socket = sockets.get(handle);
+    if (socket) {
+        // the Rust callbacks are dropped together with the connection
+        socket.onopen = null;
+        socket.onmessage = null;
+        socket.onclose = null;
+        socket.close();
+        sockets.delete(handle);
+    }
+}
+
 
 main();
\ No newline at end of file
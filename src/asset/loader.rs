@@ -1,19 +1,22 @@
 use bevy::{prelude::*, utils::HashMap};
+use super::skin::{self, LoadedSkin, SkinImage, SkinResource};
 use super::texture_type::TextureType;
 use strum::IntoEnumIterator;
 
+use crate::system::settings::Settings;
+
 #[derive(Resource, Default)]
 pub struct TextureAtlasResource {
     pub handles: HashMap<TextureType, Handle<TextureAtlas>>,
 }
 
-struct AtlasOptions {
-    width: f32,
-    height: f32,
-    columns: u32,
-    rows: u32,
-    horizontal_space: f32,
-    vertical_gap: f32,
+pub struct AtlasOptions {
+    pub width: f32,
+    pub height: f32,
+    pub columns: u32,
+    pub rows: u32,
+    pub horizontal_space: f32,
+    pub vertical_gap: f32,
 }
 
 impl Default for AtlasOptions {
@@ -29,6 +32,14 @@ impl Default for AtlasOptions {
     }
 }
 
+impl AtlasOptions {
+    /// The geometry of the classic skin.
+    pub fn classic(texture_type: TextureType) -> Self {
+        let (width, height, columns, rows, horizontal_space, vertical_gap) = texture_type.get_texture_size();
+        Self { width, height, columns, rows, horizontal_space, vertical_gap }
+    }
+}
+
 fn load_texture_as_atlas(handle: Handle<Image>,
     option: AtlasOptions) -> TextureAtlas {
     let mut texture_atlas = TextureAtlas::new_empty(handle, Vec2::new(option.width, option.height));
@@ -51,44 +62,79 @@ fn load_texture_as_atlas(handle: Handle<Image>,
     texture_atlas
 }
 
+// Replaces the atlas behind the handle, so the sprites already spawned show the new skin.
 fn load_texture(
     texture_type: TextureType,
+    texture_handle: Handle<Image>,
     option: AtlasOptions,
-    asset_server: &AssetServer,
     texture_atlases: &mut Assets<TextureAtlas>,
     texture_atlas_resource: &mut TextureAtlasResource
 ) {
-    let texture_handle = asset_server.load(texture_type.get_path());
     let texture_atlas = load_texture_as_atlas(texture_handle, option);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    texture_atlas_resource.handles.insert(texture_type, texture_atlas_handle);
+    match texture_atlas_resource.handles.get(&texture_type) {
+        Some(texture_atlas_handle) => texture_atlases.set_untracked(texture_atlas_handle, texture_atlas),
+        None => {
+            let texture_atlas_handle = texture_atlases.add(texture_atlas);
+            texture_atlas_resource.handles.insert(texture_type, texture_atlas_handle);
+        },
+    }
 }
 
-fn load_texture_with_type(
+/// Loads the skin into the atlases. Atlases the skin leaves out or gets wrong, and every atlas
+/// of a skin that cannot be read, fall back to the classic images.
+pub fn load_skin(
+    id: &str,
     asset_server: &AssetServer,
+    images: &mut Assets<Image>,
     texture_atlases: &mut Assets<TextureAtlas>,
     texture_atlas_resource: &mut TextureAtlasResource,
-    texture_type: TextureType
+    skin_resource: &mut SkinResource,
 ) {
-    let width = texture_type.get_texture_size().0;
-    let height = texture_type.get_texture_size().1;
-    let columns = texture_type.get_texture_size().2;
-    let rows = texture_type.get_texture_size().3;
-    let horizontal_space = texture_type.get_texture_size().4;
-    let vertical_gap = texture_type.get_texture_size().5;
-    load_texture(texture_type, 
-        AtlasOptions { width, height, columns, rows, horizontal_space, vertical_gap}, 
-        &asset_server, 
-        texture_atlases, 
-        texture_atlas_resource);
+    let skin = if id == skin::DEFAULT_SKIN {
+        Ok(LoadedSkin::default())
+    } else {
+        skin::platform::load(id)
+    };
+    let mut skin = skin.unwrap_or_else(|e| LoadedSkin {
+        errors: vec![e],
+        ..default()
+    });
+    for e in skin.errors.iter() {
+        warn!("{}: {}", id, e);
+    }
+
+    for texture_type in TextureType::iter() {
+        let index = skin.atlases.iter().position(|(atlas_type, _, _)| *atlas_type == texture_type);
+        let (texture_handle, option) = match index.map(|index| skin.atlases.swap_remove(index)) {
+            Some((_, SkinImage::Loaded(image), option)) => (images.add(image), option),
+            Some((_, SkinImage::Path(path), option)) => (asset_server.load(path), option),
+            None => (asset_server.load(texture_type.get_path()), AtlasOptions::classic(texture_type)),
+        };
+        load_texture(texture_type, texture_handle, option, texture_atlases, texture_atlas_resource);
+    }
+
+    skin_resource.current = id.to_string();
+    skin_resource.stamp = skin::platform::get_stamp(id);
+    skin_resource.errors = skin.errors;
+}
+
+/// A sprite drawn at the size of the classic tile, whatever the size of the skin's.
+pub fn new_sprite(texture_type: TextureType, index: usize) -> TextureAtlasSprite {
+    let (width, height, _, _, _, _) = texture_type.get_texture_size();
+    TextureAtlasSprite {
+        custom_size: Some(Vec2::new(width, height)),
+        ..TextureAtlasSprite::new(index)
+    }
 }
 
 pub fn setup(
     asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut texture_atlas_resource: ResMut<TextureAtlasResource>,
+    mut skin_resource: ResMut<SkinResource>,
+    settings: Res<Settings>,
 ) {
-    for texture_type in TextureType::iter() {
-        load_texture_with_type(&asset_server, &mut texture_atlases, &mut texture_atlas_resource, texture_type);
-    }
-}
\ No newline at end of file
+    skin_resource.skins = skin::platform::list();
+    load_skin(&settings.skin, &asset_server, &mut images, &mut texture_atlases, &mut texture_atlas_resource, &mut skin_resource);
+}
@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::loader::AtlasOptions;
+use super::texture_type::TextureType;
+use crate::utils::yaml;
+
+/// The skin built from `assets/images`, picked when no other is.
+pub const DEFAULT_SKIN: &str = "classic";
+const DEFAULT_SKIN_NAME: &str = "Classic";
+const MANIFEST_FILE: &str = "skin.yaml";
+
+/// The `skin.yaml` of a skin pack, next to its images:
+/// ```yaml
+/// name: Windows XP
+/// atlases:
+///   cells:
+///     path: cells.png
+///     tile_width: 16
+///     tile_height: 16
+///     columns: 8
+///     rows: 2
+///     horizontal_space: 1
+///     vertical_gap: 1
+/// ```
+/// The keys are the names of `TextureType::get_name`. Atlases left out keep the classic images.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkinManifest {
+    pub name: String,
+    #[serde(default)]
+    pub atlases: BTreeMap<String, AtlasManifest>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AtlasManifest {
+    /// Relative to the manifest.
+    pub path: String,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    #[serde(default = "default_count")]
+    pub columns: u32,
+    #[serde(default = "default_count")]
+    pub rows: u32,
+    #[serde(default)]
+    pub horizontal_space: f32,
+    #[serde(default)]
+    pub vertical_gap: f32,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+impl AtlasManifest {
+    pub fn get_options(&self) -> AtlasOptions {
+        AtlasOptions {
+            width: self.tile_width,
+            height: self.tile_height,
+            columns: self.columns,
+            rows: self.rows,
+            horizontal_space: self.horizontal_space,
+            vertical_gap: self.vertical_gap,
+        }
+    }
+
+    /// The smallest image that holds every tile.
+    pub fn get_image_size(&self) -> Vec2 {
+        Vec2::new(
+            self.columns as f32 * self.tile_width + self.columns.saturating_sub(1) as f32 * self.horizontal_space,
+            self.rows as f32 * self.tile_height + self.rows.saturating_sub(1) as f32 * self.vertical_gap,
+        )
+    }
+}
+
+impl SkinManifest {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        yaml::parse_yaml::<SkinManifest>(text).map_err(|e| format!("{}: {}", MANIFEST_FILE, e))
+    }
+
+    /// The atlases that can replace the classic ones. The others are reported, and keep theirs.
+    pub fn get_atlases(&self) -> (Vec<(TextureType, &AtlasManifest)>, Vec<String>) {
+        let mut atlases = Vec::new();
+        let mut errors = Vec::new();
+        for (name, atlas) in self.atlases.iter() {
+            let Some(texture_type) = TextureType::from_name(name) else {
+                errors.push(format!("{}: unknown atlas", name));
+                continue;
+            };
+            // The sprites pick their tiles by index, e.g. `CellType`.
+            let (_, _, columns, rows, _, _) = texture_type.get_texture_size();
+            if atlas.tile_width <= 0.0 || atlas.tile_height <= 0.0 {
+                errors.push(format!("{}: the tiles have no size", name));
+                continue;
+            }
+            if atlas.horizontal_space < 0.0 || atlas.vertical_gap < 0.0 {
+                errors.push(format!("{}: negative spacing", name));
+                continue;
+            }
+            // A manifest may give any count, so the product may not fit.
+            match atlas.columns.checked_mul(atlas.rows) {
+                None => errors.push(format!("{}: {}x{} tiles, too many", name, atlas.columns, atlas.rows)),
+                Some(tiles) if tiles < columns * rows => {
+                    errors.push(format!("{}: {} tiles, expected at least {}", name, tiles, columns * rows));
+                },
+                Some(_) => atlases.push((texture_type, atlas)),
+            }
+        }
+        (atlases, errors)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkinEntry {
+    /// The directory or zip in `assets/skins`, without `.zip`, and the key kept in the settings.
+    pub id: String,
+    pub name: String,
+}
+
+impl SkinEntry {
+    pub fn classic() -> Self {
+        Self {
+            id: DEFAULT_SKIN.to_string(),
+            name: DEFAULT_SKIN_NAME.to_string(),
+        }
+    }
+}
+
+pub enum SkinImage {
+    /// Read and checked against the manifest.
+    Loaded(Image),
+    /// Left to the asset server.
+    Path(String),
+}
+
+/// What `platform::load` found of a skin. Errors name the atlases that keep the classic images.
+#[derive(Default)]
+pub struct LoadedSkin {
+    pub atlases: Vec<(TextureType, SkinImage, AtlasOptions)>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Resource)]
+pub struct SkinResource {
+    /// The skins offered in the menu, the classic one first.
+    pub skins: Vec<SkinEntry>,
+    /// The skin in the atlases.
+    pub current: String,
+    /// When the files of the current skin last changed, to reload it while it is edited.
+    pub stamp: Option<SystemTime>,
+    pub errors: Vec<String>,
+}
+
+impl Default for SkinResource {
+    fn default() -> Self {
+        Self {
+            skins: vec![SkinEntry::classic()],
+            current: DEFAULT_SKIN.to_string(),
+            stamp: None,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl SkinResource {
+    pub fn get_name(&self, id: &str) -> String {
+        self.skins.iter()
+            .find(|skin| skin.id == id)
+            .map(|skin| skin.name.clone())
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+/// Skins are directories or zips in `assets/skins`, read by hand so that zips work, and images
+/// larger than the classic ones are checked against their manifest.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod platform {
+    use std::fs;
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+    use bevy::render::texture::{CompressedImageFormats, ImageType};
+
+    use super::*;
+
+    const SKINS_PATH: &str = "./assets/skins/";
+
+    enum Source {
+        Directory(PathBuf),
+        Zip(PathBuf),
+    }
+
+    impl Source {
+        fn find(id: &str) -> Option<Self> {
+            let directory = Path::new(SKINS_PATH).join(id);
+            let zip = Path::new(SKINS_PATH).join(format!("{}.zip", id));
+            if directory.join(MANIFEST_FILE).is_file() {
+                Some(Source::Directory(directory))
+            } else if zip.is_file() {
+                Some(Source::Zip(zip))
+            } else {
+                None
+            }
+        }
+
+        fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+            match self {
+                Source::Directory(directory) => {
+                    let path = directory.join(name);
+                    fs::read(&path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))
+                },
+                Source::Zip(path) => {
+                    let file = fs::File::open(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+                    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+                    let full_name = get_zip_prefix(&archive) + name;
+                    let mut entry = archive.by_name(&full_name).map_err(|e| format!("{}: {}: {}", path.display(), name, e))?;
+                    let mut bytes = Vec::new();
+                    entry.read_to_end(&mut bytes).map_err(|e| format!("{}: {}: {}", path.display(), name, e))?;
+                    Ok(bytes)
+                },
+            }
+        }
+
+        // The newest of the manifest and the images, or of the zip.
+        fn get_stamp(&self) -> Option<SystemTime> {
+            match self {
+                // The directory itself changes when a file is removed.
+                Source::Directory(directory) => fs::read_dir(directory).ok()?
+                    .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+                    .chain(fs::metadata(directory).ok()?.modified().ok())
+                    .max(),
+                Source::Zip(path) => fs::metadata(path).ok()?.modified().ok(),
+            }
+        }
+    }
+
+    // A zip of the skin directory holds its files in that directory.
+    fn get_zip_prefix(archive: &zip::ZipArchive<fs::File>) -> String {
+        archive.file_names()
+            .filter_map(|name| name.strip_suffix(MANIFEST_FILE))
+            .filter(|prefix| prefix.is_empty() || prefix.ends_with('/'))
+            .min_by_key(|prefix| prefix.len())
+            .unwrap_or("")
+            .to_string()
+    }
+
+    fn read_manifest(source: &Source) -> Result<SkinManifest, String> {
+        let bytes = source.read(MANIFEST_FILE)?;
+        SkinManifest::parse(&String::from_utf8_lossy(&bytes))
+    }
+
+    /// The classic skin and those in `assets/skins`, by id.
+    pub fn list() -> Vec<SkinEntry> {
+        let mut skins = Vec::new();
+        for entry in fs::read_dir(SKINS_PATH).into_iter().flatten().flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let id = file_name.strip_suffix(".zip").unwrap_or(&file_name).to_string();
+            if id == DEFAULT_SKIN || skins.iter().any(|skin: &SkinEntry| skin.id == id) {
+                continue;
+            }
+            let Some(source) = Source::find(&id) else {
+                continue;
+            };
+            // A skin whose manifest is being fixed stays in the menu.
+            let name = read_manifest(&source).map(|manifest| manifest.name).unwrap_or_else(|_| id.clone());
+            skins.push(SkinEntry { id, name });
+        }
+        skins.sort_by(|a, b| a.id.cmp(&b.id));
+        skins.insert(0, SkinEntry::classic());
+        skins
+    }
+
+    pub fn load(id: &str) -> Result<LoadedSkin, String> {
+        let source = Source::find(id).ok_or_else(|| format!("Skin {} not found in {}", id, SKINS_PATH))?;
+        let manifest = read_manifest(&source)?;
+        let (atlases, errors) = manifest.get_atlases();
+        let mut skin = LoadedSkin {
+            errors,
+            ..default()
+        };
+        for (texture_type, atlas) in atlases {
+            let image = source.read(&atlas.path).and_then(|bytes| {
+                let extension = Path::new(&atlas.path).extension().and_then(|e| e.to_str()).unwrap_or("png");
+                Image::from_buffer(&bytes, ImageType::Extension(extension), CompressedImageFormats::NONE, true)
+                    .map_err(|e| format!("{}: {}", atlas.path, e))
+            });
+            let image = match image {
+                Ok(image) => image,
+                Err(e) => {
+                    skin.errors.push(format!("{}: {}", texture_type.get_name(), e));
+                    continue;
+                },
+            };
+            let (size, expected) = (image.size(), atlas.get_image_size());
+            if size.x < expected.x || size.y < expected.y {
+                skin.errors.push(format!("{}: {} is {}x{}, expected at least {}x{}",
+                    texture_type.get_name(), atlas.path, size.x, size.y, expected.x, expected.y));
+                continue;
+            }
+            skin.atlases.push((texture_type, SkinImage::Loaded(image), atlas.get_options()));
+        }
+        Ok(skin)
+    }
+
+    pub fn get_stamp(id: &str) -> Option<SystemTime> {
+        Source::find(id)?.get_stamp()
+    }
+
+    /// Changes when a skin is added to `assets/skins`, removed or renamed.
+    pub fn get_list_stamp() -> Option<SystemTime> {
+        fs::metadata(SKINS_PATH).ok()?.modified().ok()
+    }
+}
+
+/// The web build offers the skins shipped with it, and its images come from the asset server.
+#[cfg(target_arch = "wasm32")]
+pub mod platform {
+    use super::*;
+
+    const SKINS: [(&str, &str); 3] = [
+        ("dark", include_str!("../../assets/skins/dark/skin.yaml")),
+        ("high_contrast", include_str!("../../assets/skins/high_contrast/skin.yaml")),
+        ("xp", include_str!("../../assets/skins/xp/skin.yaml")),
+    ];
+
+    pub fn list() -> Vec<SkinEntry> {
+        let mut skins = vec![SkinEntry::classic()];
+        for (id, text) in SKINS {
+            let name = SkinManifest::parse(text).map(|manifest| manifest.name).unwrap_or_else(|_| id.to_string());
+            skins.push(SkinEntry {
+                id: id.to_string(),
+                name,
+            });
+        }
+        skins
+    }
+
+    pub fn load(id: &str) -> Result<LoadedSkin, String> {
+        let (_, text) = SKINS.iter().find(|(skin, _)| *skin == id).ok_or_else(|| format!("Skin {} not found", id))?;
+        let manifest = SkinManifest::parse(text)?;
+        let (atlases, errors) = manifest.get_atlases();
+        Ok(LoadedSkin {
+            atlases: atlases.into_iter()
+                .map(|(texture_type, atlas)| {
+                    let path = format!("skins/{}/{}", id, atlas.path);
+                    (texture_type, SkinImage::Path(path), atlas.get_options())
+                })
+                .collect(),
+            errors,
+        })
+    }
+
+    pub fn get_stamp(_id: &str) -> Option<SystemTime> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_errors(atlas: &str) -> Vec<String> {
+        let manifest = SkinManifest::parse(&format!("name: Test\natlases:\n  cells:\n{}", atlas)).unwrap();
+        manifest.get_atlases().1
+    }
+
+    #[test]
+    fn atlases_with_too_few_or_too_many_tiles_are_reported() {
+        let atlas = "    path: cells.png\n    tile_width: 16\n    tile_height: 16\n";
+        assert!(get_errors(&format!("{}    columns: 8\n    rows: 2\n", atlas)).is_empty());
+        assert_eq!(get_errors(&format!("{}    columns: 8\n", atlas)), vec!["cells: 8 tiles, expected at least 16"]);
+        assert_eq!(
+            get_errors(&format!("{}    columns: 65536\n    rows: 65536\n", atlas)),
+            vec!["cells: 65536x65536 tiles, too many"],
+        );
+    }
+}
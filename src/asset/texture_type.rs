@@ -1,6 +1,7 @@
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[derive(Hash, PartialEq, Eq, EnumIter)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EnumIter)]
 pub enum TextureType {
     /* bases */
     Cells,
@@ -28,6 +29,36 @@ pub enum TextureType {
 }
 
 impl TextureType {
+    /// The key of the atlas in a skin manifest.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            TextureType::Cells => "cells",
+            TextureType::Smiles => "smiles",
+            TextureType::Numbers => "numbers",
+            TextureType::CornerLeftBottom => "corner_left_bottom",
+            TextureType::CornerLeftTop => "corner_left_top",
+            TextureType::CornerRightBottom => "corner_right_bottom",
+            TextureType::CornerRightTop => "corner_right_top",
+            TextureType::CornerLeftUpperTop => "corner_left_upper_top",
+            TextureType::CornerRightUpperTop => "corner_right_upper_top",
+            TextureType::EdgeLeft => "edge_left",
+            TextureType::EdgeRight => "edge_right",
+            TextureType::EdgeTop => "edge_top",
+            TextureType::EdgeBottom => "edge_bottom",
+            TextureType::EdgeLeftUpper => "edge_left_upper",
+            TextureType::EdgeRightUpper => "edge_right_upper",
+            TextureType::EdgeTopUpper => "edge_top_upper",
+            TextureType::Background => "background",
+            TextureType::Number => "number",
+            TextureType::Smile => "smile",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        TextureType::iter().find(|texture_type| texture_type.get_name() == name)
+    }
+
+    /// The image of the classic skin.
     pub fn get_path(&self) -> &str {
         match self {
             TextureType::Cells => "images/cells.png",
@@ -52,6 +83,8 @@ impl TextureType {
         }
     }
 
+    /// Width, height, columns, rows and the spaces between the tiles of the classic skin. The
+    /// board is laid out with these, whatever the skin, so its tiles are scaled to fit.
     pub fn get_texture_size(&self) -> (f32, f32, u32, u32, f32, f32) {
         match self {
             TextureType::Cells => (16.0, 16.0, 8, 2, 1.0, 1.0),
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use minesweeper::core::rules::{self, Action, Board, Outcome, TileState};
+use minesweeper::net::protocol::{self, ClientMessage, ServerMessage, PlayerInfo, PlayerStatus, StreamFrame, TileUpdate, DEFAULT_ADDRESS};
+use minesweeper::net::socket::platform::{pump, set_read_timeout};
+
+// The latest board of a streaming player, sent whole to spectators who join mid-game.
+struct StreamCache {
+    difficulty: String,
+    time_ms: u64,
+    status: PlayerStatus,
+    tiles: HashMap<(u32, u32), TileUpdate>,
+}
+
+impl StreamCache {
+    fn new() -> Self {
+        Self {
+            difficulty: String::new(),
+            time_ms: 0,
+            status: PlayerStatus::Waiting,
+            tiles: HashMap::new(),
+        }
+    }
+
+    fn update(&mut self, frame: &StreamFrame) {
+        if frame.is_reset {
+            self.tiles.clear();
+        }
+        self.difficulty = frame.difficulty.clone();
+        self.time_ms = frame.time_ms;
+        self.status = frame.status;
+        for tile in frame.tiles.iter() {
+            self.tiles.insert((tile.x, tile.y), tile.clone());
+        }
+    }
+
+    fn get_snapshot(&self) -> StreamFrame {
+        StreamFrame {
+            difficulty: self.difficulty.clone(),
+            is_reset: true,
+            time_ms: self.time_ms,
+            status: self.status,
+            tiles: self.tiles.values().cloned().collect(),
+        }
+    }
+}
+
+struct Client {
+    info: PlayerInfo,
+    sender: Sender<String>,
+    watching: Option<u32>,
+    stream: Option<StreamCache>,
+}
+
+// The relay owns the only real board of a co-op game; clients just draw the tiles it sends.
+struct CoopGame {
+    board: Board,
+    flag_owners: HashMap<(u32, u32), u32>,
+}
+
+impl CoopGame {
+    fn get_tile_update(&self, x: u32, y: u32) -> TileUpdate {
+        let state = self.board.state(x, y);
+        let is_shown = (state != TileState::Hidden && state != TileState::Flagged)
+            || self.board.outcome() != Outcome::Playing;
+        TileUpdate {
+            x,
+            y,
+            state,
+            is_mine: is_shown && self.board.is_mine(x, y),
+            num_mines_around: if is_shown { self.board.get_num_mines_around(x, y) } else { 0 },
+            flagged_by: if state == TileState::Flagged { self.flag_owners.get(&(x, y)).copied() } else { None },
+        }
+    }
+
+    fn get_positions(&self) -> Vec<(u32, u32)> {
+        let width = self.board.width;
+        (1..=self.board.height)
+            .flat_map(|y| (1..=width).map(move |x| (x, y)))
+            .collect()
+    }
+
+    fn get_tiles(&self) -> Vec<TileState> {
+        self.get_positions().into_iter()
+            .map(|(x, y)| self.board.state(x, y))
+            .collect()
+    }
+
+    // Actions are applied one at a time under the lobby lock, so simultaneous clicks resolve
+    // in arrival order: a reveal of a tile someone just flagged, or a flag on a tile that was
+    // just opened, is a no-op, and a flag already in the requested state is left alone.
+    fn apply(&mut self, id: u32, x: u32, y: u32, action: Action, flagged: bool) -> Vec<TileUpdate> {
+        if self.board.outcome() != Outcome::Playing || !self.board.contains(x, y) {
+            return Vec::new();
+        }
+        if action == Action::Flag && (self.board.state(x, y) == TileState::Flagged) == flagged {
+            return Vec::new();
+        }
+
+        let before = self.get_tiles();
+        let previous = self.board.state(x, y);
+        let outcome = self.board.apply(action, x, y);
+        // A flag on an opened tile changes nothing, so nobody gets credit for it.
+        let state = self.board.state(x, y);
+        if action == Action::Flag && state != previous {
+            if state == TileState::Flagged {
+                self.flag_owners.insert((x, y), id);
+            } else {
+                self.flag_owners.remove(&(x, y));
+            }
+        }
+
+        // Once the game is over every mine is sent as well so each client can show them.
+        let after = self.get_tiles();
+        let is_over = outcome != Outcome::Playing;
+        self.get_positions().into_iter()
+            .enumerate()
+            .filter(|(index, (x, y))| before[*index] != after[*index] || (is_over && self.board.is_mine(*x, *y)))
+            .map(|(_, (x, y))| self.get_tile_update(x, y))
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Lobby {
+    next_id: u32,
+    clients: Vec<Client>,
+    is_racing: bool,
+    coop: Option<CoopGame>,
+}
+
+impl Lobby {
+    fn send(&self, id: u32, message: &ServerMessage) {
+        if let Some(client) = self.clients.iter().find(|client| client.info.id == id) {
+            let _ = client.sender.send(protocol::to_text(message));
+        }
+    }
+
+    fn broadcast(&self, message: &ServerMessage) {
+        let text = protocol::to_text(message);
+        for client in &self.clients {
+            let _ = client.sender.send(text.clone());
+        }
+    }
+
+    fn broadcast_players(&self) {
+        let players = self.clients.iter().map(|client| client.info.clone()).collect();
+        self.broadcast(&ServerMessage::Players { players });
+    }
+
+    fn connect(&mut self, sender: Sender<String>) -> u32 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.clients.push(Client {
+            info: PlayerInfo {
+                id,
+                name: format!("player{}", id),
+                percent: 0.0,
+                status: PlayerStatus::Waiting,
+                is_streaming: false,
+            },
+            sender,
+            watching: None,
+            stream: None,
+        });
+        self.send(id, &ServerMessage::Welcome { id });
+        id
+    }
+
+    fn send_to_spectators(&self, id: u32, message: &ServerMessage) {
+        let text = protocol::to_text(message);
+        for client in self.clients.iter().filter(|client| client.watching == Some(id)) {
+            let _ = client.sender.send(text.clone());
+        }
+    }
+
+    fn stop_stream(&mut self, id: u32) {
+        self.send_to_spectators(id, &ServerMessage::StreamEnd { id });
+        for client in self.clients.iter_mut() {
+            if client.watching == Some(id) {
+                client.watching = None;
+            }
+            if client.info.id == id {
+                client.stream = None;
+                client.info.is_streaming = false;
+            }
+        }
+    }
+
+    fn disconnect(&mut self, id: u32) {
+        self.stop_stream(id);
+        self.clients.retain(|client| client.info.id != id);
+        if self.coop.is_some() {
+            self.broadcast(&ServerMessage::Cursor { id, x: 0, y: 0 });
+        }
+        if self.is_racing {
+            self.check_race_over(None);
+        }
+        self.broadcast_players();
+    }
+
+    fn handle(&mut self, id: u32, message: ClientMessage) {
+        match message {
+            ClientMessage::Join { name } => {
+                if let Some(client) = self.clients.iter_mut().find(|client| client.info.id == id) {
+                    client.info.name = name;
+                }
+                self.broadcast_players();
+            },
+            ClientMessage::StartRace { difficulty } => {
+                if self.is_racing {
+                    self.send(id, &ServerMessage::Error { message: "A race is already running".to_string() });
+                    return;
+                }
+                // Races are held on the standard boards only.
+                if rules::get_difficulty_size(&difficulty).is_none() {
+                    self.send(id, &ServerMessage::Error { message: format!("Unknown difficulty {}", difficulty) });
+                    return;
+                }
+                self.is_racing = true;
+                self.coop = None;
+                for client in self.clients.iter_mut() {
+                    client.info.percent = 0.0;
+                    client.info.status = PlayerStatus::Playing;
+                }
+                let seed = rand::random::<u32>() as u64;
+                println!("race start: {} seed {} with {} players", difficulty, seed, self.clients.len());
+                self.broadcast(&ServerMessage::RaceStart { difficulty, seed });
+                self.broadcast_players();
+            },
+            ClientMessage::Progress { percent, status } => {
+                if !self.is_racing {
+                    return;
+                }
+                let Some(client) = self.clients.iter_mut().find(|client| client.info.id == id) else {
+                    return;
+                };
+                if client.info.status != PlayerStatus::Playing {
+                    return;
+                }
+                client.info.percent = percent;
+                client.info.status = status;
+                self.broadcast_players();
+
+                if status == PlayerStatus::Cleared {
+                    self.check_race_over(Some(id));
+                } else {
+                    self.check_race_over(None);
+                }
+            },
+            ClientMessage::StartCoop { difficulty } => {
+                if self.is_racing {
+                    self.send(id, &ServerMessage::Error { message: "A race is already running".to_string() });
+                    return;
+                }
+                let Some((width, height, num_mines)) = rules::get_board_size(&difficulty) else {
+                    self.send(id, &ServerMessage::Error { message: format!("Unknown difficulty {}", difficulty) });
+                    return;
+                };
+                let seed = rand::random::<u32>() as u64;
+                self.coop = Some(CoopGame {
+                    board: Board::new(width, height, num_mines, seed),
+                    flag_owners: HashMap::new(),
+                });
+                println!("co-op start: {} seed {} with {} players", difficulty, seed, self.clients.len());
+                self.broadcast(&ServerMessage::CoopStart { difficulty, seed });
+            },
+            ClientMessage::Action { x, y, action, flagged } => {
+                let Some(coop) = self.coop.as_mut() else {
+                    return;
+                };
+                let tiles = coop.apply(id, x, y, action, flagged);
+                if tiles.is_empty() {
+                    return;
+                }
+                let outcome = coop.board.outcome();
+                self.broadcast(&ServerMessage::Tiles { tiles });
+                if outcome != Outcome::Playing {
+                    println!("co-op over: {:?} by {}", outcome, id);
+                    self.broadcast(&ServerMessage::CoopOver { is_win: outcome == Outcome::Win, by: id });
+                }
+            },
+            ClientMessage::Stream { frame } => {
+                let Some(client) = self.clients.iter_mut().find(|client| client.info.id == id) else {
+                    return;
+                };
+                client.stream.get_or_insert_with(StreamCache::new).update(&frame);
+                let is_new = !client.info.is_streaming;
+                client.info.is_streaming = true;
+                self.send_to_spectators(id, &ServerMessage::Stream { id, frame });
+                if is_new {
+                    self.broadcast_players();
+                }
+            },
+            ClientMessage::StopStream => {
+                self.stop_stream(id);
+                self.broadcast_players();
+            },
+            ClientMessage::Spectate { id: target } => {
+                let snapshot = target
+                    .and_then(|target| self.clients.iter().find(|client| client.info.id == target))
+                    .and_then(|client| client.stream.as_ref())
+                    .map(|stream| stream.get_snapshot());
+                if let Some(client) = self.clients.iter_mut().find(|client| client.info.id == id) {
+                    client.watching = target;
+                }
+                if let (Some(target), Some(frame)) = (target, snapshot) {
+                    self.send(id, &ServerMessage::Stream { id: target, frame });
+                }
+            },
+            ClientMessage::Cursor { x, y } => {
+                if self.coop.is_none() {
+                    return;
+                }
+                let text = protocol::to_text(&ServerMessage::Cursor { id, x, y });
+                for client in self.clients.iter().filter(|client| client.info.id != id) {
+                    let _ = client.sender.send(text.clone());
+                }
+            },
+        }
+    }
+
+    // The first player to clear wins; otherwise the race ends with the last survivor.
+    fn check_race_over(&mut self, cleared: Option<u32>) {
+        let winner = if cleared.is_some() {
+            cleared
+        } else {
+            let survivors: Vec<&Client> = self.clients.iter()
+                .filter(|client| client.info.status == PlayerStatus::Playing)
+                .collect();
+            match survivors.len() {
+                0 => None,
+                1 if self.clients.len() > 1 => Some(survivors[0].info.id),
+                _ => return,
+            }
+        };
+
+        self.is_racing = false;
+        for client in self.clients.iter_mut() {
+            if client.info.status == PlayerStatus::Playing {
+                client.info.status = PlayerStatus::Waiting;
+            }
+        }
+        println!("race over: winner {:?}", winner);
+        self.broadcast(&ServerMessage::RaceOver { winner });
+    }
+}
+
+fn handle_connection(stream: TcpStream, lobby: Arc<Mutex<Lobby>>) {
+    if let Err(e) = set_read_timeout(&stream) {
+        eprintln!("unable to set read timeout: {}", e);
+        return;
+    }
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("handshake failed: {}", e);
+            return;
+        }
+    };
+
+    let (sender, receiver) = channel::<String>();
+    let id = lobby.lock().unwrap().connect(sender);
+    println!("player {} connected", id);
+
+    let result = pump(&mut socket, &receiver, |text| {
+        match protocol::from_text::<ClientMessage>(&text) {
+            Some(message) => lobby.lock().unwrap().handle(id, message),
+            None => eprintln!("invalid message from {}: {}", id, text),
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("player {} error: {}", id, e);
+    }
+
+    lobby.lock().unwrap().disconnect(id);
+    println!("player {} disconnected", id);
+}
+
+fn main() {
+    let address = std::env::args().nth(1).unwrap_or(DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).expect("Unable to bind relay address");
+    println!("relay listening on ws://{}", address);
+
+    let lobby = Arc::new(Mutex::new(Lobby::default()));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let lobby = lobby.clone();
+                thread::spawn(move || handle_connection(stream, lobby));
+            },
+            Err(e) => eprintln!("accept failed: {}", e),
+        }
+    }
+}
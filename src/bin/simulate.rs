@@ -0,0 +1,196 @@
+use std::process;
+use std::time::Instant;
+use serde::Serialize;
+
+use minesweeper::core::player::{Player, SolverBot, VisibleBoard};
+use minesweeper::core::rules::{self, Board, Outcome};
+
+const DIFFICULTIES: [&str; 3] = ["Easy", "Normal", "Hard"];
+const USAGE: &str = "usage: simulate [--games N] [--difficulty Easy|Normal|Hard|All] [--seed N] [--format csv|json]";
+
+struct Options {
+    games: u32,
+    difficulties: Vec<String>,
+    seed: u64,
+    format: String,
+}
+
+struct GameResult {
+    is_win: bool,
+    num_guesses: u32,
+    num_moves: u32,
+    bbbv: u32,
+    time_us: u64,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    difficulty: String,
+    games: u32,
+    wins: u32,
+    win_rate: f64,
+    guesses_per_game: f64,
+    guessed_game_rate: f64,
+    avg_3bv: f64,
+    avg_moves: f64,
+    time_us_mean: f64,
+    time_us_p50: u64,
+    time_us_p90: u64,
+    time_us_p99: u64,
+    time_us_max: u64,
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        games: 1000,
+        difficulties: DIFFICULTIES.iter().map(|difficulty| difficulty.to_string()).collect(),
+        seed: 0,
+        format: "csv".to_string(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            "--games" | "--difficulty" | "--seed" | "--format" => {},
+            _ => exit_with_usage(&format!("unknown option {}", arg)),
+        }
+        let Some(value) = args.next() else {
+            exit_with_usage(&format!("missing value for {}", arg));
+        };
+        match arg.as_str() {
+            "--games" => {
+                options.games = value.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid game count {}", value)));
+            },
+            "--difficulty" => {
+                // The last one given wins, `all` included.
+                options.difficulties = if value.eq_ignore_ascii_case("all") {
+                    DIFFICULTIES.iter().map(|difficulty| difficulty.to_string()).collect()
+                } else {
+                    let Some(difficulty) = DIFFICULTIES.iter().find(|difficulty| difficulty.eq_ignore_ascii_case(&value)) else {
+                        exit_with_usage(&format!("unknown difficulty {}", value));
+                    };
+                    vec![difficulty.to_string()]
+                };
+            },
+            "--seed" => {
+                options.seed = value.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid seed {}", value)));
+            },
+            "--format" => {
+                if value != "csv" && value != "json" {
+                    exit_with_usage(&format!("unknown format {}", value));
+                }
+                options.format = value;
+            },
+            _ => unreachable!(),
+        }
+    }
+    options
+}
+
+// Board applies the same placement and reveal rules as the game, so the numbers match real play.
+fn play_game(width: u32, height: u32, num_mines: u32, seed: u64) -> GameResult {
+    let start = Instant::now();
+    let mut board = Board::new(width, height, num_mines, seed);
+    let mut player = SolverBot::new(seed);
+    let mut num_guesses = 0;
+    let mut num_moves = 0;
+
+    while board.outcome() == Outcome::Playing {
+        let Some(player_move) = player.next_move(&VisibleBoard::from_board(&board)) else {
+            break;
+        };
+        if player_move.is_guess {
+            num_guesses += 1;
+        }
+        num_moves += 1;
+        board.apply(player_move.action, player_move.x, player_move.y);
+    }
+
+    GameResult {
+        is_win: board.outcome() == Outcome::Win,
+        num_guesses,
+        num_moves,
+        bbbv: board.get_3bv(),
+        time_us: start.elapsed().as_micros() as u64,
+    }
+}
+
+fn get_percentile(sorted: &[u64], percentile: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted[(sorted.len() - 1) * percentile / 100]
+}
+
+fn summarize(difficulty: &str, results: &[GameResult]) -> Summary {
+    let games = results.len() as u32;
+    let count = games.max(1) as f64;
+    let wins = results.iter().filter(|result| result.is_win).count() as u32;
+    let mut times: Vec<u64> = results.iter().map(|result| result.time_us).collect();
+    times.sort_unstable();
+
+    Summary {
+        difficulty: difficulty.to_string(),
+        games,
+        wins,
+        win_rate: wins as f64 / count,
+        guesses_per_game: results.iter().map(|result| result.num_guesses as f64).sum::<f64>() / count,
+        guessed_game_rate: results.iter().filter(|result| result.num_guesses > 0).count() as f64 / count,
+        avg_3bv: results.iter().map(|result| result.bbbv as f64).sum::<f64>() / count,
+        avg_moves: results.iter().map(|result| result.num_moves as f64).sum::<f64>() / count,
+        time_us_mean: times.iter().sum::<u64>() as f64 / count,
+        time_us_p50: get_percentile(&times, 50),
+        time_us_p90: get_percentile(&times, 90),
+        time_us_p99: get_percentile(&times, 99),
+        time_us_max: times.last().copied().unwrap_or(0),
+    }
+}
+
+fn print_csv(summaries: &[Summary]) {
+    println!("difficulty,games,wins,win_rate,guesses_per_game,guessed_game_rate,avg_3bv,avg_moves,time_us_mean,time_us_p50,time_us_p90,time_us_p99,time_us_max");
+    for summary in summaries {
+        println!("{},{},{},{:.4},{:.4},{:.4},{:.2},{:.2},{:.1},{},{},{},{}",
+            summary.difficulty,
+            summary.games,
+            summary.wins,
+            summary.win_rate,
+            summary.guesses_per_game,
+            summary.guessed_game_rate,
+            summary.avg_3bv,
+            summary.avg_moves,
+            summary.time_us_mean,
+            summary.time_us_p50,
+            summary.time_us_p90,
+            summary.time_us_p99,
+            summary.time_us_max);
+    }
+}
+
+fn main() {
+    let options = parse_options();
+
+    let mut summaries = Vec::new();
+    for difficulty in options.difficulties.iter() {
+        let (width, height, num_mines) = rules::get_difficulty_size(difficulty).expect("Unknown difficulty");
+        let results: Vec<GameResult> = (0..options.games as u64)
+            .map(|game| play_game(width, height, num_mines, options.seed.wrapping_add(game)))
+            .collect();
+        summaries.push(summarize(difficulty, &results));
+    }
+
+    if options.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&summaries).expect("Unable to serialize summary"));
+    } else {
+        print_csv(&summaries);
+    }
+}
@@ -0,0 +1,254 @@
+use std::io::{self, Stdout, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use minesweeper::core::rules::{self, Action, Board, Outcome, TileState};
+
+const DIFFICULTIES: [&str; 3] = ["Easy", "Normal", "Hard"];
+const USAGE: &str = "usage: tui [--difficulty Easy|Normal|Hard] [--seed N]";
+const BOARD_LEFT: u16 = 2;
+const BOARD_TOP: u16 = 3;
+const CELL_WIDTH: u16 = 2;
+const SMILE_LEFT: u16 = 2;
+const SMILE_WIDTH: u16 = 4;
+
+// Puts the terminal back even when the game panics.
+struct Terminal {
+    stdout: Stdout,
+}
+
+impl Terminal {
+    fn new() -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture, Hide, Clear(ClearType::All))?;
+        Ok(Self { stdout })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, Show, DisableMouseCapture, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Game {
+    difficulty: String,
+    /// Kept within u32 like the seeds of the game, so a board can be replayed there.
+    seed: Option<u32>,
+    board: Board,
+    cursor: (u32, u32),
+    start: Option<Instant>,
+    elapsed: Duration,
+}
+
+impl Game {
+    fn new(difficulty: &str, seed: Option<u32>) -> Self {
+        let (width, height, num_mines) = rules::get_difficulty_size(difficulty).expect("Unknown difficulty");
+        Self {
+            difficulty: difficulty.to_string(),
+            seed,
+            board: Board::new(width, height, num_mines, seed.unwrap_or_else(rand::random::<u32>) as u64),
+            cursor: (width.div_ceil(2), height.div_ceil(2)),
+            start: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(&self.difficulty, self.seed);
+    }
+
+    fn get_time(&self) -> u64 {
+        match self.start {
+            Some(start) => (self.elapsed + start.elapsed()).as_secs(),
+            None => self.elapsed.as_secs(),
+        }
+    }
+
+    // Same entry points as the mouse in the Bevy game: a left click reveals a hidden cell or
+    // chords a revealed one, a right click toggles a flag.
+    fn click(&mut self, x: u32, y: u32, is_flag: bool) {
+        if self.board.outcome() != Outcome::Playing || !self.board.contains(x, y) {
+            return;
+        }
+        let action = if is_flag {
+            Action::Flag
+        } else if self.board.state(x, y) == TileState::Revealed {
+            Action::Chord
+        } else {
+            Action::Reveal
+        };
+
+        if action == Action::Reveal && self.start.is_none() && self.board.state(x, y) == TileState::Hidden {
+            self.start = Some(Instant::now());
+        }
+        if self.board.apply(action, x, y) != Outcome::Playing {
+            if let Some(start) = self.start.take() {
+                self.elapsed += start.elapsed();
+            }
+        }
+    }
+
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let x = (self.cursor.0 as i32 + dx).clamp(1, self.board.width as i32);
+        let y = (self.cursor.1 as i32 + dy).clamp(1, self.board.height as i32);
+        self.cursor = (x as u32, y as u32);
+    }
+
+    fn get_smile(&self) -> &str {
+        match self.board.outcome() {
+            Outcome::Playing => ":)",
+            Outcome::Win => "B)",
+            Outcome::Defeated => "X(",
+        }
+    }
+
+    fn get_cell_at(&self, column: u16, row: u16) -> Option<(u32, u32)> {
+        if column < BOARD_LEFT || row < BOARD_TOP {
+            return None;
+        }
+        let x = ((column - BOARD_LEFT) / CELL_WIDTH) as u32 + 1;
+        let y = (row - BOARD_TOP) as u32 + 1;
+        if self.board.contains(x, y) {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+}
+
+fn get_number_color(number: u32) -> Color {
+    match number {
+        1 => Color::Blue,
+        2 => Color::DarkGreen,
+        3 => Color::Red,
+        4 => Color::DarkBlue,
+        5 => Color::DarkRed,
+        6 => Color::DarkCyan,
+        7 => Color::Black,
+        _ => Color::DarkGrey,
+    }
+}
+
+// The text, foreground and background of one cell, matching the sprites of the Bevy game.
+fn get_cell_style(board: &Board, x: u32, y: u32) -> (String, Color, Color) {
+    let is_over = board.outcome() != Outcome::Playing;
+    match board.state(x, y) {
+        TileState::Hidden if is_over && board.is_mine(x, y) => ("*".to_string(), Color::Black, Color::Grey),
+        TileState::Hidden => ("·".to_string(), Color::DarkGrey, Color::Grey),
+        TileState::Flagged => ("F".to_string(), Color::Red, Color::Grey),
+        TileState::WrongFlagged => ("X".to_string(), Color::Red, Color::White),
+        TileState::Exploded => ("*".to_string(), Color::Black, Color::Red),
+        TileState::Revealed => {
+            let number = board.get_num_mines_around(x, y);
+            if number == 0 {
+                (" ".to_string(), Color::Black, Color::White)
+            } else {
+                (number.to_string(), get_number_color(number), Color::White)
+            }
+        },
+    }
+}
+
+fn draw(stdout: &mut Stdout, game: &Game) -> io::Result<()> {
+    let board = &game.board;
+    let num_remaining = board.num_mines as i64 - board.num_flagged() as i64;
+
+    queue!(stdout, MoveTo(0, 1), Clear(ClearType::CurrentLine), MoveTo(SMILE_LEFT, 1))?;
+    queue!(stdout, SetForegroundColor(Color::Yellow), Print(format!("[{}]", game.get_smile())), ResetColor)?;
+    queue!(stdout, Print(format!("  mines {:03}  time {:03}  {}", num_remaining, game.get_time().min(999), game.difficulty)))?;
+
+    for y in 1..=board.height {
+        queue!(stdout, MoveTo(BOARD_LEFT, BOARD_TOP + y as u16 - 1))?;
+        for x in 1..=board.width {
+            let (text, foreground, background) = get_cell_style(board, x, y);
+            let background = if (x, y) == game.cursor { Color::Yellow } else { background };
+            queue!(stdout, SetForegroundColor(foreground), SetBackgroundColor(background), Print(format!("{} ", text)))?;
+        }
+        queue!(stdout, ResetColor)?;
+    }
+
+    queue!(stdout, MoveTo(BOARD_LEFT, BOARD_TOP + board.height as u16 + 1))?;
+    queue!(stdout, Print("arrows/hjkl move  space reveal/chord  f flag  r reset  q quit"))?;
+    stdout.flush()
+}
+
+fn parse_options() -> (String, Option<u32>) {
+    let mut difficulty = "Easy".to_string();
+    let mut seed = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_default();
+        let known_difficulty = DIFFICULTIES.iter().find(|known| known.eq_ignore_ascii_case(&value));
+        match (arg.as_str(), known_difficulty) {
+            ("--difficulty", Some(known)) => difficulty = known.to_string(),
+            ("--seed", _) if value.parse::<u32>().is_ok() => seed = value.parse().ok(),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            },
+        }
+    }
+    (difficulty, seed)
+}
+
+fn run(terminal: &mut Terminal, game: &mut Game) -> io::Result<()> {
+    loop {
+        draw(&mut terminal.stdout, game)?;
+        if !event::poll(Duration::from_millis(200))? {
+            continue;
+        }
+
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                    KeyCode::Left | KeyCode::Char('h') => game.move_cursor(-1, 0),
+                    KeyCode::Right | KeyCode::Char('l') => game.move_cursor(1, 0),
+                    KeyCode::Up | KeyCode::Char('k') => game.move_cursor(0, -1),
+                    KeyCode::Down | KeyCode::Char('j') => game.move_cursor(0, 1),
+                    KeyCode::Char(' ') | KeyCode::Enter => game.click(game.cursor.0, game.cursor.1, false),
+                    KeyCode::Char('f') => game.click(game.cursor.0, game.cursor.1, true),
+                    KeyCode::Char('r') => game.reset(),
+                    _ => {},
+                }
+            },
+            Event::Mouse(mouse) => {
+                let is_smile = mouse.row == 1 && mouse.column >= SMILE_LEFT && mouse.column < SMILE_LEFT + SMILE_WIDTH;
+                match mouse.kind {
+                    MouseEventKind::Down(MouseButton::Left) if is_smile => game.reset(),
+                    MouseEventKind::Down(button @ (MouseButton::Left | MouseButton::Right)) => {
+                        if let Some((x, y)) = game.get_cell_at(mouse.column, mouse.row) {
+                            game.cursor = (x, y);
+                            game.click(x, y, button == MouseButton::Right);
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn main() {
+    let (difficulty, seed) = parse_options();
+    let mut game = Game::new(&difficulty, seed);
+
+    let mut terminal = Terminal::new().expect("Unable to set up the terminal");
+    if let Err(e) = run(&mut terminal, &mut game) {
+        drop(terminal);
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::process;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use firestore::*;
+
+use minesweeper::core::rules::Move;
+use minesweeper::core::verify::{self, Submission};
+
+const USAGE: &str = "usage: verify FILE... | verify --service [--interval SEC] [--key PATH]";
+const PROJECT_ID: &str = "minesweeper-86284";
+const RANKING_COLLECTION: &str = "ranking";
+
+struct ServiceOptions {
+    interval: u64,
+    key: String,
+}
+
+// The fields the game writes to a ranking entry, plus the ones only the verifier sets.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct RankingDocument {
+    #[serde(alias = "_firestore_id", skip_serializing)]
+    doc_id: Option<String>,
+    id: String,
+    time: f32,
+    difficulty: String,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    moves: Vec<Move>,
+    #[serde(default)]
+    verified: bool,
+    #[serde(default)]
+    rejected: Option<String>,
+}
+
+impl RankingDocument {
+    fn get_submission(&self) -> Submission {
+        Submission {
+            difficulty: self.difficulty.clone(),
+            seed: self.seed,
+            time_ms: (self.time * 1000.0).round() as u64,
+            moves: self.moves.clone(),
+        }
+    }
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn parse_service_options(args: &[String]) -> ServiceOptions {
+    let mut options = ServiceOptions {
+        interval: 30,
+        key: "./key/firebase_key.json".to_string(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            exit_with_usage(&format!("missing value for {}", arg));
+        };
+        match arg.as_str() {
+            "--interval" => {
+                options.interval = value.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid interval {}", value)));
+            },
+            "--key" => options.key = value.clone(),
+            _ => exit_with_usage(&format!("unknown option {}", arg)),
+        }
+    }
+    options
+}
+
+// Files hold a submission as JSON or YAML, so a ghost run from `./save/` can be checked as is.
+fn verify_files(paths: &[String]) -> bool {
+    let mut is_all_verified = true;
+    for path in paths {
+        let result = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_yaml::from_str::<Submission>(&text).map_err(|e| e.to_string()))
+            .and_then(|submission| verify::verify(&submission).map_err(|e| e.to_string()));
+        match result {
+            Ok(time_ms) => println!("verified {} {:.3}s", path, time_ms as f64 / 1000.0),
+            Err(e) => {
+                println!("rejected {} {}", path, e);
+                is_all_verified = false;
+            },
+        }
+    }
+    is_all_verified
+}
+
+async fn verify_pending(db: &FirestoreDb) -> Result<(), Box<dyn std::error::Error>> {
+    let docs = db.fluent()
+        .select()
+        .from(RANKING_COLLECTION)
+        .filter(|q| q.for_all([q.field("verified").eq(false)]))
+        .query()
+        .await?;
+
+    for doc in docs.iter() {
+        let Ok(mut entry) = FirestoreDb::deserialize_doc_to::<RankingDocument>(doc) else {
+            continue;
+        };
+        let Some(doc_id) = entry.doc_id.clone() else {
+            continue;
+        };
+        if entry.rejected.is_some() {
+            continue;
+        }
+
+        // The replayed time replaces the reported one, which only had to be close to it.
+        let fields = match verify::verify(&entry.get_submission()) {
+            Ok(time_ms) => {
+                println!("verified {} {} {:.3}s", doc_id, entry.difficulty, time_ms as f64 / 1000.0);
+                entry.time = time_ms as f32 / 1000.0;
+                entry.verified = true;
+                ["verified", "time"]
+            },
+            Err(e) => {
+                println!("rejected {} {}", doc_id, e);
+                entry.rejected = Some(e.to_string());
+                ["rejected", "verified"]
+            },
+        };
+
+        let _updated: RankingDocument = db.fluent()
+            .update()
+            .fields(fields)
+            .in_col(RANKING_COLLECTION)
+            .document_id(&doc_id)
+            .object(&entry)
+            .execute()
+            .await?;
+    }
+    Ok(())
+}
+
+async fn run_service(options: ServiceOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let db = FirestoreDb::with_options_service_account_key_file(
+        FirestoreDbOptions::new(PROJECT_ID.to_string()),
+        options.key.into(),
+    )
+    .await?;
+
+    println!("verifying new rankings every {}s", options.interval);
+    loop {
+        if let Err(e) = verify_pending(&db).await {
+            eprintln!("{}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(options.interval)).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        None => exit_with_usage("nothing to verify"),
+        Some("--service") => {
+            let options = parse_service_options(&args[1..]);
+            if let Err(e) = run_service(options).await {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Some(_) => {
+            if !verify_files(&args) {
+                process::exit(1);
+            }
+        },
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use crate::component::grid::{Grid, MARGIN_X, MARGIN_Y};
 use crate::asset::texture_type::TextureType;
+use crate::core::rules::TileState;
 use crate::system::egui::TOP_BAR_HEIGHT;
 
 #[derive(PartialEq, Eq)]
@@ -9,10 +10,34 @@ pub enum CellState {
     Pressed,
     Revealed,
     Flagged,
+    /// Marked with a question mark, which only the player sees. Still hidden for the rules.
+    Questioned,
     WrongFlagged,
     Exploded,
 }
 
+impl CellState {
+    pub fn from_tile(state: TileState) -> Self {
+        match state {
+            TileState::Hidden => CellState::Hidden,
+            TileState::Revealed => CellState::Revealed,
+            TileState::Flagged => CellState::Flagged,
+            TileState::WrongFlagged => CellState::WrongFlagged,
+            TileState::Exploded => CellState::Exploded,
+        }
+    }
+
+    pub fn to_tile(&self) -> TileState {
+        match self {
+            CellState::Hidden | CellState::Pressed | CellState::Questioned => TileState::Hidden,
+            CellState::Revealed => TileState::Revealed,
+            CellState::Flagged => TileState::Flagged,
+            CellState::WrongFlagged => TileState::WrongFlagged,
+            CellState::Exploded => TileState::Exploded,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum QueryState {
     None,
@@ -126,7 +151,7 @@ impl Cell {
         self.is_left_pressed = false;
 
         match self.state {
-            CellState::Pressed => {
+            CellState::Pressed | CellState::Questioned => {
                 self.is_opening = true;
             },
             CellState::Revealed => {
@@ -139,7 +164,15 @@ impl Cell {
         }
     }
 
-    pub fn right_just_pressed(&mut self) {
+    pub fn left_cancel(&mut self) {
+        self.is_left_pressed = false;
+        self.query_state = QueryState::None;
+        if self.state == CellState::Pressed {
+            self.state = CellState::Hidden;
+        }
+    }
+
+    pub fn right_just_pressed(&mut self, is_question_marks: bool) {
         match self.state {
             CellState::Hidden => {
                 self.state = CellState::Flagged;
@@ -147,7 +180,10 @@ impl Cell {
             CellState::Pressed => {
                 self.state = CellState::Flagged;
             },
-            CellState::Flagged => {
+            CellState::Flagged if is_question_marks => {
+                self.state = CellState::Questioned;
+            },
+            CellState::Flagged | CellState::Questioned => {
                 self.state = CellState::Hidden;
             },
             _ => {}
@@ -168,6 +204,13 @@ impl Cell {
                 }
             },
             CellState::Flagged => 2,
+            CellState::Questioned => {
+                if self.is_left_pressed {
+                    4
+                } else {
+                    3
+                }
+            },
             CellState::WrongFlagged => 7,
             CellState::Exploded => 6,
         }
@@ -184,7 +227,7 @@ impl Cell {
                     true
                 }
             },
-            CellState::Pressed => {
+            CellState::Pressed | CellState::Questioned => {
                 if self.is_mine {
                     self.state = CellState::Exploded;
                     false
@@ -206,7 +249,7 @@ impl Cell {
     }
 
     pub fn bomb(&mut self) {
-        if self.is_mine && self.state == CellState::Hidden {
+        if self.is_mine && matches!(self.state, CellState::Hidden | CellState::Questioned) {
             self.state = CellState::Revealed;
         }
     }
@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+
+pub const GHOST_COLOR: Color = Color::rgba(0.55, 0.75, 1.0, 0.45);
+
+#[derive(Component)]
+pub struct GhostCell {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl GhostCell {
+    pub fn new(x: u32, y: u32) -> Self {
+        Self { x, y }
+    }
+}
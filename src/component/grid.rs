@@ -1,8 +1,8 @@
 use bevy::prelude::*;
 use bevy::utils::HashSet;
-use rand::seq::SliceRandom;
 
 use crate::asset::texture_type::TextureType;
+use crate::core::rules;
 #[derive(Resource)]
 pub struct Grid {
     pub width: u32,
@@ -11,6 +11,14 @@ pub struct Grid {
     pub window_position: Vec2,
     pub grid_window_size: Vec2,
     pub cells: Vec<(u32, u32, Entity)>,
+    /// The mines were planted around a safe start, so the board differs from the one its seed gives.
+    pub is_safe_start: bool,
+    /// The mines were taken from a loaded layout.
+    pub is_layout: bool,
+    /// Mines to plant on the first click instead of those of the seed.
+    pub next_layout: Option<Vec<(u32, u32)>>,
+    /// Keep the mines away from the first click.
+    pub wants_safe_start: bool,
 }
 
 pub const MARGIN_UP: f32 = 56.0;
@@ -29,6 +37,10 @@ impl Default for Grid {
             window_position: Vec2::new(0.0, 0.0),
             grid_window_size: Vec2::new(0.0, 0.0),
             cells: Vec::new(),
+            is_safe_start: false,
+            is_layout: false,
+            next_layout: None,
+            wants_safe_start: false,
         }
     }
 }
@@ -59,27 +71,10 @@ impl Grid {
         self.cells.clear();
     }
 
-    pub fn create_mine_positions(&mut self, num_mines: u32, exclude_pos: Option<(u32, u32)>) {
-        let mut rng = rand::thread_rng();
-        
-        let num_mines = if num_mines > self.width * self.height {
-            self.width * self.height
-        } else {
-            num_mines
-        };
-    
-        // Create a vector with all possible positions
-        let mut positions: Vec<(u32, u32)> = (1..=self.width)
-            .flat_map(|x| (1..=self.height).map(move |y| (x, y)))
-            .collect();
-
-        if let Some ((x, y)) = exclude_pos {
-            positions.retain(|(x_, y_)| *x_ != x || *y_ != y);
-        }
-    
-        positions.shuffle(&mut rng);
-    
-        self.mine_positions = positions.into_iter().take(num_mines as usize).collect::<HashSet<_>>()
+    pub fn create_mine_positions(&mut self, num_mines: u32, exclude: &[(u32, u32)], seed: u64) {
+        self.mine_positions = rules::create_mine_positions_excluding(self.width, self.height, num_mines, exclude, seed)
+            .into_iter()
+            .collect::<HashSet<_>>()
     }
 
     pub fn add_cell(&mut self, x: u32, y: u32, entity: Entity) {
@@ -96,48 +91,10 @@ impl Grid {
     }
 
     pub fn get_arround_cells(&self, x: u32, y: u32) -> Vec<(u32, u32, Option<Entity>)> {
-        let width = self.width;
-        let height = self.height;
-
-        let mut cells = Vec::new();
-        // 1 2 3
-        // 4 x 5
-        // 6 7 8
-
-        // case 1
-        if x > 1 && y > 1 {
-            cells.push((x - 1, y - 1, self.find_cell(x - 1, y - 1)));
-        }
-        // case 2
-        if y > 1 {
-            cells.push((x, y - 1, self.find_cell(x, y - 1)));
-        }
-        // case 3
-        if x < width && y > 1 {
-            cells.push((x + 1, y - 1, self.find_cell(x + 1, y - 1)));
-        }
-        // case 4
-        if x > 1 {
-            cells.push((x - 1, y, self.find_cell(x - 1, y)));
-        }
-        // case 5
-        if x < width {
-            cells.push((x + 1, y, self.find_cell(x + 1, y)));
-        }
-        // case 6
-        if x > 1 && y < height {
-            cells.push((x - 1, y + 1, self.find_cell(x - 1, y + 1)));
-        }
-        // case 7
-        if y < height {
-            cells.push((x, y + 1, self.find_cell(x, y + 1)));
-        }
-        // case 8
-        if x < width && y < height {
-            cells.push((x + 1, y + 1, self.find_cell(x + 1, y + 1)));
-        }
-
-        cells
+        rules::get_arround_positions(self.width, self.height, x, y)
+            .into_iter()
+            .map(|(x, y)| (x, y, self.find_cell(x, y)))
+            .collect()
     }
 
     pub fn get_num_mines_around(&self, x: u32, y: u32) -> u32 {
@@ -41,4 +41,9 @@ impl SmileComponent {
         self.state = SmileSprite::Normal;
         next_state.set(GameState::Ready);
     }
+
+    pub fn cancel(&mut self) {
+        self.is_pressed = false;
+        self.state = SmileSprite::Normal;
+    }
 }
\ No newline at end of file
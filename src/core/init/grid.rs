@@ -6,9 +6,11 @@ use crate::component::smile::{SmileComponent, SmileSprite};
 use crate::component::number::{NumberSprite, NumberType, NumberTypeComponent, NumberIndex, NumberIndexComponent};
 use crate::component::mine::TotalMine;
 use crate::component::frame::Frame;
+use crate::component::ghost::{GhostCell, GHOST_COLOR};
 use crate::system::egui::TOP_BAR_HEIGHT;
 use crate::system::difficulty::Difficulty;
 use crate::system::state::GameState;
+use crate::system::seed::Seed;
 use crate::system::mouse;
 use crate::asset::{self, texture_type::TextureType};
 
@@ -32,7 +34,7 @@ fn spawn_cell(
         cell,
         SpriteSheetBundle {
             texture_atlas: texture_atlas_handle.clone(),
-            sprite: TextureAtlasSprite::new(index as usize),
+            sprite: asset::loader::new_sprite(TextureType::Cells, index as usize),
             transform: Transform {
                 translation: position,
                 ..default()
@@ -43,7 +45,24 @@ fn spawn_cell(
             Vec3::new(position.x + grid.window_position.x, -position.y + grid.window_position.y + TOP_BAR_HEIGHT / 2.0, 0.0), 
             width, 
             height),
-    )).set_parent(frame_id).id();
+    )).with_children(|commands| {
+        commands.spawn((
+            GhostCell::new(x, y),
+            SpriteSheetBundle {
+                texture_atlas: texture_atlas_handle.clone(),
+                sprite: TextureAtlasSprite {
+                    color: GHOST_COLOR,
+                    ..asset::loader::new_sprite(TextureType::Cells, 0)
+                },
+                transform: Transform {
+                    translation: Vec3::new(0.0, 0.0, 1.0),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }).set_parent(frame_id).id();
 
     grid.cells.push((x, y, id));
 }
@@ -69,7 +88,7 @@ fn spawn_frame(
         commands.spawn((
             SpriteSheetBundle {
                 texture_atlas: texture_atlas_handle.clone(),
-                sprite: TextureAtlasSprite::new(0),
+                sprite: asset::loader::new_sprite(texture_type, 0),
                 transform: Transform {
                     translation: Vec3::new(position.x + MARGIN_X, position.y + 1.0 + MARGIN_Y - TOP_BAR_HEIGHT / 2.0, position.z),
                     scale: scale,
@@ -149,7 +168,7 @@ fn spawn_frame(
         commands.spawn((
             SpriteSheetBundle {
                 texture_atlas: texture_atlas_handle.clone(),
-                sprite: TextureAtlasSprite::new(0),
+                sprite: asset::loader::new_sprite(TextureType::Number, 0),
                 transform: Transform {
                     translation: Vec3::new(position.x + MARGIN_X, position.y + MARGIN_Y - TOP_BAR_HEIGHT / 2.0, position.z),
                     ..default()
@@ -163,7 +182,7 @@ fn spawn_frame(
                 NumberIndexComponent::new(NumberIndex::First),
                 SpriteSheetBundle {
                     texture_atlas: texture_atlas_handle.clone(),
-                    sprite: TextureAtlasSprite::new(NumberSprite::Zero as usize),
+                    sprite: asset::loader::new_sprite(TextureType::Numbers, NumberSprite::Zero as usize),
                     transform: Transform {
                         translation: Vec3::new(-TextureType::Numbers.get_texture_size().0, 0.0, position.z * 2.0),
                         ..default()
@@ -176,7 +195,7 @@ fn spawn_frame(
                 NumberIndexComponent::new(NumberIndex::Second),
                 SpriteSheetBundle {
                     texture_atlas: texture_atlas_handle.clone(),
-                    sprite: TextureAtlasSprite::new(NumberSprite::Zero as usize),
+                    sprite: asset::loader::new_sprite(TextureType::Numbers, NumberSprite::Zero as usize),
                     transform: Transform {
                         translation: Vec3::new(0.0, 0.0, position.z * 2.0),
                         ..default()
@@ -189,7 +208,7 @@ fn spawn_frame(
                 NumberIndexComponent::new(NumberIndex::Third),
                 SpriteSheetBundle {
                     texture_atlas: texture_atlas_handle.clone(),
-                    sprite: TextureAtlasSprite::new(NumberSprite::Zero as usize),
+                    sprite: asset::loader::new_sprite(TextureType::Numbers, NumberSprite::Zero as usize),
                     transform: Transform {
                         translation: Vec3::new(TextureType::Numbers.get_texture_size().0,  0.0, position.z * 2.0),
                         ..default()
@@ -212,7 +231,7 @@ fn spawn_frame(
         commands.spawn((
             SpriteSheetBundle {
                 texture_atlas: texture_atlas_handle.clone(),
-                sprite: TextureAtlasSprite::new(0),
+                sprite: asset::loader::new_sprite(TextureType::Smile, 0),
                 transform: Transform {
                     translation: position,
                     ..default()
@@ -225,7 +244,7 @@ fn spawn_frame(
                 SmileComponent::new(),
                 SpriteSheetBundle {
                     texture_atlas: texture_atlas_handle.clone(),
-                    sprite: TextureAtlasSprite::new(SmileSprite::Normal as usize),
+                    sprite: asset::loader::new_sprite(TextureType::Smiles, SmileSprite::Normal as usize),
                     transform: Transform {
                         translation: Vec3::new(0.0, 0.0, position.z * 2.0),
                         ..default()
@@ -244,24 +263,19 @@ fn spawn_frame(
     spawn(position);
 }
 
-fn get_difficulty(difficulty: &Difficulty) -> (u32, u32, u32) {
-    match difficulty {
-        Difficulty::Easy => (9, 9, 10),
-        Difficulty::Normal => (16, 16, 40),
-        Difficulty::Hard => (30, 16, 99),
-    }
-}
-
 fn spawn_grid(
     mines: &mut TotalMine,
     difficulty: &Difficulty,
+    seed: &Seed,
     commands: &mut Commands,
     grid: &mut Grid,
 ) -> Entity {
-    let (width, height, num_mines) = get_difficulty(difficulty);
+    let (width, height, num_mines) = difficulty.get_size();
     grid.init(width, height);
     mines.init(num_mines);
-    grid.create_mine_positions(mines.0, None);
+    grid.create_mine_positions(mines.0, &[], seed.value);
+    grid.is_safe_start = false;
+    grid.is_layout = false;
     
     commands.spawn((
         Frame::new(),
@@ -290,10 +304,11 @@ pub fn init(
     mut mines: ResMut<TotalMine>,
     mut grid: ResMut<Grid>,
     difficulty : Res<Difficulty>,
+    seed: Res<Seed>,
     texture_atlas_resource: Res<asset::loader::TextureAtlasResource>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    let frame_id = spawn_grid(&mut mines, &difficulty, &mut commands, &mut grid);
+    let frame_id = spawn_grid(&mut mines, &difficulty, &seed, &mut commands, &mut grid);
     spawn_cells(&mut commands, &mut grid, &texture_atlas_resource, frame_id);
     spawn_frame(&mut commands, &mut grid, &texture_atlas_resource, frame_id);
     next_state.set(GameState::Ready);
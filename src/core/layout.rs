@@ -0,0 +1,138 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+use crate::core::rules;
+
+/// A board with its mines placed by hand, e.g. to set up a test scenario. Written as YAML rows
+/// from the top of the board down, with `*` for a mine and `.` for a free cell:
+/// ```yaml
+/// rows:
+///   - "*........"
+///   - "........."
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub rows: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    Parse(String),
+    Empty,
+    UnevenRow(usize),
+    UnknownCell { row: usize, column: usize, cell: char },
+    InvalidSize { width: u32, height: u32, num_mines: u32 },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Parse(e) => write!(f, "{}", e),
+            LayoutError::Empty => write!(f, "the board has no rows"),
+            LayoutError::UnevenRow(row) => write!(f, "row {} is not as long as the first", row),
+            LayoutError::UnknownCell { row, column, cell } => {
+                write!(f, "row {} column {} is '{}', expected '*' or '.'", row, column, cell)
+            },
+            LayoutError::InvalidSize { width, height, num_mines } => write!(
+                f,
+                "{}x{} with {} mines, expected {}-{} by {}-{} cells with at least one mine and nine free cells",
+                width, height, num_mines,
+                rules::MIN_CUSTOM_SIZE, rules::MAX_CUSTOM_WIDTH, rules::MIN_CUSTOM_SIZE, rules::MAX_CUSTOM_HEIGHT,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+impl Layout {
+    /// Reads and checks a layout, e.g. the text of a board file.
+    pub fn parse(text: &str) -> Result<Self, LayoutError> {
+        let layout = serde_yaml::from_str::<Layout>(text).map_err(|e| LayoutError::Parse(e.to_string()))?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        let Some(first) = self.rows.first() else {
+            return Err(LayoutError::Empty);
+        };
+        let width = first.chars().count();
+        for (index, row) in self.rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(LayoutError::UnevenRow(index + 1));
+            }
+            if let Some((column, cell)) = row.chars().enumerate().find(|(_, cell)| *cell != '*' && *cell != '.') {
+                return Err(LayoutError::UnknownCell { row: index + 1, column: column + 1, cell });
+            }
+        }
+
+        let (width, height, num_mines) = self.get_size();
+        if !rules::is_valid_custom_size(width, height, num_mines) {
+            return Err(LayoutError::InvalidSize { width, height, num_mines });
+        }
+        Ok(())
+    }
+
+    /// Width, height and number of mines, the same as `rules::get_difficulty_size`.
+    pub fn get_size(&self) -> (u32, u32, u32) {
+        let width = self.rows.first().map(|row| row.chars().count()).unwrap_or(0) as u32;
+        let num_mines = self.rows.iter().map(|row| row.chars().filter(|cell| *cell == '*').count()).sum::<usize>() as u32;
+        (width, self.rows.len() as u32, num_mines)
+    }
+
+    /// The mines in board coordinates, where `y` counts up from the bottom row.
+    pub fn get_mine_positions(&self) -> Vec<(u32, u32)> {
+        let height = self.rows.len() as u32;
+        self.rows.iter().enumerate()
+            .flat_map(|(index, row)| {
+                row.chars().enumerate()
+                    .filter(|(_, cell)| *cell == '*')
+                    .map(move |(column, _)| (column as u32 + 1, height - index as u32))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&str]) -> String {
+        let rows: Vec<String> = rows.iter().map(|row| format!("  - \"{}\"\n", row)).collect();
+        format!("rows:\n{}", rows.concat())
+    }
+
+    #[test]
+    fn rows_are_read_from_the_top_down() {
+        let mut board = vec!["........."; 9];
+        board[0] = "*........";
+        board[8] = "........*";
+        let layout = Layout::parse(&rows(&board)).unwrap();
+        assert_eq!(layout.get_size(), (9, 9, 2));
+        assert_eq!(layout.get_mine_positions(), vec![(1, 9), (9, 1)]);
+    }
+
+    #[test]
+    fn broken_boards_are_rejected() {
+        let mut uneven = vec!["*........"; 9];
+        uneven[3] = "*.......";
+        let mut unknown = vec!["*........"; 9];
+        unknown[1] = "*...x....";
+        let cases = [
+            ("rows: [unclosed".to_string(), None),
+            ("rows: []".to_string(), Some(LayoutError::Empty)),
+            (rows(&uneven), Some(LayoutError::UnevenRow(4))),
+            (rows(&unknown), Some(LayoutError::UnknownCell { row: 2, column: 5, cell: 'x' })),
+            (rows(&["*........"; 8]), Some(LayoutError::InvalidSize { width: 9, height: 8, num_mines: 8 })),
+            (rows(&["........."; 9]), Some(LayoutError::InvalidSize { width: 9, height: 9, num_mines: 0 })),
+        ];
+        for (text, expected) in cases {
+            let result = Layout::parse(&text);
+            match expected {
+                Some(expected) => assert_eq!(result, Err(expected), "{}", text),
+                None => assert!(matches!(result, Err(LayoutError::Parse(_))), "{}", text),
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::core::rules::{self, Action, Board, TileState};
+
+/// What a player can see of a tile: numbers and flags only, never the mines underneath.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibleTile {
+    Hidden,
+    Flagged,
+    Revealed(u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct VisibleBoard {
+    pub width: u32,
+    pub height: u32,
+    pub num_mines: u32,
+    tiles: Vec<VisibleTile>,
+}
+
+impl VisibleBoard {
+    pub fn new(width: u32, height: u32, num_mines: u32) -> Self {
+        Self {
+            width,
+            height,
+            num_mines,
+            tiles: vec![VisibleTile::Hidden; (width * height) as usize],
+        }
+    }
+
+    pub fn from_board(board: &Board) -> Self {
+        let mut visible = Self::new(board.width, board.height, board.num_mines);
+        for y in 1..=board.height {
+            for x in 1..=board.width {
+                let tile = match board.state(x, y) {
+                    TileState::Hidden => VisibleTile::Hidden,
+                    TileState::Flagged | TileState::WrongFlagged => VisibleTile::Flagged,
+                    TileState::Revealed | TileState::Exploded => VisibleTile::Revealed(board.get_num_mines_around(x, y)),
+                };
+                visible.set(x, y, tile);
+            }
+        }
+        visible
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        ((y - 1) * self.width + (x - 1)) as usize
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> VisibleTile {
+        self.tiles[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, tile: VisibleTile) {
+        let index = self.index(x, y);
+        self.tiles[index] = tile;
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (u32, u32)> {
+        let width = self.width;
+        (1..=self.height).flat_map(move |y| (1..=width).map(move |x| (x, y)))
+    }
+
+    pub fn get_arround_positions(&self, x: u32, y: u32) -> Vec<(u32, u32)> {
+        rules::get_arround_positions(self.width, self.height, x, y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerMove {
+    pub action: Action,
+    pub x: u32,
+    pub y: u32,
+    pub is_guess: bool,
+}
+
+/// Anything that can pick the next move from what is visible on the board.
+pub trait Player {
+    fn get_name(&self) -> &str;
+    fn next_move(&mut self, board: &VisibleBoard) -> Option<PlayerMove>;
+}
+
+// A revealed number together with the hidden tiles around it and the mines still among them.
+struct Constraint {
+    unknowns: HashSet<(u32, u32)>,
+    num_mines: u32,
+}
+
+/// Plays every move the numbers prove safe, and otherwise reveals the least likely mine.
+pub struct SolverBot {
+    rng: StdRng,
+}
+
+impl SolverBot {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn get_constraints(board: &VisibleBoard) -> Vec<Constraint> {
+        let mut constraints = Vec::new();
+        for (x, y) in board.positions() {
+            let VisibleTile::Revealed(number) = board.get(x, y) else {
+                continue;
+            };
+            let mut unknowns = HashSet::new();
+            let mut num_flagged = 0;
+            for (x_, y_) in board.get_arround_positions(x, y) {
+                match board.get(x_, y_) {
+                    VisibleTile::Hidden => {
+                        unknowns.insert((x_, y_));
+                    },
+                    VisibleTile::Flagged => num_flagged += 1,
+                    VisibleTile::Revealed(_) => {},
+                }
+            }
+            if unknowns.is_empty() {
+                continue;
+            }
+            constraints.push(Constraint {
+                unknowns,
+                num_mines: number.saturating_sub(num_flagged),
+            });
+        }
+        constraints
+    }
+
+    fn find_certain(constraints: &[Constraint]) -> Option<(Action, (u32, u32))> {
+        for constraint in constraints {
+            if constraint.num_mines == 0 {
+                return constraint.unknowns.iter().min().map(|pos| (Action::Reveal, *pos));
+            }
+            if constraint.num_mines as usize == constraint.unknowns.len() {
+                return constraint.unknowns.iter().min().map(|pos| (Action::Flag, *pos));
+            }
+        }
+
+        // When one constraint's tiles are a subset of another's, the difference is decided too.
+        for small in constraints {
+            for large in constraints {
+                if small.unknowns.len() >= large.unknowns.len() || !small.unknowns.is_subset(&large.unknowns) {
+                    continue;
+                }
+                let rest: Vec<&(u32, u32)> = large.unknowns.difference(&small.unknowns).collect();
+                let rest_mines = large.num_mines.saturating_sub(small.num_mines);
+                if rest_mines == 0 {
+                    return rest.into_iter().min().map(|pos| (Action::Reveal, *pos));
+                }
+                if rest_mines as usize == rest.len() {
+                    return rest.into_iter().min().map(|pos| (Action::Flag, *pos));
+                }
+            }
+        }
+        None
+    }
+
+    fn guess(&mut self, board: &VisibleBoard, constraints: &[Constraint]) -> Option<((u32, u32), bool)> {
+        let hidden: Vec<(u32, u32)> = board.positions()
+            .filter(|(x, y)| board.get(*x, *y) == VisibleTile::Hidden)
+            .collect();
+        if hidden.is_empty() {
+            return None;
+        }
+
+        // The opening move is always safe, and the middle is the most likely place for an empty area.
+        if hidden.len() == (board.width * board.height) as usize {
+            return Some(((board.width.div_ceil(2), board.height.div_ceil(2)), false));
+        }
+
+        let num_flagged = board.positions()
+            .filter(|(x, y)| board.get(*x, *y) == VisibleTile::Flagged)
+            .count() as u32;
+        let default_probability = board.num_mines.saturating_sub(num_flagged) as f32 / hidden.len() as f32;
+
+        let mut best: Vec<(u32, u32)> = Vec::new();
+        let mut best_probability = f32::MAX;
+        for pos in hidden {
+            let probability = constraints.iter()
+                .filter(|constraint| constraint.unknowns.contains(&pos))
+                .map(|constraint| constraint.num_mines as f32 / constraint.unknowns.len() as f32)
+                .reduce(f32::max)
+                .unwrap_or(default_probability);
+            if probability < best_probability {
+                best_probability = probability;
+                best.clear();
+            }
+            if probability <= best_probability {
+                best.push(pos);
+            }
+        }
+
+        let index = self.rng.gen_range(0..best.len());
+        Some((best[index], true))
+    }
+}
+
+impl Player for SolverBot {
+    fn get_name(&self) -> &str {
+        "Solver"
+    }
+
+    fn next_move(&mut self, board: &VisibleBoard) -> Option<PlayerMove> {
+        let constraints = Self::get_constraints(board);
+        if let Some((action, (x, y))) = Self::find_certain(&constraints) {
+            return Some(PlayerMove { action, x, y, is_guess: false });
+        }
+        let ((x, y), is_guess) = self.guess(board, &constraints)?;
+        Some(PlayerMove { action: Action::Reveal, x, y, is_guess })
+    }
+}
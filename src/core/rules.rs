@@ -0,0 +1,530 @@
+use std::collections::{HashSet, VecDeque};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Reveal,
+    Chord,
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    pub time_ms: u64,
+    pub x: u32,
+    pub y: u32,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TileState {
+    Hidden,
+    Revealed,
+    Flagged,
+    WrongFlagged,
+    Exploded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Playing,
+    Win,
+    Defeated,
+}
+
+pub fn get_difficulty_size(difficulty: &str) -> Option<(u32, u32, u32)> {
+    match difficulty {
+        "Easy" => Some((9, 9, 10)),
+        "Normal" => Some((16, 16, 40)),
+        "Hard" => Some((30, 16, 99)),
+        _ => None,
+    }
+}
+
+pub const MIN_CUSTOM_SIZE: u32 = 9;
+pub const MAX_CUSTOM_WIDTH: u32 = 50;
+pub const MAX_CUSTOM_HEIGHT: u32 = 30;
+
+/// Whether a custom board fits the window, has a mine, and leaves room for a safe start.
+pub fn is_valid_custom_size(width: u32, height: u32, num_mines: u32) -> bool {
+    (MIN_CUSTOM_SIZE..=MAX_CUSTOM_WIDTH).contains(&width)
+        && (MIN_CUSTOM_SIZE..=MAX_CUSTOM_HEIGHT).contains(&height)
+        && num_mines >= 1
+        && num_mines + 9 <= width * height
+}
+
+pub fn get_custom_size_name(width: u32, height: u32, num_mines: u32) -> String {
+    format!("{}x{}x{}", width, height, num_mines)
+}
+
+/// A custom board written as `<width>x<height>x<mines>`, e.g. `20x12x40`.
+pub fn parse_custom_size(size: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = size.split('x').map(|part| part.trim().parse::<u32>().ok());
+    let (Some(Some(width)), Some(Some(height)), Some(Some(num_mines)), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    is_valid_custom_size(width, height, num_mines).then_some((width, height, num_mines))
+}
+
+/// The size of a standard difficulty or a custom board. Only the standard ones are ranked, so
+/// the verifier keeps to `get_difficulty_size`.
+pub fn get_board_size(difficulty: &str) -> Option<(u32, u32, u32)> {
+    get_difficulty_size(difficulty).or_else(|| parse_custom_size(difficulty))
+}
+
+// The shuffle does not depend on the excluded position, so every player of a seed
+// gets the same board apart from the single mine moved out from under the first click.
+pub fn create_mine_positions(width: u32, height: u32, num_mines: u32, exclude_pos: Option<(u32, u32)>, seed: u64) -> Vec<(u32, u32)> {
+    let exclude: Vec<(u32, u32)> = exclude_pos.into_iter().collect();
+    create_mine_positions_excluding(width, height, num_mines, &exclude, seed)
+}
+
+/// Like `create_mine_positions`, but keeps every position of `exclude` free of mines.
+pub fn create_mine_positions_excluding(width: u32, height: u32, num_mines: u32, exclude: &[(u32, u32)], seed: u64) -> Vec<(u32, u32)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let num_mines = if num_mines > width * height {
+        width * height
+    } else {
+        num_mines
+    };
+
+    let mut positions: Vec<(u32, u32)> = (1..=width)
+        .flat_map(|x| (1..=height).map(move |y| (x, y)))
+        .collect();
+
+    positions.shuffle(&mut rng);
+
+    positions.retain(|position| !exclude.contains(position));
+
+    positions.into_iter().take(num_mines as usize).collect()
+}
+
+/// The first click and the cells around it, which a safe start keeps free of mines so that the
+/// first click always opens an area. Only the first click on a board too full for that.
+pub fn get_start_area(width: u32, height: u32, num_mines: u32, x: u32, y: u32) -> Vec<(u32, u32)> {
+    let mut area = get_arround_positions(width, height, x, y);
+    area.push((x, y));
+    if num_mines + area.len() as u32 > width * height {
+        vec![(x, y)]
+    } else {
+        area
+    }
+}
+
+pub fn get_arround_positions(width: u32, height: u32, x: u32, y: u32) -> Vec<(u32, u32)> {
+    let mut positions = Vec::new();
+    // 1 2 3
+    // 4 x 5
+    // 6 7 8
+    if x > 1 && y > 1 {
+        positions.push((x - 1, y - 1));
+    }
+    if y > 1 {
+        positions.push((x, y - 1));
+    }
+    if x < width && y > 1 {
+        positions.push((x + 1, y - 1));
+    }
+    if x > 1 {
+        positions.push((x - 1, y));
+    }
+    if x < width {
+        positions.push((x + 1, y));
+    }
+    if x > 1 && y < height {
+        positions.push((x - 1, y + 1));
+    }
+    if y < height {
+        positions.push((x, y + 1));
+    }
+    if x < width && y < height {
+        positions.push((x + 1, y + 1));
+    }
+    positions
+}
+
+/// Renderer-free model of a single game, applying the same rules as `core::update::cells`, whose
+/// tests replay seeded games through both.
+#[derive(Debug, Clone)]
+pub struct Board {
+    pub width: u32,
+    pub height: u32,
+    pub num_mines: u32,
+    pub seed: u64,
+    pub is_planted: bool,
+    mines: HashSet<(u32, u32)>,
+    tiles: Vec<TileState>,
+    outcome: Outcome,
+}
+
+impl Board {
+    pub fn new(width: u32, height: u32, num_mines: u32, seed: u64) -> Self {
+        Self {
+            width,
+            height,
+            num_mines,
+            seed,
+            is_planted: false,
+            mines: HashSet::new(),
+            tiles: vec![TileState::Hidden; (width * height) as usize],
+            outcome: Outcome::Playing,
+        }
+    }
+
+    pub fn plant(&mut self, exclude_pos: Option<(u32, u32)>) {
+        self.mines = create_mine_positions(self.width, self.height, self.num_mines, exclude_pos, self.seed)
+            .into_iter()
+            .collect();
+        self.is_planted = true;
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        ((y - 1) * self.width + (x - 1)) as usize
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= 1 && y >= 1 && x <= self.width && y <= self.height
+    }
+
+    pub fn state(&self, x: u32, y: u32) -> TileState {
+        self.tiles[self.index(x, y)]
+    }
+
+    fn set_state(&mut self, x: u32, y: u32, state: TileState) {
+        let index = self.index(x, y);
+        self.tiles[index] = state;
+    }
+
+    pub fn is_mine(&self, x: u32, y: u32) -> bool {
+        self.mines.contains(&(x, y))
+    }
+
+    pub fn mine_positions(&self) -> &HashSet<(u32, u32)> {
+        &self.mines
+    }
+
+    pub fn get_arround_positions(&self, x: u32, y: u32) -> Vec<(u32, u32)> {
+        get_arround_positions(self.width, self.height, x, y)
+    }
+
+    pub fn get_num_mines_around(&self, x: u32, y: u32) -> u32 {
+        self.get_arround_positions(x, y)
+            .iter()
+            .filter(|pos| self.mines.contains(pos))
+            .count() as u32
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn num_flagged(&self) -> u32 {
+        self.tiles.iter()
+            .filter(|state| **state == TileState::Flagged || **state == TileState::WrongFlagged)
+            .count() as u32
+    }
+
+    pub fn num_revealed(&self) -> u32 {
+        self.tiles.iter().filter(|state| **state == TileState::Revealed).count() as u32
+    }
+
+    pub fn apply(&mut self, action: Action, x: u32, y: u32) -> Outcome {
+        if self.outcome != Outcome::Playing || !self.contains(x, y) {
+            return self.outcome;
+        }
+
+        match action {
+            Action::Reveal => {
+                if !self.is_planted {
+                    self.plant(Some((x, y)));
+                }
+                if self.state(x, y) == TileState::Hidden {
+                    self.open(vec![(x, y)]);
+                }
+            },
+            Action::Chord => self.chord(x, y),
+            Action::Flag => {
+                match self.state(x, y) {
+                    TileState::Hidden => self.set_state(x, y, TileState::Flagged),
+                    TileState::Flagged => self.set_state(x, y, TileState::Hidden),
+                    _ => {}
+                }
+            },
+        }
+
+        if self.outcome == Outcome::Playing && self.is_cleared() {
+            self.outcome = Outcome::Win;
+        }
+        self.outcome
+    }
+
+    fn chord(&mut self, x: u32, y: u32) {
+        if self.state(x, y) != TileState::Revealed || self.is_mine(x, y) {
+            return;
+        }
+        let num_mines_around = self.get_num_mines_around(x, y);
+        if num_mines_around == 0 {
+            return;
+        }
+
+        let arround_positions = self.get_arround_positions(x, y);
+        let num_of_flagged = arround_positions.iter()
+            .filter(|(x, y)| self.state(*x, *y) == TileState::Flagged)
+            .count() as u32;
+        if num_of_flagged != num_mines_around {
+            return;
+        }
+
+        let targets = arround_positions.into_iter()
+            .filter(|(x, y)| matches!(self.state(*x, *y), TileState::Hidden | TileState::Flagged))
+            .collect();
+        self.open(targets);
+    }
+
+    fn open(&mut self, starts: Vec<(u32, u32)>) {
+        let mut queue: VecDeque<(u32, u32)> = VecDeque::new();
+        let mut visited: HashSet<(u32, u32)> = HashSet::new();
+        let mut targets: Vec<(u32, u32)> = Vec::new();
+        for start in starts {
+            if visited.insert(start) {
+                queue.push_back(start);
+            }
+        }
+
+        while let Some((x, y)) = queue.pop_front() {
+            targets.push((x, y));
+            if self.is_mine(x, y) || self.get_num_mines_around(x, y) != 0 {
+                continue;
+            }
+            for pos in self.get_arround_positions(x, y) {
+                if visited.insert(pos) {
+                    queue.push_back(pos);
+                }
+            }
+        }
+
+        for (x, y) in targets {
+            let is_mine = self.is_mine(x, y);
+            match self.state(x, y) {
+                TileState::Hidden => {
+                    if is_mine {
+                        self.set_state(x, y, TileState::Exploded);
+                        self.outcome = Outcome::Defeated;
+                    } else {
+                        self.set_state(x, y, TileState::Revealed);
+                    }
+                },
+                TileState::Flagged => {
+                    if !is_mine {
+                        self.set_state(x, y, TileState::WrongFlagged);
+                        self.outcome = Outcome::Defeated;
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // The fewest clicks that clear the board without flags: one per opening, plus one per
+    // number that no opening uncovers. Only meaningful once the mines are planted.
+    pub fn get_3bv(&self) -> u32 {
+        let mut visited: HashSet<(u32, u32)> = HashSet::new();
+        let mut count = 0;
+        let positions: Vec<(u32, u32)> = (1..=self.height)
+            .flat_map(|y| (1..=self.width).map(move |x| (x, y)))
+            .collect();
+
+        for pos in positions.iter() {
+            if visited.contains(pos) || self.is_mine(pos.0, pos.1) || self.get_num_mines_around(pos.0, pos.1) != 0 {
+                continue;
+            }
+            count += 1;
+            let mut queue = VecDeque::from([*pos]);
+            visited.insert(*pos);
+            while let Some((x, y)) = queue.pop_front() {
+                if self.get_num_mines_around(x, y) != 0 {
+                    continue;
+                }
+                for next in self.get_arround_positions(x, y) {
+                    if visited.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        count + positions.iter()
+            .filter(|pos| !visited.contains(pos) && !self.is_mine(pos.0, pos.1))
+            .count() as u32
+    }
+
+    fn is_cleared(&self) -> bool {
+        self.tiles.iter().all(|state| *state == TileState::Flagged || *state == TileState::Revealed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A board with the mines placed by hand, as if the first click had planted them.
+    fn board_with_mines(width: u32, height: u32, mines: &[(u32, u32)]) -> Board {
+        let mut board = Board::new(width, height, mines.len() as u32, 0);
+        board.mines = mines.iter().copied().collect();
+        board.is_planted = true;
+        board
+    }
+
+    #[test]
+    fn mine_positions_follow_the_seed() {
+        let first = create_mine_positions(30, 16, 99, None, 42);
+        assert_eq!(first, create_mine_positions(30, 16, 99, None, 42));
+        assert_ne!(first, create_mine_positions(30, 16, 99, None, 43));
+        assert_eq!(first.len(), 99);
+        assert_eq!(first.iter().collect::<HashSet<_>>().len(), 99);
+        assert!(first.iter().all(|(x, y)| (1..=30).contains(x) && (1..=16).contains(y)));
+    }
+
+    #[test]
+    fn excluded_position_only_moves_its_own_mine() {
+        let without: HashSet<_> = create_mine_positions(16, 16, 40, None, 7).into_iter().collect();
+        let mine = *without.iter().next().unwrap();
+        let with: HashSet<_> = create_mine_positions(16, 16, 40, Some(mine), 7).into_iter().collect();
+        assert!(!with.contains(&mine));
+        assert_eq!(with.len(), 40);
+        assert_eq!(without.difference(&with).count(), 1);
+
+        let free = (1..=16).flat_map(|x| (1..=16).map(move |y| (x, y))).find(|pos| !without.contains(pos)).unwrap();
+        let same: HashSet<_> = create_mine_positions(16, 16, 40, Some(free), 7).into_iter().collect();
+        assert_eq!(same, without);
+    }
+
+    #[test]
+    fn excluding_keeps_the_start_area_free() {
+        let area = get_start_area(9, 9, 10, 5, 5);
+        assert_eq!(area.len(), 9);
+        for seed in 0..50 {
+            let mines = create_mine_positions_excluding(9, 9, 10, &area, seed);
+            assert_eq!(mines.len(), 10);
+            assert!(mines.iter().all(|pos| !area.contains(pos)));
+        }
+    }
+
+    #[test]
+    fn excluding_caps_the_mines_at_the_free_cells() {
+        assert_eq!(create_mine_positions_excluding(3, 3, 20, &[], 1).len(), 9);
+        assert_eq!(create_mine_positions_excluding(3, 3, 9, &[(2, 2)], 1).len(), 8);
+    }
+
+    #[test]
+    fn start_area_shrinks_at_corners_and_on_full_boards() {
+        assert_eq!(get_start_area(9, 9, 10, 1, 1).len(), 4);
+        assert_eq!(get_start_area(9, 9, 10, 1, 5).len(), 6);
+        assert_eq!(get_start_area(9, 9, 75, 5, 5), vec![(5, 5)]);
+    }
+
+    #[test]
+    fn first_reveal_is_never_a_mine() {
+        for seed in 0..50 {
+            let mut board = Board::new(9, 9, 10, seed);
+            assert_eq!(board.apply(Action::Reveal, 5, 5), Outcome::Playing);
+            assert!(!board.is_mine(5, 5));
+            assert_eq!(board.state(5, 5), TileState::Revealed);
+            assert_eq!(board.mine_positions().len(), 10);
+        }
+    }
+
+    #[test]
+    fn reveal_floods_the_opening() {
+        // One mine in the corner: a click far from it opens everything else.
+        let mut board = board_with_mines(9, 9, &[(9, 9)]);
+        assert_eq!(board.apply(Action::Reveal, 1, 1), Outcome::Playing);
+        assert_eq!(board.num_revealed(), 80);
+        assert_eq!(board.state(9, 9), TileState::Hidden);
+    }
+
+    #[test]
+    fn reveal_of_a_number_opens_only_it() {
+        let mut board = board_with_mines(9, 9, &[(5, 5)]);
+        board.apply(Action::Reveal, 4, 4);
+        assert_eq!(board.num_revealed(), 1);
+        assert_eq!(board.get_num_mines_around(4, 4), 1);
+    }
+
+    #[test]
+    fn reveal_of_a_mine_is_a_defeat() {
+        let mut board = board_with_mines(9, 9, &[(5, 5)]);
+        assert_eq!(board.apply(Action::Reveal, 5, 5), Outcome::Defeated);
+        assert_eq!(board.state(5, 5), TileState::Exploded);
+        // Nothing moves after the game ends.
+        assert_eq!(board.apply(Action::Reveal, 1, 1), Outcome::Defeated);
+        assert_eq!(board.state(1, 1), TileState::Hidden);
+    }
+
+    #[test]
+    fn flag_toggles_and_blocks_reveal() {
+        let mut board = board_with_mines(9, 9, &[(5, 5)]);
+        board.apply(Action::Flag, 1, 1);
+        assert_eq!(board.state(1, 1), TileState::Flagged);
+        assert_eq!(board.num_flagged(), 1);
+        board.apply(Action::Reveal, 1, 1);
+        assert_eq!(board.state(1, 1), TileState::Flagged);
+        board.apply(Action::Flag, 1, 1);
+        assert_eq!(board.state(1, 1), TileState::Hidden);
+    }
+
+    #[test]
+    fn chord_opens_around_matching_flags() {
+        let mut board = board_with_mines(9, 9, &[(5, 5), (1, 9)]);
+        board.apply(Action::Reveal, 4, 4);
+        // Not enough flags: nothing happens.
+        board.apply(Action::Chord, 4, 4);
+        assert_eq!(board.num_revealed(), 1);
+
+        board.apply(Action::Flag, 5, 5);
+        assert_eq!(board.apply(Action::Chord, 4, 4), Outcome::Playing);
+        assert_eq!(board.state(3, 3), TileState::Revealed);
+        assert_eq!(board.state(5, 5), TileState::Flagged);
+    }
+
+    #[test]
+    fn chord_with_a_wrong_flag_is_a_defeat() {
+        let mut board = board_with_mines(9, 9, &[(5, 5)]);
+        board.apply(Action::Reveal, 4, 4);
+        board.apply(Action::Flag, 3, 3);
+        assert_eq!(board.apply(Action::Chord, 4, 4), Outcome::Defeated);
+        assert_eq!(board.state(3, 3), TileState::WrongFlagged);
+        assert_eq!(board.state(5, 5), TileState::Exploded);
+    }
+
+    #[test]
+    fn out_of_bounds_moves_are_ignored() {
+        let mut board = Board::new(9, 9, 10, 1);
+        assert_eq!(board.apply(Action::Reveal, 0, 5), Outcome::Playing);
+        assert_eq!(board.apply(Action::Reveal, 10, 5), Outcome::Playing);
+        assert!(!board.is_planted);
+    }
+
+    #[test]
+    fn win_needs_every_cell_revealed_or_flagged() {
+        let mut board = board_with_mines(9, 9, &[(1, 1), (9, 9)]);
+        board.apply(Action::Reveal, 5, 5);
+        assert_eq!(board.outcome(), Outcome::Playing);
+        board.apply(Action::Flag, 1, 1);
+        assert_eq!(board.outcome(), Outcome::Playing);
+        assert_eq!(board.apply(Action::Flag, 9, 9), Outcome::Win);
+    }
+
+    #[test]
+    fn three_bv_counts_openings_and_lone_numbers() {
+        // One opening for the whole board around a corner mine.
+        assert_eq!(board_with_mines(9, 9, &[(9, 9)]).get_3bv(), 1);
+        // A wall of mines down the middle column splits the board into two openings.
+        let wall: Vec<_> = (1..=9).map(|y| (5, y)).collect();
+        assert_eq!(board_with_mines(9, 9, &wall).get_3bv(), 2);
+    }
+}
@@ -3,6 +3,8 @@ use crate::component::grid::Grid;
 use crate::component::cell::{Cell, CellState, QueryState};
 use crate::component::mine::TotalMine;
 use crate::system::state::GameState;
+use crate::system::seed::Seed;
+use crate::core::rules;
 
 pub fn reset(
     mut q_cells: Query<&mut Cell>,
@@ -31,6 +33,7 @@ pub fn first_click(
     mut grid: ResMut<Grid>,
     mut next_state: ResMut<NextState<GameState>>,
     mines: Res<TotalMine>,
+    seed: Res<Seed>,
 ) {
     let mut queue: Vec<(u32, u32)> = Vec::new();
     for cell in q_cells.iter_mut() {
@@ -44,7 +47,20 @@ pub fn first_click(
     }
 
     let (x, y) = queue.pop().unwrap();
-    grid.create_mine_positions(mines.0, Some((x, y)));
+    let layout = grid.next_layout.clone();
+    grid.is_layout = layout.is_some();
+    grid.is_safe_start = grid.wants_safe_start && !grid.is_layout;
+    if let Some(layout) = layout {
+        // The first click may hit a mine, so a scenario plays out the same every time.
+        grid.mine_positions = layout.into_iter().collect();
+    } else {
+        let exclude = if grid.is_safe_start {
+            rules::get_start_area(grid.width, grid.height, mines.0, x, y)
+        } else {
+            vec![(x, y)]
+        };
+        grid.create_mine_positions(mines.0, &exclude, seed.value);
+    }
     plant_mines_cells(&mut q_cells, &mut grid);
     next_state.set(GameState::Playing);
 }
@@ -187,7 +203,7 @@ fn update_querying_done_cell(
                 if open_others {
                     cell.is_opening = true;
                 }
-            } else if cell.state == CellState::Flagged {
+            } else if cell.state == CellState::Flagged || cell.state == CellState::Questioned {
                 if open_others {
                     cell.is_opening = true;
                 }
@@ -293,3 +309,186 @@ pub fn texture_for_ready(
 ) {
     update_cells_texture(&mut q_cells);
 }
+
+// The simulator, the terminal game and the replay verifier play by `rules::Board`, so the cells
+// have to agree with it move for move.
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::schedule::Schedule;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::core::rules::{Action, Board, Outcome, TileState};
+    use crate::system::mouse;
+
+    struct Game {
+        world: World,
+        first_click: Schedule,
+        update: Schedule,
+        state: GameState,
+    }
+
+    impl Game {
+        fn new(width: u32, height: u32, num_mines: u32, seed: u64) -> Self {
+            let mut world = World::new();
+            let mut grid = Grid::new();
+            grid.init(width, height);
+            for x in 1..=width {
+                for y in 1..=height {
+                    let entity = world.spawn(Cell::new(x, y, false, 0)).id();
+                    grid.add_cell(x, y, entity);
+                }
+            }
+            world.insert_resource(grid);
+            world.insert_resource(TotalMine(num_mines));
+            world.insert_resource(Seed::fixed(seed));
+            world.init_resource::<NextState<GameState>>();
+
+            let mut first_click = Schedule::new();
+            first_click.add_systems(super::first_click);
+            let mut update = Schedule::new();
+            update.add_systems(super::update);
+            Self { world, first_click, update, state: GameState::Ready }
+        }
+
+        fn run(&mut self) {
+            if self.state == GameState::Ready {
+                self.first_click.run(&mut self.world);
+                self.apply_state();
+            }
+            if self.state == GameState::Playing {
+                self.update.run(&mut self.world);
+                self.apply_state();
+            }
+        }
+
+        fn apply_state(&mut self) {
+            if let Some(state) = self.world.resource_mut::<NextState<GameState>>().0.take() {
+                self.state = state;
+            }
+        }
+
+        fn cell(&mut self, x: u32, y: u32) -> Mut<'_, Cell> {
+            let entity = self.world.resource::<Grid>().find_cell(x, y).unwrap();
+            self.world.get_mut::<Cell>(entity).unwrap()
+        }
+
+        // A press and a release on separate frames, like the mouse and the bot.
+        fn left_click(&mut self, x: u32, y: u32) -> Option<Action> {
+            self.cell(x, y).left_pressed();
+            self.run();
+            let mut cell = self.cell(x, y);
+            let action = mouse::get_release_action(&cell);
+            cell.left_released();
+            self.run();
+            action
+        }
+
+        fn right_click(&mut self, x: u32, y: u32) -> Option<Action> {
+            let mut cell = self.cell(x, y);
+            let action = mouse::get_flag_action(&cell);
+            cell.right_just_pressed(false);
+            // The last flag wins on the next frame.
+            self.run();
+            action
+        }
+
+        fn get_outcome(&self) -> Outcome {
+            match self.state {
+                GameState::Win => Outcome::Win,
+                GameState::Defeated => Outcome::Defeated,
+                _ => Outcome::Playing,
+            }
+        }
+    }
+
+    fn assert_same(game: &mut Game, board: &Board, context: &str) {
+        assert_eq!(game.get_outcome(), board.outcome(), "{}", context);
+        let mut q_cells = game.world.query::<&Cell>();
+        for cell in q_cells.iter(&game.world) {
+            assert_eq!(cell.state.to_tile(), board.state(cell.x, cell.y), "{} at ({}, {})", context, cell.x, cell.y);
+            if board.is_planted {
+                assert_eq!(cell.is_mine, board.is_mine(cell.x, cell.y), "{} at ({}, {})", context, cell.x, cell.y);
+            }
+        }
+    }
+
+    // Clicks that make progress: reveals of free cells, flags on mines and chords around numbers,
+    // mixed with random clicks that hit mines and misplace flags.
+    fn pick_click(rng: &mut StdRng, board: &Board) -> (bool, u32, u32) {
+        let random = (rng.gen_range(1..=board.width), rng.gen_range(1..=board.height));
+        let cells: Vec<(u32, u32)> = (1..=board.width).flat_map(|x| (1..=board.height).map(move |y| (x, y))).collect();
+        let pick = |rng: &mut StdRng, filter: &dyn Fn(&(u32, u32)) -> bool| {
+            let candidates: Vec<_> = cells.iter().copied().filter(filter).collect();
+            if candidates.is_empty() {
+                random
+            } else {
+                candidates[rng.gen_range(0..candidates.len())]
+            }
+        };
+        let (is_left, (x, y)) = match rng.gen_range(0..60) {
+            0 => (true, random),
+            1 => (false, random),
+            2..=19 if board.is_planted => (false, pick(rng, &|(x, y)| board.state(*x, *y) == TileState::Hidden && board.is_mine(*x, *y))),
+            20..=34 => (true, pick(rng, &|(x, y)| board.state(*x, *y) == TileState::Revealed && board.get_num_mines_around(*x, *y) != 0)),
+            _ => (true, pick(rng, &|(x, y)| board.state(*x, *y) == TileState::Hidden && !(board.is_planted && board.is_mine(*x, *y)))),
+        };
+        (is_left, x, y)
+    }
+
+    #[test]
+    fn cells_play_by_the_rules_of_board() {
+        let mut outcomes = [0; 3];
+        for (width, height, num_mines) in [(9, 9, 10), (16, 16, 40), (30, 16, 99)] {
+            for seed in 0..40 {
+                let mut game = Game::new(width, height, num_mines, seed);
+                let mut board = Board::new(width, height, num_mines, seed);
+                let mut rng = StdRng::seed_from_u64(seed);
+                for step in 0..1000 {
+                    let (is_left, x, y) = pick_click(&mut rng, &board);
+                    let action = if is_left {
+                        game.left_click(x, y)
+                    } else {
+                        game.right_click(x, y)
+                    };
+                    if let Some(action) = action {
+                        board.apply(action, x, y);
+                    }
+                    assert_same(&mut game, &board, &format!("{}x{}x{} seed {} step {} {:?}", width, height, num_mines, seed, step, action));
+                    if board.outcome() != Outcome::Playing {
+                        break;
+                    }
+                }
+                outcomes[board.outcome() as usize] += 1;
+            }
+        }
+        // Both endings come up, so the comparison covers them.
+        assert!(outcomes[Outcome::Win as usize] > 0 && outcomes[Outcome::Defeated as usize] > 0, "{:?}", outcomes);
+    }
+
+    #[test]
+    fn next_layout_is_planted_under_the_first_click() {
+        let mut game = Game::new(9, 9, 2, 0);
+        game.world.resource_mut::<Grid>().next_layout = Some(vec![(1, 1), (5, 5)]);
+        game.left_click(1, 1);
+        let grid = game.world.resource::<Grid>();
+        assert!(grid.is_layout && !grid.is_safe_start);
+        assert_eq!(grid.mine_positions, [(1, 1), (5, 5)].into_iter().collect());
+        assert_eq!(game.state, GameState::Defeated);
+    }
+
+    #[test]
+    fn safe_start_keeps_the_start_area_free() {
+        for seed in 0..20 {
+            let mut game = Game::new(9, 9, 10, seed);
+            game.world.resource_mut::<Grid>().wants_safe_start = true;
+            game.left_click(5, 5);
+            let grid = game.world.resource::<Grid>();
+            assert!(grid.is_safe_start);
+            for position in rules::get_start_area(9, 9, 10, 5, 5) {
+                assert!(!grid.mine_positions.contains(&position), "seed {} at {:?}", seed, position);
+            }
+        }
+    }
+}
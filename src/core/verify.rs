@@ -0,0 +1,195 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+use crate::core::rules::{self, Board, Move, Outcome};
+
+// The game timer stops a frame or two after the winning click, so the reported time may trail it.
+pub const TIME_TOLERANCE_MS: u64 = 1000;
+// Well above what a human sustains, so only scripted logs trip it.
+pub const MAX_MOVES_PER_SEC: u64 = 20;
+
+/// A leaderboard submission: enough to regenerate the board and replay the game on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    pub difficulty: String,
+    pub seed: u64,
+    pub time_ms: u64,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    UnknownDifficulty(String),
+    EmptyLog,
+    OutOfOrder(usize),
+    OutOfBounds(usize),
+    NotWon,
+    TooFast { num_moves: usize, time_ms: u64 },
+    TimeMismatch { reported_ms: u64, replayed_ms: u64 },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::UnknownDifficulty(difficulty) => write!(f, "unknown difficulty {}", difficulty),
+            VerifyError::EmptyLog => write!(f, "the move log is empty"),
+            VerifyError::OutOfOrder(index) => write!(f, "move {} is earlier than the one before it", index),
+            VerifyError::OutOfBounds(index) => write!(f, "move {} is outside the board", index),
+            VerifyError::NotWon => write!(f, "the moves do not clear the board"),
+            VerifyError::TooFast { num_moves, time_ms } => write!(f, "{} moves in {} ms is too fast", num_moves, time_ms),
+            VerifyError::TimeMismatch { reported_ms, replayed_ms } => {
+                write!(f, "reported {} ms but the replay took {} ms", reported_ms, replayed_ms)
+            },
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Replays the submission on the board its seed generates and returns the time of the winning move.
+/// Moves recorded after the win are ignored, the same as the game ignores clicks on a cleared board.
+pub fn verify(submission: &Submission) -> Result<u64, VerifyError> {
+    let (width, height, num_mines) = rules::get_difficulty_size(&submission.difficulty)
+        .ok_or_else(|| VerifyError::UnknownDifficulty(submission.difficulty.clone()))?;
+    if submission.moves.is_empty() {
+        return Err(VerifyError::EmptyLog);
+    }
+
+    let mut board = Board::new(width, height, num_mines, submission.seed);
+    let mut last_time_ms = 0;
+    let mut num_moves = 0;
+    for (index, player_move) in submission.moves.iter().enumerate() {
+        if player_move.time_ms < last_time_ms {
+            return Err(VerifyError::OutOfOrder(index));
+        }
+        if !board.contains(player_move.x, player_move.y) {
+            return Err(VerifyError::OutOfBounds(index));
+        }
+        last_time_ms = player_move.time_ms;
+        num_moves += 1;
+        if board.apply(player_move.action, player_move.x, player_move.y) != Outcome::Playing {
+            break;
+        }
+    }
+
+    if board.outcome() != Outcome::Win {
+        return Err(VerifyError::NotWon);
+    }
+    // The first move starts the clock, so only the ones after it count against the time.
+    if (num_moves as u64 - 1) * 1000 > last_time_ms.max(1) * MAX_MOVES_PER_SEC {
+        return Err(VerifyError::TooFast { num_moves, time_ms: last_time_ms });
+    }
+    if submission.time_ms.abs_diff(last_time_ms) > TIME_TOLERANCE_MS {
+        return Err(VerifyError::TimeMismatch { reported_ms: submission.time_ms, replayed_ms: last_time_ms });
+    }
+    Ok(last_time_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::rules::Action;
+
+    const SEED: u64 = 1234;
+    const MOVE_INTERVAL_MS: u64 = 200;
+
+    // Opens the top left corner, then flags every mine and reveals every free cell still hidden.
+    fn winning_submission(seed: u64) -> Submission {
+        let (width, height, num_mines) = rules::get_difficulty_size("Easy").unwrap();
+        let mut board = Board::new(width, height, num_mines, seed);
+        let mut moves = Vec::new();
+        let mut play = |board: &mut Board, action: Action, x: u32, y: u32| {
+            moves.push(Move { time_ms: moves.len() as u64 * MOVE_INTERVAL_MS, x, y, action });
+            board.apply(action, x, y)
+        };
+        play(&mut board, Action::Reveal, 1, 1);
+        for y in 1..=height {
+            for x in 1..=width {
+                if board.state(x, y) != rules::TileState::Hidden {
+                    continue;
+                }
+                let action = if board.is_mine(x, y) { Action::Flag } else { Action::Reveal };
+                play(&mut board, action, x, y);
+            }
+        }
+        assert_eq!(board.outcome(), Outcome::Win);
+        let time_ms = moves.last().unwrap().time_ms;
+        Submission { difficulty: "Easy".to_string(), seed, time_ms, moves }
+    }
+
+    #[test]
+    fn valid_replay_returns_the_replayed_time() {
+        let mut submission = winning_submission(SEED);
+        let replayed_ms = submission.time_ms;
+        assert_eq!(verify(&submission), Ok(replayed_ms));
+
+        submission.time_ms += TIME_TOLERANCE_MS;
+        assert_eq!(verify(&submission), Ok(replayed_ms));
+    }
+
+    #[test]
+    fn tampered_move_is_rejected() {
+        let mut submission = winning_submission(SEED);
+        let index = submission.moves.iter().rposition(|m| m.action == Action::Flag).unwrap();
+        submission.moves[index].action = Action::Reveal;
+        assert_eq!(verify(&submission), Err(VerifyError::NotWon));
+
+        let mut submission = winning_submission(SEED);
+        submission.moves.pop();
+        assert_eq!(verify(&submission), Err(VerifyError::NotWon));
+    }
+
+    #[test]
+    fn wrong_seed_is_rejected() {
+        let mut submission = winning_submission(SEED);
+        submission.seed = SEED + 1;
+        assert_eq!(verify(&submission), Err(VerifyError::NotWon));
+    }
+
+    #[test]
+    fn time_outside_the_tolerance_is_rejected() {
+        let mut submission = winning_submission(SEED);
+        let replayed_ms = submission.time_ms;
+        for reported_ms in [replayed_ms + TIME_TOLERANCE_MS + 1, replayed_ms - TIME_TOLERANCE_MS - 1] {
+            submission.time_ms = reported_ms;
+            assert_eq!(verify(&submission), Err(VerifyError::TimeMismatch { reported_ms, replayed_ms }));
+        }
+    }
+
+    #[test]
+    fn move_rate_above_the_maximum_is_rejected() {
+        let mut submission = winning_submission(SEED);
+        let num_moves = submission.moves.len();
+        // Exactly the maximum rate passes, one millisecond less does not.
+        let interval_ms = 1000 / MAX_MOVES_PER_SEC;
+        for (index, player_move) in submission.moves.iter_mut().enumerate() {
+            player_move.time_ms = index as u64 * interval_ms;
+        }
+        let time_ms = (num_moves as u64 - 1) * interval_ms;
+        submission.time_ms = time_ms;
+        assert_eq!(verify(&submission), Ok(time_ms));
+
+        submission.moves.last_mut().unwrap().time_ms -= 1;
+        submission.time_ms = time_ms - 1;
+        assert_eq!(verify(&submission), Err(VerifyError::TooFast { num_moves, time_ms: time_ms - 1 }));
+    }
+
+    #[test]
+    fn malformed_logs_are_rejected() {
+        let mut submission = winning_submission(SEED);
+        submission.difficulty = "Huge".to_string();
+        assert_eq!(verify(&submission), Err(VerifyError::UnknownDifficulty("Huge".to_string())));
+
+        let mut submission = winning_submission(SEED);
+        submission.moves[2].time_ms = 0;
+        submission.moves[1].time_ms = 1;
+        assert_eq!(verify(&submission), Err(VerifyError::OutOfOrder(2)));
+
+        let mut submission = winning_submission(SEED);
+        submission.moves[1].x = 0;
+        assert_eq!(verify(&submission), Err(VerifyError::OutOfBounds(1)));
+
+        submission.moves.clear();
+        assert_eq!(verify(&submission), Err(VerifyError::EmptyLog));
+    }
+}
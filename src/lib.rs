@@ -0,0 +1,86 @@
+#[cfg(feature = "client")]
+pub mod asset {
+    pub mod loader;
+    pub mod skin;
+    pub mod texture_type;
+}
+
+#[cfg(feature = "client")]
+pub mod component {
+    pub mod cell;
+    pub mod smile;
+    pub mod number;
+    pub mod mine;
+    pub mod grid;
+    pub mod frame;
+    pub mod ghost;
+}
+
+#[cfg(feature = "client")]
+pub mod system {
+    pub mod game;
+    pub mod state;
+    pub mod difficulty;
+    pub mod mouse;
+    pub mod timer;
+    pub mod egui;
+    pub mod window;
+    pub mod uuid;
+    pub mod config;
+    pub mod settings;
+    pub mod auth;
+    pub mod identity;
+    pub mod firestore;
+    pub mod backend;
+    pub mod outbox;
+    pub mod network;
+    pub mod period;
+    pub mod profile;
+    pub mod name;
+    pub mod session;
+    pub mod guest;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod oauth;
+    pub mod seed;
+    pub mod replay;
+    pub mod ghost;
+    pub mod lobby;
+    pub mod race;
+    pub mod coop;
+    pub mod spectate;
+    pub mod bot;
+    pub mod sound;
+    pub mod skin;
+    pub mod layout;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod cli;
+}
+
+pub mod utils {
+    pub mod yaml;
+    pub mod storage;
+}
+
+pub mod core {
+    pub mod rules;
+    pub mod player;
+    pub mod verify;
+    pub mod layout;
+    #[cfg(feature = "client")]
+    pub mod init {
+        pub mod camera;
+        pub mod grid;
+    }
+    #[cfg(feature = "client")]
+    pub mod update {
+        pub mod cells;
+        pub mod mines;
+        pub mod smiles;
+        pub mod time;
+    }
+}
+
+pub mod net {
+    pub mod protocol;
+    pub mod socket;
+}
@@ -1,54 +1,40 @@
-pub mod asset {
-    pub mod loader;
-    pub mod texture_type;
-}
-
-pub mod component {
-    pub mod cell;
-    pub mod smile;
-    pub mod number;
-    pub mod mine;
-    pub mod grid;
-    pub mod frame;
-}
-
-pub mod system {
-    pub mod game;
-    pub mod state;
-    pub mod difficulty;
-    pub mod mouse;
-    pub mod timer;
-    pub mod egui;
-    pub mod window;
-    pub mod uuid;
-    pub mod auth;
-    pub mod firestore;
-}
-
-pub mod utils {
-    pub mod yaml;
-}
+use bevy::prelude::*;
+use minesweeper::system;
 
-pub mod core {
-    pub mod init {
-        pub mod camera;
-        pub mod grid;
-    }
-    pub mod update {
-        pub mod cells;
-        pub mod mines;
-        pub mod smiles;
-        pub mod time;
+fn main() {
+    // Checked before the window opens, so it also runs where there is no display.
+    #[cfg(not(target_arch = "wasm32"))]
+    let cli = system::cli::Cli::from_env();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = cli.validate.as_ref() {
+        std::process::exit(system::cli::validate(path));
     }
-}
 
-use bevy::prelude::*;
-fn main() {
-    App::new()
+    let mut app = App::new();
+    app
         .add_plugins(DefaultPlugins)
+        .add_plugins(system::settings::SettingsPlugin);
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_plugins(system::cli::CliPlugin(cli));
+
+    app
+        .add_plugins(system::config::ConfigPlugin)
         .add_plugins(system::game::GamePlugin)
+        .add_plugins(system::skin::SkinPlugin)
+        .add_plugins(system::layout::LayoutPlugin)
         .add_plugins(system::egui::EguiMenuPlugin)
-        .add_plugins(system::auth::AuthPlugin)
+        .add_plugins(system::network::NetworkPlugin)
         .add_plugins(system::firestore::FirestorePlugin)
+        .add_plugins(system::profile::ProfilePlugin)
+        .add_plugins(system::name::NamePlugin)
+        .add_plugins(system::session::SessionPlugin)
+        .add_plugins(system::guest::GuestPlugin)
+        .add_plugins(system::ghost::GhostPlugin)
+        .add_plugins(system::lobby::LobbyPlugin)
+        .add_plugins(system::race::RacePlugin)
+        .add_plugins(system::coop::CoopPlugin)
+        .add_plugins(system::spectate::SpectatePlugin)
+        .add_plugins(system::bot::BotPlugin)
+        .add_plugins(system::sound::SoundPlugin)
         .run();
 }
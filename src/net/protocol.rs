@@ -0,0 +1,85 @@
+use serde::{Serialize, Deserialize};
+
+use crate::core::rules::{Action, TileState};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerStatus {
+    Waiting,
+    Playing,
+    Cleared,
+    Dead,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub id: u32,
+    pub name: String,
+    pub percent: f32,
+    pub status: PlayerStatus,
+    #[serde(default)]
+    pub is_streaming: bool,
+}
+
+// Mine details are only filled in once a tile is open, so hidden tiles give nothing away.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileUpdate {
+    pub x: u32,
+    pub y: u32,
+    pub state: TileState,
+    pub is_mine: bool,
+    pub num_mines_around: u32,
+    pub flagged_by: Option<u32>,
+}
+
+// A reset frame starts a new board; every other frame only carries the tiles that changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamFrame {
+    pub difficulty: String,
+    pub is_reset: bool,
+    pub time_ms: u64,
+    pub status: PlayerStatus,
+    pub tiles: Vec<TileUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientMessage {
+    Join { name: String },
+    StartRace { difficulty: String },
+    Progress { percent: f32, status: PlayerStatus },
+    StartCoop { difficulty: String },
+    // `flagged` is the flag state the player wants, so two players flagging the same tile agree.
+    Action { x: u32, y: u32, action: Action, flagged: bool },
+    Cursor { x: u32, y: u32 },
+    Stream { frame: StreamFrame },
+    StopStream,
+    Spectate { id: Option<u32> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ServerMessage {
+    Welcome { id: u32 },
+    Players { players: Vec<PlayerInfo> },
+    RaceStart { difficulty: String, seed: u64 },
+    RaceOver { winner: Option<u32> },
+    CoopStart { difficulty: String, seed: u64 },
+    Tiles { tiles: Vec<TileUpdate> },
+    Cursor { id: u32, x: u32, y: u32 },
+    CoopOver { is_win: bool, by: u32 },
+    Stream { id: u32, frame: StreamFrame },
+    StreamEnd { id: u32 },
+    Error { message: String },
+}
+
+pub fn to_text<T>(message: &T) -> String
+where T: Serialize {
+    serde_json::to_string(message).expect("Unable to serialize message")
+}
+
+pub fn from_text<T>(text: &str) -> Option<T>
+where T: for<'de> Deserialize<'de> {
+    serde_json::from_str(text).ok()
+}
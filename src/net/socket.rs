@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Message queue shared between the game and a background WebSocket connection.
+#[derive(Clone, Default)]
+pub struct SocketState {
+    pub inbox: Arc<Mutex<VecDeque<String>>>,
+    pub is_open: Arc<Mutex<bool>>,
+    pub is_closed: Arc<Mutex<bool>>,
+    /// Why the connection failed or closed, for the game to log and show.
+    pub error: Arc<Mutex<Option<String>>>,
+}
+
+impl SocketState {
+    pub fn poll(&self) -> Vec<String> {
+        self.inbox.lock().unwrap().drain(..).collect()
+    }
+
+    pub fn is_open(&self) -> bool {
+        *self.is_open.lock().unwrap()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        *self.is_closed.lock().unwrap()
+    }
+
+    pub fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap().take()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod platform {
+    use super::*;
+    use std::io::{Read, Write, ErrorKind};
+    use std::net::TcpStream;
+    use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+    use std::thread;
+    use std::time::Duration;
+    use tungstenite::{Message, WebSocket};
+
+    const READ_TIMEOUT: Duration = Duration::from_millis(20);
+
+    // Reads incoming text frames and writes queued outgoing ones until either side closes. The
+    // error is boxed, since only its text is shown.
+    pub fn pump<S>(
+        socket: &mut WebSocket<S>,
+        outgoing: &Receiver<String>,
+        mut on_message: impl FnMut(String),
+    ) -> Result<(), Box<tungstenite::Error>>
+    where S: Read + Write {
+        loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => on_message(text),
+                Ok(Message::Close(_)) => return Ok(()),
+                Ok(_) => {},
+                Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {},
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(Box::new(e)),
+            }
+
+            loop {
+                match outgoing.try_recv() {
+                    Ok(text) => socket.send(Message::Text(text))?,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        let _ = socket.close(None);
+                        return Ok(());
+                    },
+                }
+            }
+        }
+    }
+
+    pub fn set_read_timeout(stream: &TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))
+    }
+
+    pub struct Connection {
+        pub state: SocketState,
+        sender: Sender<String>,
+    }
+
+    impl Connection {
+        pub fn connect(address: &str) -> Self {
+            let state = SocketState::default();
+            let (sender, receiver) = channel::<String>();
+            let address = address.to_string();
+            let thread_state = state.clone();
+
+            thread::spawn(move || {
+                let result = TcpStream::connect(&address)
+                    .map_err(|e| Box::new(tungstenite::Error::Io(e)))
+                    .and_then(|stream| {
+                        set_read_timeout(&stream).map_err(|e| Box::new(tungstenite::Error::Io(e)))?;
+                        tungstenite::client(format!("ws://{}", address), stream)
+                            .map_err(|e| Box::new(match e {
+                                tungstenite::HandshakeError::Failure(e) => e,
+                                tungstenite::HandshakeError::Interrupted(_) => tungstenite::Error::ConnectionClosed,
+                            }))
+                    });
+
+                match result {
+                    Ok((mut socket, _)) => {
+                        *thread_state.is_open.lock().unwrap() = true;
+                        let inbox = thread_state.inbox.clone();
+                        if let Err(e) = pump(&mut socket, &receiver, |text| inbox.lock().unwrap().push_back(text)) {
+                            *thread_state.error.lock().unwrap() = Some(e.to_string());
+                        }
+                    },
+                    Err(e) => *thread_state.error.lock().unwrap() = Some(e.to_string()),
+                }
+                *thread_state.is_open.lock().unwrap() = false;
+                *thread_state.is_closed.lock().unwrap() = true;
+            });
+
+            Self { state, sender }
+        }
+
+        pub fn send(&self, text: String) {
+            let _ = self.sender.send(text);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub mod platform {
+    use super::*;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::closure::Closure;
+
+    #[wasm_bindgen]
+    extern "C" {
+        fn socket_connect_js(url: &str, on_open: &Closure<dyn FnMut()>, on_message: &Closure<dyn FnMut(String)>, on_close: &Closure<dyn FnMut()>) -> u32;
+        fn socket_send_js(handle: u32, text: &str);
+        fn socket_close_js(handle: u32);
+    }
+
+    pub struct Connection {
+        pub state: SocketState,
+        handle: u32,
+        _callbacks: (Closure<dyn FnMut()>, Closure<dyn FnMut(String)>, Closure<dyn FnMut()>),
+    }
+
+    // The browser runs everything on one thread, so the JS callbacks never race the game loop.
+    unsafe impl Send for Connection {}
+    unsafe impl Sync for Connection {}
+
+    impl Connection {
+        pub fn connect(address: &str) -> Self {
+            let state = SocketState::default();
+
+            let is_open = state.is_open.clone();
+            let on_open = Closure::<dyn FnMut()>::new(move || {
+                *is_open.lock().unwrap() = true;
+            });
+            let inbox = state.inbox.clone();
+            let on_message = Closure::<dyn FnMut(String)>::new(move |text: String| {
+                inbox.lock().unwrap().push_back(text);
+            });
+            let is_open = state.is_open.clone();
+            let is_closed = state.is_closed.clone();
+            let on_close = Closure::<dyn FnMut()>::new(move || {
+                *is_open.lock().unwrap() = false;
+                *is_closed.lock().unwrap() = true;
+            });
+
+            let handle = socket_connect_js(&format!("ws://{}", address), &on_open, &on_message, &on_close);
+            Self {
+                state,
+                handle,
+                _callbacks: (on_open, on_message, on_close),
+            }
+        }
+
+        pub fn send(&self, text: String) {
+            socket_send_js(self.handle, &text);
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            socket_close_js(self.handle);
+        }
+    }
+}
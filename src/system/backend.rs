@@ -0,0 +1,491 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+
+use crate::core::verify::Submission;
+use crate::system::config::Config;
+use crate::system::identity::IdentityProvider;
+use crate::system::firestore::RankingData;
+use crate::system::period::RankingRange;
+use crate::utils::storage;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendError {
+    /// The backend could not be reached or refused the request.
+    Connection(String),
+    /// The backend answered with something that is not what was asked for.
+    InvalidData(String),
+    /// Local storage could not be read or written.
+    Storage(String),
+    /// Something unique, like a display name, belongs to someone else.
+    Conflict(String),
+    /// The backend refused the request, e.g. by its rules, and will refuse it again.
+    Rejected(String),
+}
+
+impl BackendError {
+    /// Whether the same request may succeed later, once the network or the backend is back.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, BackendError::Conflict(_) | BackendError::Rejected(_))
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::Connection(e) => write!(f, "Connection failed: {}", e),
+            BackendError::InvalidData(e) => write!(f, "Invalid data: {}", e),
+            BackendError::Storage(e) => write!(f, "Storage failed: {}", e),
+            BackendError::Conflict(e) => write!(f, "Already taken: {}", e),
+            BackendError::Rejected(e) => write!(f, "Rejected: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+pub type BackendResult<T> = Result<T, BackendError>;
+
+// Native tasks run on the tokio thread pool, browser tasks on the single JS thread.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BackendFuture<T> = Pin<Box<dyn Future<Output = BackendResult<T>> + Send>>;
+#[cfg(target_arch = "wasm32")]
+pub type BackendFuture<T> = Pin<Box<dyn Future<Output = BackendResult<T>>>>;
+
+/// One leaderboard: the entries of a difficulty, fastest first, made within `range` when given.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RankingQuery {
+    pub difficulty: String,
+    pub is_verified_only: bool,
+    pub range: Option<RankingRange>,
+}
+
+impl RankingQuery {
+    pub fn is_match(&self, data: &RankingData) -> bool {
+        data.difficulty == self.difficulty
+            && (data.verified || !self.is_verified_only)
+            && self.range.iter().all(|range| range.contains(data.created_at))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RankingPage {
+    pub entries: Vec<RankingData>,
+    /// No entries come after this page.
+    pub is_last: bool,
+    /// The leaderboard is ranked from only part of its entries, see `RankingPeriod`.
+    #[serde(default)]
+    pub is_truncated: bool,
+}
+
+impl RankingPage {
+    /// Sorts the entries of a whole leaderboard and takes the page of `limit` after `after`.
+    pub fn from_entries(mut entries: Vec<RankingData>, after: Option<RankingData>, limit: u32) -> Self {
+        entries.sort();
+        entries.retain(|data| after.iter().all(|after| data > after));
+        let is_last = entries.len() <= limit as usize;
+        entries.truncate(limit as usize);
+        Self { entries, is_last, is_truncated: false }
+    }
+
+    /// The page of `limit` after `after` on a period leaderboard.
+    pub fn from_period(period: RankingPeriod, after: Option<RankingData>, limit: u32) -> Self {
+        Self {
+            is_truncated: period.is_truncated,
+            ..Self::from_entries(period.entries, after, limit)
+        }
+    }
+}
+
+// Firestore cannot order by time with a range on created_at, so a period is read whole, up to
+// this many entries, and ranked on the client.
+pub const MAX_PERIOD_ENTRIES: u32 = 1000;
+// A period read serves its pages and the best entry for this long, like the cached pages.
+const PERIOD_CACHE_TTL_SEC: i64 = 60;
+
+/// The entries of a period leaderboard, the latest `MAX_PERIOD_ENTRIES` of them.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RankingPeriod {
+    pub entries: Vec<RankingData>,
+    /// The period holds more entries than were read, so earlier ones are left out of the ranks.
+    pub is_truncated: bool,
+}
+
+/// Period reads by leaderboard, so that the pages of a period and the best entry share one read.
+/// Periods that are over cannot change, and stay cached.
+#[derive(Clone, Default)]
+pub struct PeriodCache {
+    periods: Arc<Mutex<HashMap<RankingQuery, (i64, RankingPeriod)>>>,
+}
+
+impl PeriodCache {
+    pub fn get(&self, query: &RankingQuery) -> Option<RankingPeriod> {
+        let now = Utc::now().timestamp();
+        let is_past = query.range.is_some_and(|range| range.end <= now as u64);
+        self.periods.lock().unwrap().get(query)
+            .filter(|(read_at, _)| is_past || now - read_at < PERIOD_CACHE_TTL_SEC)
+            .map(|(_, period)| period.clone())
+    }
+
+    pub fn insert(&self, query: RankingQuery, period: RankingPeriod) {
+        self.periods.lock().unwrap().insert(query, (Utc::now().timestamp(), period));
+    }
+
+    /// Forgets the periods of `difficulty`, once a new entry is submitted to it.
+    pub fn expire(&self, difficulty: &str) {
+        self.periods.lock().unwrap().retain(|query, _| query.difficulty != difficulty);
+    }
+}
+
+/// The fastest entry of a user, with its place on the leaderboard counting from 1.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RankingBest {
+    pub entry: RankingData,
+    pub rank: u32,
+}
+
+impl RankingBest {
+    /// Finds the best of `id` among all the entries of a leaderboard. Equal times rank in the order
+    /// of the pages, the earlier entry first.
+    pub fn find(entries: &[RankingData], id: &str) -> Option<Self> {
+        let entry = entries.iter().filter(|data| data.id == id).min()?;
+        Some(Self {
+            entry: entry.clone(),
+            rank: entries.iter().filter(|data| *data < entry).count() as u32 + 1,
+        })
+    }
+}
+
+/// A logged in user: the opaque key their entries are stored under, and the display name shown for
+/// it once one is chosen. Email addresses never leave the login function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoginUser {
+    pub user_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Restores the login on the next launch without another trip through the browser.
+    #[serde(default)]
+    pub session: Option<String>,
+}
+
+/// A login to start in the browser.
+#[derive(Debug, Clone)]
+pub struct LoginRequest {
+    /// Ties the answer to this attempt, and names it to `cancel_login`.
+    pub state: String,
+    pub provider: IdentityProvider,
+    pub config: Config,
+    /// Where the backend puts the login page it opened, so that it can be opened again.
+    pub url: Arc<Mutex<Option<String>>>,
+}
+
+impl LoginRequest {
+    pub fn new(state: String, provider: IdentityProvider, config: Config) -> Self {
+        Self {
+            state,
+            provider,
+            config,
+            url: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+/// Entries a live leaderboard has seen added or changed, drained by the game every frame.
+pub type RankingSink = Arc<Mutex<Vec<RankingData>>>;
+
+/// Where finished games are submitted and the leaderboard is fetched from.
+pub trait RankingBackend: Send + Sync {
+    // Whether entries leave this machine, which decides if there is a connection to show.
+    fn is_remote(&self) -> bool {
+        true
+    }
+    /// Adds the entry of a win, marked as a guest's when `is_guest`.
+    fn submit(&self, user_id: String, name: String, is_guest: bool, submission: Submission) -> BackendFuture<()>;
+    /// Fetches up to `limit` entries ordered by time, starting after `after` when given, which is
+    /// the last entry of the previous page.
+    fn fetch_page(&self, query: RankingQuery, after: Option<RankingData>, limit: u32) -> BackendFuture<RankingPage>;
+    fn fetch_best(&self, query: RankingQuery, id: String) -> BackendFuture<Option<RankingBest>>;
+    /// Fetches every entry of a user on all difficulties, oldest first.
+    fn fetch_user(&self, id: String) -> BackendFuture<Vec<RankingData>>;
+    /// Pushes entries of the leaderboard created from now on into `sink`, again whenever they
+    /// change, until `unlisten` is called with the same `listen_id`.
+    fn listen(&self, listen_id: u32, query: RankingQuery, sink: RankingSink) -> BackendFuture<()>;
+    fn unlisten(&self, listen_id: u32);
+}
+
+/// Connects once at startup, then opens the login page in the browser and resolves to the user
+/// once the login comes back.
+pub trait AuthBackend: Send + Sync {
+    // Whether logins go through a provider, rather than resolving at once to the local user.
+    fn is_remote(&self) -> bool {
+        true
+    }
+    fn connect(&self) -> BackendFuture<()>;
+    fn listen_login(&self, request: LoginRequest) -> BackendFuture<LoginUser>;
+    /// Stops the `listen_login` of the request with `state`, which then fails.
+    fn cancel_login(&self, state: String);
+    /// Resolves to the user of a session saved by an earlier login, or to `None` once it has
+    /// expired or ended.
+    fn restore_session(&self, session: String) -> BackendFuture<Option<LoginUser>>;
+    fn end_session(&self, session: String) -> BackendFuture<()>;
+    /// Makes `name` the display name of the user `session` belongs to, failing with `Conflict` when
+    /// another user has it. The backend checks the session, so nobody can name another user.
+    fn claim_name(&self, session: String, name: String) -> BackendFuture<()>;
+    /// Ties the guest to `token` before its first entry, so that only its holder can merge it.
+    /// Registering the same guest again succeeds.
+    fn register_guest(&self, guest_id: String, token: String) -> BackendFuture<()>;
+    /// Moves the entries of the guest to the user, under the name of the user or a pseudonym until
+    /// one is chosen. Fails with `Conflict` when `token` is not the one the guest was registered
+    /// with. Resolves to the number of entries moved.
+    fn merge_guest(&self, guest_id: String, token: String, user_id: String, name: Option<String>) -> BackendFuture<u32>;
+}
+
+#[derive(Resource, Clone)]
+pub struct BackendResource {
+    pub ranking: Arc<dyn RankingBackend>,
+    pub auth: Arc<dyn AuthBackend>,
+}
+
+impl BackendResource {
+    pub fn new<B>(backend: B) -> Self
+    where B: RankingBackend + AuthBackend + 'static {
+        let backend = Arc::new(backend);
+        Self {
+            ranking: backend.clone(),
+            auth: backend,
+        }
+    }
+}
+
+/// Keeps rankings in memory, and under a storage key when given one, for tests and offline play.
+/// Every login resolves at once to the same local user.
+pub struct LocalBackend {
+    user_id: String,
+    key: Option<String>,
+    rankings: Mutex<Vec<RankingData>>,
+    listeners: Mutex<Vec<(u32, RankingQuery, RankingSink)>>,
+}
+
+impl LocalBackend {
+    pub fn new(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            key: None,
+            rankings: Mutex::new(Vec::new()),
+            listeners: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_storage(user_id: &str, key: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            key: Some(key.to_string()),
+            rankings: Mutex::new(storage::load_yaml::<Vec<RankingData>>(key).unwrap_or_default()),
+            listeners: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn get_entries(&self, query: &RankingQuery) -> Vec<RankingData> {
+        self.rankings.lock().unwrap().iter().filter(|data| query.is_match(data)).cloned().collect()
+    }
+
+    fn save(&self, rankings: &[RankingData]) -> BackendResult<()> {
+        match self.key.as_ref() {
+            Some(key) => storage::save_yaml(key, &rankings).map_err(BackendError::Storage),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RankingBackend for LocalBackend {
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    fn submit(&self, user_id: String, name: String, is_guest: bool, submission: Submission) -> BackendFuture<()> {
+        let mut rankings = self.rankings.lock().unwrap();
+        let entry = RankingData {
+            id: user_id,
+            name,
+            time: submission.time_ms as f32 / 1000.0,
+            difficulty: submission.difficulty,
+            created_at: Utc::now().timestamp() as u64,
+            verified: false,
+            guest: is_guest,
+            doc_id: uuid::Uuid::new_v4().to_string(),
+        };
+        for (_, query, sink) in self.listeners.lock().unwrap().iter() {
+            if query.is_match(&entry) {
+                sink.lock().unwrap().push(entry.clone());
+            }
+        }
+        rankings.push(entry);
+
+        let result = self.save(&rankings);
+        Box::pin(async move { result })
+    }
+
+    fn fetch_page(&self, query: RankingQuery, after: Option<RankingData>, limit: u32) -> BackendFuture<RankingPage> {
+        let page = RankingPage::from_entries(self.get_entries(&query), after, limit);
+        Box::pin(async move { Ok(page) })
+    }
+
+    fn fetch_best(&self, query: RankingQuery, id: String) -> BackendFuture<Option<RankingBest>> {
+        let best = RankingBest::find(&self.get_entries(&query), &id);
+        Box::pin(async move { Ok(best) })
+    }
+
+    fn fetch_user(&self, id: String) -> BackendFuture<Vec<RankingData>> {
+        let mut entries: Vec<RankingData> = self.rankings.lock().unwrap().iter()
+            .filter(|data| data.id == id)
+            .cloned()
+            .collect();
+        entries.sort_by_key(|data| data.created_at);
+        Box::pin(async move { Ok(entries) })
+    }
+
+    // Submissions feed the sinks directly, so there is nothing to wait for.
+    fn listen(&self, listen_id: u32, query: RankingQuery, sink: RankingSink) -> BackendFuture<()> {
+        self.listeners.lock().unwrap().push((listen_id, query, sink));
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn unlisten(&self, listen_id: u32) {
+        self.listeners.lock().unwrap().retain(|(id, _, _)| *id != listen_id);
+    }
+}
+
+impl AuthBackend for LocalBackend {
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    fn connect(&self) -> BackendFuture<()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn listen_login(&self, _request: LoginRequest) -> BackendFuture<LoginUser> {
+        let user = LoginUser {
+            user_id: self.user_id.clone(),
+            name: Some(self.user_id.clone()),
+            session: None,
+        };
+        Box::pin(async move { Ok(user) })
+    }
+
+    fn cancel_login(&self, _state: String) {}
+
+    // The local user logs in at once, so there is no session to keep.
+    fn restore_session(&self, _session: String) -> BackendFuture<Option<LoginUser>> {
+        Box::pin(async move { Ok(None) })
+    }
+
+    fn end_session(&self, _session: String) -> BackendFuture<()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    // There is only the one local user to name.
+    fn claim_name(&self, _session: String, _name: String) -> BackendFuture<()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    // Nobody else can reach these rankings, so any token will do.
+    fn register_guest(&self, _guest_id: String, _token: String) -> BackendFuture<()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn merge_guest(&self, guest_id: String, _token: String, user_id: String, name: Option<String>) -> BackendFuture<u32> {
+        let mut rankings = self.rankings.lock().unwrap();
+        let mut count = 0;
+        for data in rankings.iter_mut().filter(|data| data.id == guest_id) {
+            data.id = user_id.clone();
+            data.name = name.clone().unwrap_or_default();
+            data.guest = false;
+            count += 1;
+        }
+
+        let result = self.save(&rankings).map(|()| count);
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, time: f32, created_at: u64) -> RankingData {
+        RankingData {
+            id: id.to_string(),
+            name: id.to_string(),
+            time,
+            difficulty: "Easy".to_string(),
+            created_at,
+            verified: false,
+            guest: false,
+            doc_id: format!("{}-{}", id, created_at),
+        }
+    }
+
+    #[test]
+    fn best_is_the_fastest_entry_of_the_user() {
+        let entries = [entry("a", 12.0, 1), entry("b", 9.0, 2), entry("a", 10.0, 3), entry("c", 11.0, 4)];
+        let best = RankingBest::find(&entries, "a").unwrap();
+        assert_eq!((best.entry.time, best.entry.created_at, best.rank), (10.0, 3, 2));
+        assert!(RankingBest::find(&entries, "d").is_none());
+    }
+
+    #[test]
+    fn equal_times_rank_the_earlier_entry_first() {
+        let entries = [entry("late", 10.0, 20), entry("early", 10.0, 10), entry("fast", 9.0, 30)];
+        let ranks = ["fast", "early", "late"].map(|id| RankingBest::find(&entries, id).unwrap().rank);
+        assert_eq!(ranks, [1, 2, 3]);
+
+        let page = RankingPage::from_entries(entries.to_vec(), None, 10);
+        let ids = page.entries.iter().map(|entry| entry.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["fast", "early", "late"]);
+    }
+
+    #[test]
+    fn entries_of_the_same_second_page_by_document() {
+        let mut entries = vec![entry("c", 10.0, 5), entry("a", 10.0, 5), entry("b", 10.0, 5)];
+        entries[0].doc_id = "3".to_string();
+        entries[1].doc_id = "1".to_string();
+        entries[2].doc_id = "2".to_string();
+        assert_eq!(RankingBest::find(&entries, "b").unwrap().rank, 2);
+
+        let first = RankingPage::from_entries(entries.clone(), None, 2);
+        let second = RankingPage::from_entries(entries, first.entries.last().cloned(), 2);
+        let ids = first.entries.iter().chain(second.entries.iter()).map(|entry| entry.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert!(second.is_last);
+    }
+
+    #[test]
+    fn period_pages_keep_the_truncation() {
+        let period = RankingPeriod { entries: vec![entry("b", 9.0, 2), entry("a", 8.0, 1)], is_truncated: true };
+        let page = RankingPage::from_period(period, None, 1);
+        assert_eq!((page.entries[0].id.as_str(), page.is_last, page.is_truncated), ("a", false, true));
+    }
+
+    #[test]
+    fn period_cache_forgets_the_difficulty_of_a_submission() {
+        let cache = PeriodCache::default();
+        let query = |difficulty: &str| RankingQuery {
+            difficulty: difficulty.to_string(),
+            is_verified_only: false,
+            range: Some(RankingRange { start: 0, end: u64::MAX }),
+        };
+        cache.insert(query("Easy"), RankingPeriod::default());
+        cache.insert(query("Hard"), RankingPeriod::default());
+        assert!(cache.get(&query("Easy")).is_some());
+
+        cache.expire("Easy");
+        assert!(cache.get(&query("Easy")).is_none());
+        assert!(cache.get(&query("Hard")).is_some());
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+
+use crate::component::cell::{Cell, CellState};
+use crate::component::grid::Grid;
+use crate::component::mine::TotalMine;
+use crate::core::player::{Player, SolverBot, VisibleBoard, VisibleTile};
+use crate::core::rules::Action;
+use crate::system::mouse::{self, InputMode};
+use crate::system::replay::{self, MoveEvent};
+use crate::system::state::GameState;
+
+pub const MIN_MOVES_PER_SEC: f32 = 1.0;
+pub const MAX_MOVES_PER_SEC: f32 = 30.0;
+const RESTART_DELAY_SEC: f32 = 2.0;
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BotResource>()
+            .add_systems(OnEnter(GameState::Ready), reset_bot)
+            .add_systems(Update, play_bot
+                .after(mouse::mouse_events_system)
+                .before(replay::record));
+    }
+}
+
+#[derive(Resource)]
+pub struct BotResource {
+    pub is_enabled: bool,
+    pub is_auto_restart: bool,
+    pub moves_per_sec: f32,
+    pub is_used: bool,
+    player: Box<dyn Player + Send + Sync>,
+    elapsed: f32,
+    pressed: Option<(u32, u32)>,
+}
+
+impl Default for BotResource {
+    fn default() -> Self {
+        Self {
+            is_enabled: false,
+            is_auto_restart: false,
+            moves_per_sec: 5.0,
+            is_used: false,
+            player: Box::new(SolverBot::new(rand::random())),
+            elapsed: 0.0,
+            pressed: None,
+        }
+    }
+}
+
+impl BotResource {
+    pub fn get_player_name(&self) -> &str {
+        self.player.get_name()
+    }
+
+    pub fn set_player(&mut self, player: Box<dyn Player + Send + Sync>) {
+        self.player = player;
+        self.pressed = None;
+    }
+}
+
+// Games the bot touched are kept off the leaderboard and the personal bests.
+pub fn run_if_unassisted(
+    bot: Res<BotResource>,
+) -> bool {
+    !bot.is_used
+}
+
+pub fn reset_bot(
+    mut bot: ResMut<BotResource>,
+) {
+    bot.is_used = false;
+    bot.elapsed = 0.0;
+    bot.pressed = None;
+}
+
+fn get_visible_board(
+    grid: &Grid,
+    num_mines: u32,
+    q_cells: &Query<&mut Cell>,
+) -> VisibleBoard {
+    let mut board = VisibleBoard::new(grid.width, grid.height, num_mines);
+    for cell in q_cells.iter() {
+        let tile = match cell.state {
+            CellState::Hidden | CellState::Pressed | CellState::Questioned => VisibleTile::Hidden,
+            CellState::Flagged | CellState::WrongFlagged => VisibleTile::Flagged,
+            CellState::Revealed | CellState::Exploded => VisibleTile::Revealed(cell.num_mines_around),
+        };
+        board.set(cell.x, cell.y, tile);
+    }
+    board
+}
+
+// A reveal or chord is pressed on one step and released on the next, so the move is visible
+// the same way a mouse click is.
+pub fn play_bot(
+    mut bot: ResMut<BotResource>,
+    time: Res<Time>,
+    input_mode: Res<InputMode>,
+    (current_state, mut next_state): (Res<State<GameState>>, ResMut<NextState<GameState>>),
+    (grid, mines): (Res<Grid>, Res<TotalMine>),
+    mut q_cells: Query<&mut Cell>,
+    mut move_events: EventWriter<MoveEvent>,
+) {
+    if !bot.is_enabled || *input_mode == InputMode::Disabled {
+        return;
+    }
+    bot.elapsed += time.delta_seconds();
+
+    match current_state.get() {
+        GameState::Init => return,
+        GameState::Ready | GameState::Playing => {},
+        GameState::Win | GameState::Defeated => {
+            if bot.is_auto_restart && *input_mode == InputMode::Local && bot.elapsed >= RESTART_DELAY_SEC {
+                next_state.set(GameState::Ready);
+            }
+            return;
+        },
+    }
+
+    let interval = 0.5 / bot.moves_per_sec.clamp(MIN_MOVES_PER_SEC, MAX_MOVES_PER_SEC);
+    if bot.elapsed < interval {
+        return;
+    }
+    bot.elapsed = 0.0;
+
+    if let Some((x, y)) = bot.pressed.take() {
+        let Some(entity) = grid.find_cell(x, y) else {
+            return;
+        };
+        if let Ok(mut cell) = q_cells.get_mut(entity) {
+            if cell.is_left_pressed {
+                mouse::release_cell(&mut cell, &input_mode, &mut move_events);
+            }
+        }
+        return;
+    }
+
+    let board = get_visible_board(&grid, mines.0, &q_cells);
+    let Some(player_move) = bot.player.next_move(&board) else {
+        return;
+    };
+    let Some(entity) = grid.find_cell(player_move.x, player_move.y) else {
+        return;
+    };
+    let Ok(mut cell) = q_cells.get_mut(entity) else {
+        return;
+    };
+
+    bot.is_used = true;
+    match player_move.action {
+        Action::Flag => mouse::flag_cell(&mut cell, &input_mode, false, &mut move_events),
+        Action::Reveal | Action::Chord => {
+            cell.left_pressed();
+            bot.pressed = Some((player_move.x, player_move.y));
+        },
+    }
+}
@@ -0,0 +1,305 @@
+use bevy::prelude::*;
+use std::fs;
+use std::process;
+
+use crate::core::layout::Layout;
+use crate::core::verify::{self, Submission};
+use crate::system::backend::{BackendResource, LocalBackend};
+use crate::system::bot::BotResource;
+use crate::system::config;
+use crate::system::difficulty::Difficulty;
+use crate::system::egui::{IsLobbyOpen, IsRankingOpen, IsSettingsOpen};
+use crate::system::layout::LayoutResource;
+use crate::system::lobby::LobbyResource;
+use crate::system::seed::Seed;
+
+const USAGE: &str = "usage: minesweeper [--difficulty Easy|Normal|Hard|<width>x<height>x<mines>] [--seed N] [--board FILE]
+                   [--mode play|bot|multiplayer|ranking|settings] [--server ADDRESS] [--offline]
+                   [--validate FILE] [--config FILE] [--<config key> VALUE]";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StartMode {
+    #[default]
+    Play,
+    /// The bot plays, starting a new game after each one.
+    Bot,
+    Multiplayer,
+    Ranking,
+    Settings,
+}
+
+impl StartMode {
+    fn from_string(mode: &str) -> Option<Self> {
+        match mode.to_lowercase().as_str() {
+            "play" => Some(Self::Play),
+            "bot" => Some(Self::Bot),
+            "multiplayer" => Some(Self::Multiplayer),
+            "ranking" => Some(Self::Ranking),
+            "settings" => Some(Self::Settings),
+            _ => None,
+        }
+    }
+}
+
+/// What the command line asks of the game. Config flags are kept for the command line layer of
+/// `config::platform`.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct Cli {
+    pub difficulty: Option<Difficulty>,
+    pub seed: Option<u64>,
+    pub board: Option<Layout>,
+    pub mode: StartMode,
+    /// A relay to connect to at startup.
+    pub server: Option<String>,
+    /// Rankings and the login stay on this machine.
+    pub is_offline: bool,
+    /// A board or replay to check without opening a window.
+    pub validate: Option<String>,
+    /// The file given by `--config`.
+    pub config_path: Option<String>,
+    /// `--<config key>` flags in the order given, with `_` in the key.
+    pub config_values: Vec<(String, String)>,
+}
+
+impl Cli {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                return Err(format!("unexpected argument {}", arg));
+            };
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name.replace('-', "_"), Some(value.to_string())),
+                None => (name.replace('-', "_"), None),
+            };
+            match name.as_str() {
+                "help" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                },
+                "offline" => {
+                    cli.is_offline = true;
+                    continue;
+                },
+                _ => {},
+            }
+
+            let Some(value) = inline_value.or_else(|| args.next().cloned()) else {
+                return Err(format!("missing value for --{}", name));
+            };
+            match name.as_str() {
+                "difficulty" => {
+                    let difficulty = Difficulty::from_string(&value)
+                        .or_else(|| Difficulty::from_string(&capitalize(&value)))
+                        .ok_or_else(|| format!("unknown difficulty {}", value))?;
+                    cli.difficulty = Some(difficulty);
+                },
+                // Seeds are kept within u32, see `seed::new_seed`.
+                "seed" => {
+                    let seed = value.parse::<u32>().map_err(|_| format!("invalid seed {}", value))?;
+                    cli.seed = Some(seed as u64);
+                },
+                "board" => {
+                    let text = fs::read_to_string(&value).map_err(|e| format!("unable to read {}: {}", value, e))?;
+                    cli.board = Some(Layout::parse(&text).map_err(|e| format!("invalid board {}: {}", value, e))?);
+                },
+                "mode" => {
+                    cli.mode = StartMode::from_string(&value).ok_or_else(|| format!("unknown mode {}", value))?;
+                },
+                "server" => cli.server = Some(value),
+                "validate" => cli.validate = Some(value),
+                "config" => cli.config_path = Some(value),
+                name if config::platform::KEYS.contains(&name) => cli.config_values.push((name.to_string(), value)),
+                _ => return Err(format!("unknown option --{}", name)),
+            }
+        }
+
+        if cli.board.is_some() && cli.difficulty.is_some() {
+            return Err("--board sets the size, leave out --difficulty".to_string());
+        }
+        if cli.is_offline && cli.server.is_some() {
+            return Err("--server needs the network, leave out --offline".to_string());
+        }
+        Ok(cli)
+    }
+
+    /// Parses the arguments of the process, or exits with the usage.
+    pub fn from_env() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        Self::parse(&args).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        })
+    }
+}
+
+// `easy` is as good as `Easy` on the command line.
+fn capitalize(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+        None => String::new(),
+    }
+}
+
+/// Checks a board file, or replays a submission or best run, and returns the exit status:
+/// 0 if it is valid, 1 if not.
+pub fn validate(path: &str) -> i32 {
+    let result = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| {
+            let value = serde_yaml::from_str::<serde_yaml::Value>(&text).map_err(|e| e.to_string())?;
+            if value.get("rows").is_some() {
+                let layout = Layout::parse(&text).map_err(|e| e.to_string())?;
+                let (width, height, num_mines) = layout.get_size();
+                Ok(format!("{}x{} board with {} mines", width, height, num_mines))
+            } else {
+                let submission = serde_yaml::from_value::<Submission>(value).map_err(|e| e.to_string())?;
+                let time_ms = verify::verify(&submission).map_err(|e| e.to_string())?;
+                Ok(format!("{} replay won in {:.3}s", submission.difficulty, time_ms as f64 / 1000.0))
+            }
+        });
+    match result {
+        Ok(summary) => {
+            println!("valid {} {}", path, summary);
+            0
+        },
+        Err(e) => {
+            println!("invalid {} {}", path, e);
+            1
+        },
+    }
+}
+
+/// Applies the command line over the settings. Added before the plugins whose resources it sets,
+/// e.g. the backend of `FirestorePlugin`.
+pub struct CliPlugin(pub Cli);
+
+impl Plugin for CliPlugin {
+    fn build(&self, app: &mut App) {
+        let cli = self.0.clone();
+        if cli.is_offline && !app.world.contains_resource::<BackendResource>() {
+            app.insert_resource(BackendResource::new(LocalBackend::with_storage("Local", "ranking")));
+        }
+        if let Some(layout) = cli.board.clone() {
+            let layout = LayoutResource::new(layout);
+            if let Some(difficulty) = layout.get_difficulty() {
+                app.insert_resource(difficulty);
+            }
+            app.insert_resource(layout);
+        } else if let Some(difficulty) = cli.difficulty {
+            app.insert_resource(difficulty);
+        }
+        if let Some(value) = cli.seed {
+            app.insert_resource(Seed::fixed(value));
+        }
+        app
+            .insert_resource(cli)
+            .add_systems(Startup, apply_start_mode);
+    }
+}
+
+pub fn apply_start_mode(
+    cli: Res<Cli>,
+    mut bot: ResMut<BotResource>,
+    mut lobby: ResMut<LobbyResource>,
+    (mut is_lobby_open, mut is_ranking_open, mut is_settings_open): (ResMut<IsLobbyOpen>, ResMut<IsRankingOpen>, ResMut<IsSettingsOpen>),
+) {
+    match cli.mode {
+        StartMode::Play => {},
+        StartMode::Bot => {
+            bot.is_enabled = true;
+            bot.is_auto_restart = true;
+        },
+        StartMode::Multiplayer => is_lobby_open.0 = true,
+        StartMode::Ranking => is_ranking_open.0 = true,
+        StartMode::Settings => is_settings_open.0 = true,
+    }
+    if let Some(server) = cli.server.as_ref() {
+        lobby.address = server.clone();
+        lobby.connect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Cli::parse(&args)
+    }
+
+    // A file of its own per test, since the tests run in parallel.
+    fn write_file(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("minesweeper_cli_{}_{}", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    const BOARD: &str = "rows:\n  - \"*........\"\n  - \".........\"\n  - \".........\"\n  - \".........\"\n  - \".........\"\n  - \".........\"\n  - \".........\"\n  - \".........\"\n  - \"........*\"\n";
+
+    #[test]
+    fn options_are_read_with_either_spelling() {
+        let cli = parse(&["--difficulty", "easy", "--seed=1234", "--mode", "Bot", "--server", "localhost:9001"]).unwrap();
+        assert_eq!(cli.difficulty, Some(Difficulty::Easy));
+        assert_eq!(cli.seed, Some(1234));
+        assert_eq!(cli.mode, StartMode::Bot);
+        assert_eq!(cli.server.as_deref(), Some("localhost:9001"));
+
+        let cli = parse(&["--difficulty=20x12x40", "--offline"]).unwrap();
+        assert_eq!(cli.difficulty, Some(Difficulty::Custom { width: 20, height: 12, mines: 40 }));
+        assert!(cli.is_offline);
+    }
+
+    #[test]
+    fn config_flags_are_kept_in_order() {
+        let cli = parse(&["--config", "staging.yaml", "--project-id", "a", "--key_path=./key.json", "--project_id=b"]).unwrap();
+        assert_eq!(cli.config_path.as_deref(), Some("staging.yaml"));
+        assert_eq!(cli.config_values, vec![
+            ("project_id".to_string(), "a".to_string()),
+            ("key_path".to_string(), "./key.json".to_string()),
+            ("project_id".to_string(), "b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn seed_must_fit_in_u32() {
+        assert_eq!(parse(&["--seed", "4294967295"]).unwrap().seed, Some(u32::MAX as u64));
+        assert!(parse(&["--seed", "4294967296"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+    }
+
+    #[test]
+    fn bad_arguments_are_errors() {
+        for args in [
+            &["easy"][..],
+            &["--seed"],
+            &["--difficulty", "Huge"],
+            &["--mode", "watch"],
+            &["--unknown", "1"],
+            &["--offline", "--server", "localhost:9001"],
+        ] {
+            assert!(parse(args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn board_sets_the_size() {
+        let path = write_file("board.yaml", BOARD);
+        let cli = parse(&["--board", &path]).unwrap();
+        assert_eq!(cli.board.unwrap().get_size(), (9, 9, 2));
+        assert!(parse(&["--board", &path, "--difficulty", "Easy"]).is_err());
+        assert!(parse(&["--board", "./missing/board.yaml"]).is_err());
+    }
+
+    #[test]
+    fn validate_checks_boards() {
+        assert_eq!(validate(&write_file("valid.yaml", BOARD)), 0);
+        assert_eq!(validate(&write_file("uneven.yaml", "rows:\n  - \"*..\"\n  - \"..\"\n")), 1);
+        assert_eq!(validate(&write_file("unknown.yaml", "time: 1\n")), 1);
+        assert_eq!(validate("./missing/board.yaml"), 1);
+    }
+}
@@ -0,0 +1,426 @@
+use bevy::prelude::*;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::system::identity::IdentityProvider;
+
+pub const DEFAULT_PROJECT_ID: &str = "minesweeper-86284";
+const DEFAULT_KEY_PATH: &str = "./key/firebase_key.json";
+
+pub struct ConfigPlugin;
+
+// Loaded while the app is built, since the backend is chosen from it before anything runs. Added
+// after `CliPlugin`, whose config flags are the last layer.
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        let (config, errors) = merge(platform::get_layers(&app.world));
+        for e in errors.iter() {
+            error!("{}", e);
+        }
+        app
+            .insert_resource(config)
+            .insert_resource(ConfigErrors {
+                is_open: !errors.is_empty(),
+                errors,
+            });
+    }
+}
+
+/// Everything that differs between deployments, merged from the layers of `platform::get_layers`,
+/// later ones winning: the defaults, `config.yaml`, `MINESWEEPER_*` environment variables and
+/// `--<key> <value>` flags natively, or the `gameConfig` of `out/config.js` on the web.
+#[derive(Debug, Clone, Resource, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The Firebase project of native builds. The web build has it in `firebaseConfig`.
+    pub project_id: String,
+    /// The service account key of native builds. Without it rankings stay on this machine.
+    pub key_path: String,
+    /// The Google client, for configs from before `providers`.
+    pub client_id: String,
+    /// The login function the browser returns to on the web.
+    pub redirect_uri: String,
+    /// Turns the access token of a native login into the user and a session, and claims display
+    /// names on both builds.
+    pub session_endpoint: String,
+    /// Offered on the login popup, in this order.
+    pub providers: Vec<IdentityProvider>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            project_id: DEFAULT_PROJECT_ID.to_string(),
+            key_path: DEFAULT_KEY_PATH.to_string(),
+            client_id: platform::DEFAULT_CLIENT_ID.to_string(),
+            redirect_uri: platform::DEFAULT_REDIRECT_URI.to_string(),
+            session_endpoint: String::new(),
+            providers: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn get_providers(&self) -> Vec<IdentityProvider> {
+        if !self.providers.is_empty() {
+            self.providers.clone()
+        } else if !self.client_id.is_empty() {
+            vec![IdentityProvider::Google {
+                client_id: self.client_id.clone(),
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    // Drops what cannot work, so that one bad provider does not take the others down with it.
+    fn validate(&mut self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        let mut error = |message: String| errors.push(ConfigError {
+            source: "config".to_string(),
+            message,
+        });
+
+        if self.project_id.is_empty() {
+            error("project_id is empty, using the default".to_string());
+            self.project_id = DEFAULT_PROJECT_ID.to_string();
+        }
+        for (key, url) in [("redirect_uri", &mut self.redirect_uri), ("session_endpoint", &mut self.session_endpoint)] {
+            if !url.is_empty() && !is_url(url) {
+                error(format!("{} is not an http(s) URL: {}", key, url));
+                url.clear();
+            }
+        }
+
+        let mut ids: Vec<String> = Vec::new();
+        self.providers.retain(|provider| {
+            let id = provider.get_id().to_string();
+            let problem = if id.is_empty() || id.contains('.') {
+                Some("needs an id without dots".to_string())
+            } else if ids.contains(&id) {
+                Some("is listed twice".to_string())
+            } else if provider.get_client_id().is_empty() {
+                Some("has no client_id".to_string())
+            } else if !is_url(provider.get_auth_endpoint()) || !is_url(provider.get_token_endpoint()) {
+                Some("needs http(s) auth_endpoint and token_endpoint".to_string())
+            } else {
+                None
+            };
+            ids.push(id.clone());
+            match problem {
+                Some(problem) => {
+                    error(format!("provider '{}' {}, leaving it out", id, problem));
+                    false
+                },
+                None => true,
+            }
+        });
+        errors
+    }
+}
+
+fn is_url(value: &str) -> bool {
+    value.starts_with("https://") || value.starts_with("http://")
+}
+
+/// Why a layer, or a part of the merged config, was left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The layer, e.g. the path of the file or the name of the variable.
+    pub source: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+/// Shown in a window at startup, since a broken config is otherwise only noticed when the login
+/// or the rankings fail.
+#[derive(Resource, Default)]
+pub struct ConfigErrors {
+    pub errors: Vec<ConfigError>,
+    pub is_open: bool,
+}
+
+/// Keys and values from one place, e.g. a file. Errors found while reading it are kept with it.
+pub struct ConfigLayer {
+    pub source: String,
+    pub values: Mapping,
+    pub errors: Vec<String>,
+}
+
+impl ConfigLayer {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            values: Mapping::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Reads a YAML mapping, e.g. the text of `config.yaml`. An empty text is an empty mapping.
+    pub fn from_yaml(source: impl Into<String>, text: &str) -> Self {
+        let mut layer = Self::new(source);
+        match serde_yaml::from_str::<Value>(text) {
+            Ok(Value::Mapping(values)) => layer.values = values,
+            Ok(Value::Null) => {},
+            Ok(_) => layer.errors.push("expected a mapping of keys to values".to_string()),
+            Err(e) => layer.errors.push(e.to_string()),
+        }
+        layer
+    }
+
+    /// Sets a key from text, e.g. an environment variable. Lists are written in YAML flow style.
+    pub fn set(&mut self, key: &str, value: &str) {
+        let value = if key == "providers" {
+            match serde_yaml::from_str::<Value>(value) {
+                Ok(value) => value,
+                Err(e) => {
+                    self.errors.push(format!("{}: {}", key, e));
+                    return;
+                },
+            }
+        } else {
+            Value::String(value.to_string())
+        };
+        self.values.insert(Value::String(key.to_string()), value);
+    }
+}
+
+/// Applies the layers over the defaults in order. A layer that does not read, or does not fit the
+/// config, is left out whole and reported instead of panicking.
+pub fn merge(layers: Vec<ConfigLayer>) -> (Config, Vec<ConfigError>) {
+    let mut errors = Vec::new();
+    let mut merged = match serde_yaml::to_value(Config::default()) {
+        Ok(Value::Mapping(values)) => values,
+        _ => Mapping::new(),
+    };
+
+    for layer in layers {
+        if !layer.errors.is_empty() {
+            errors.extend(layer.errors.into_iter().map(|message| ConfigError {
+                source: layer.source.clone(),
+                message,
+            }));
+            continue;
+        }
+        let mut candidate = merged.clone();
+        candidate.extend(layer.values);
+        match serde_yaml::from_value::<Config>(Value::Mapping(candidate.clone())) {
+            Ok(_) => merged = candidate,
+            Err(e) => errors.push(ConfigError {
+                source: layer.source,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    let mut config = serde_yaml::from_value::<Config>(Value::Mapping(merged)).unwrap_or_default();
+    errors.extend(config.validate());
+    (config, errors)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod platform {
+    use std::fs;
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::system::cli::Cli;
+
+    pub const DEFAULT_CLIENT_ID: &str = "";
+    pub const DEFAULT_REDIRECT_URI: &str = "";
+    const DEFAULT_CONFIG_PATH: &str = "./assets/yaml/config.yaml";
+    const ENV_PREFIX: &str = "MINESWEEPER_";
+    pub const KEYS: [&str; 6] = ["project_id", "key_path", "client_id", "redirect_uri", "session_endpoint", "providers"];
+
+    pub fn get_layers(world: &World) -> Vec<ConfigLayer> {
+        let cli = world.get_resource::<Cli>();
+        let path = cli.and_then(|cli| cli.config_path.clone())
+            .or_else(|| std::env::var(format!("{}CONFIG", ENV_PREFIX)).ok());
+        let mut layers = vec![get_file_layer(path), get_env_layer()];
+        layers.extend(cli.map(get_cli_layer));
+        layers
+    }
+
+    /// The providers the login popup offers. Native logins end at the session endpoint, so without
+    /// one none of them can work.
+    pub fn get_login_providers(config: &Config) -> Vec<IdentityProvider> {
+        if config.session_endpoint.is_empty() {
+            Vec::new()
+        } else {
+            config.get_providers()
+        }
+    }
+
+    // The default file may be missing, e.g. for offline play, but a file asked for by name may not.
+    fn get_file_layer(path: Option<String>) -> ConfigLayer {
+        let is_default = path.is_none();
+        let path = path.unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+        match fs::read_to_string(&path) {
+            Ok(text) => ConfigLayer::from_yaml(path, &text),
+            Err(e) if is_default && e.kind() == ErrorKind::NotFound => {
+                info!("{} not found, using the defaults", path);
+                ConfigLayer::new(path)
+            },
+            Err(e) => {
+                let mut layer = ConfigLayer::new(path);
+                layer.errors.push(e.to_string());
+                layer
+            },
+        }
+    }
+
+    // `MINESWEEPER_CLIENT_ID` sets `client_id`, and so on.
+    fn get_env_layer() -> ConfigLayer {
+        let mut layer = ConfigLayer::new("environment");
+        for key in KEYS {
+            if let Ok(value) = std::env::var(format!("{}{}", ENV_PREFIX, key.to_uppercase())) {
+                layer.set(key, &value);
+            }
+        }
+        layer
+    }
+
+    // The flags were read by `Cli::parse`, with the other options of the game.
+    fn get_cli_layer(cli: &Cli) -> ConfigLayer {
+        let mut layer = ConfigLayer::new("command line");
+        for (key, value) in cli.config_values.iter() {
+            layer.set(key, value);
+        }
+        layer
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub mod platform {
+    use wasm_bindgen::prelude::*;
+    use serde_wasm_bindgen::from_value;
+
+    use super::*;
+
+    // The deployed web client, for pages whose `config.js` predates `gameConfig`.
+    pub const DEFAULT_CLIENT_ID: &str = "687056820470-5ujnqbk4pblq6huvl7nb42ggmi24dt8h.apps.googleusercontent.com";
+    pub const DEFAULT_REDIRECT_URI: &str = "https://oauth-6kvyjc5h2a-uc.a.run.app";
+
+    #[wasm_bindgen]
+    extern "C" {
+        fn load_config_js() -> JsValue;
+    }
+
+    /// The providers the login popup offers. The login function of the web build needs no
+    /// session endpoint.
+    pub fn get_login_providers(config: &Config) -> Vec<IdentityProvider> {
+        config.get_providers()
+    }
+
+    pub fn get_layers(_world: &World) -> Vec<ConfigLayer> {
+        let value = load_config_js();
+        if value.is_undefined() || value.is_null() {
+            return Vec::new();
+        }
+        let mut layer = ConfigLayer::new("gameConfig");
+        match from_value::<Value>(value) {
+            Ok(Value::Mapping(values)) => layer.values = values,
+            Ok(_) => layer.errors.push("expected an object".to_string()),
+            Err(e) => layer.errors.push(e.to_string()),
+        }
+        vec![layer]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(source: &str, text: &str) -> ConfigLayer {
+        ConfigLayer::from_yaml(source, text)
+    }
+
+    #[test]
+    fn no_layers_give_the_defaults() {
+        assert_eq!(merge(Vec::new()), (Config::default(), Vec::new()));
+        assert_eq!(merge(vec![layer("empty", "")]), (Config::default(), Vec::new()));
+    }
+
+    #[test]
+    fn later_layers_win_key_by_key() {
+        let mut env = ConfigLayer::new("environment");
+        env.set("client_id", "from-env");
+        let (config, errors) = merge(vec![
+            layer("file", "project_id: from-file\nclient_id: from-file\n"),
+            env,
+            layer("command line", "key_path: ./key/other.json\n"),
+        ]);
+        assert_eq!(errors, Vec::new());
+        assert_eq!(config.project_id, "from-file");
+        assert_eq!(config.client_id, "from-env");
+        assert_eq!(config.key_path, "./key/other.json");
+    }
+
+    #[test]
+    fn bad_layer_is_skipped_with_an_error() {
+        let (config, errors) = merge(vec![
+            layer("good", "client_id: good\n"),
+            layer("broken", "client_id: [unclosed\n"),
+            layer("list", "- not a mapping\n"),
+            layer("wrong type", "client_id: good-too\nproviders: not a list\n"),
+        ]);
+        assert_eq!(config.client_id, "good");
+        let sources = errors.iter().map(|e| e.source.as_str()).collect::<Vec<_>>();
+        assert_eq!(sources, ["broken", "list", "wrong type"]);
+        assert_eq!(errors[1].message, "expected a mapping of keys to values");
+    }
+
+    #[test]
+    fn unknown_key_leaves_the_whole_layer_out() {
+        let (config, errors) = merge(vec![layer("file", "client_id: typo-layer\nclinet_id: oops\n")]);
+        assert_eq!(config, Config::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].source, "file");
+        assert!(errors[0].message.contains("clinet_id"), "{}", errors[0].message);
+    }
+
+    #[test]
+    fn invalid_providers_and_urls_are_dropped() {
+        let (config, errors) = merge(vec![layer("file", r#"
+project_id: ""
+session_endpoint: "ftp://example.com/session"
+providers:
+  - kind: "github"
+    client_id: "github-client"
+  - kind: "google"
+    client_id: ""
+  - kind: "oidc"
+    id: "sso.company"
+    name: "Company"
+    client_id: "sso-client"
+    auth_endpoint: "https://sso.example.com/authorize"
+    token_endpoint: "https://sso.example.com/token"
+  - kind: "oidc"
+    id: "company"
+    name: "Company"
+    client_id: "sso-client"
+    auth_endpoint: "sso.example.com/authorize"
+    token_endpoint: "https://sso.example.com/token"
+  - kind: "github"
+    client_id: "second-github-client"
+"#)]);
+        assert_eq!(config.providers, vec![IdentityProvider::GitHub { client_id: "github-client".to_string() }]);
+        assert_eq!(config.project_id, DEFAULT_PROJECT_ID);
+        assert_eq!(config.session_endpoint, "");
+        let messages = errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [
+            "project_id is empty, using the default",
+            "session_endpoint is not an http(s) URL: ftp://example.com/session",
+            "provider 'google' has no client_id, leaving it out",
+            "provider 'sso.company' needs an id without dots, leaving it out",
+            "provider 'company' needs http(s) auth_endpoint and token_endpoint, leaving it out",
+            "provider 'github' is listed twice, leaving it out",
+        ]);
+    }
+}
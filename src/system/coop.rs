@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::component::cell::{Cell, CellState};
+use crate::component::grid::Grid;
+use crate::core::rules::{Action, TileState};
+use crate::net::protocol::{ClientMessage, ServerMessage, TileUpdate};
+use crate::system::difficulty::Difficulty;
+use crate::system::egui::IsLobbyOpen;
+use crate::system::lobby::{self, LobbyResource, ServerEvent};
+use crate::system::mouse::{self, Clickable, InputMode, RestartEvent};
+use crate::system::replay::MoveEvent;
+use crate::system::seed::Seed;
+use crate::system::state::GameState;
+
+pub struct CoopPlugin;
+
+impl Plugin for CoopPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CoopResource>()
+            .add_systems(OnEnter(GameState::Init), enter_board)
+            .add_systems(Update, (
+                poll_coop,
+                send_actions.after(mouse::mouse_events_system),
+                send_cursor,
+                color_flags.after(poll_coop),
+                draw_cursors,
+            ));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct CoopResource {
+    pub is_active: bool,
+    pub cursors: HashMap<u32, (u32, u32)>,
+    pub flag_owners: HashMap<(u32, u32), u32>,
+    is_starting: bool,
+    last_cursor: (u32, u32),
+}
+
+impl CoopResource {
+    fn clear(&mut self) {
+        self.cursors.clear();
+        self.flag_owners.clear();
+        self.last_cursor = (0, 0);
+    }
+
+    fn apply(&mut self, tile: &TileUpdate, cell: &mut Cell) {
+        match tile.flagged_by {
+            Some(id) if tile.state == TileState::Flagged => {
+                self.flag_owners.insert((tile.x, tile.y), id);
+            },
+            _ => {
+                self.flag_owners.remove(&(tile.x, tile.y));
+            },
+        }
+
+        cell.change_mine(tile.is_mine, tile.num_mines_around);
+        cell.state = CellState::from_tile(tile.state);
+    }
+}
+
+// A fresh board belongs to the co-op game that was just started, or is a local one again.
+pub fn enter_board(
+    mut coop: ResMut<CoopResource>,
+    mut input_mode: ResMut<InputMode>,
+) {
+    if coop.is_starting {
+        coop.is_starting = false;
+        *input_mode = InputMode::Remote;
+    } else {
+        coop.is_active = false;
+        coop.clear();
+        if *input_mode == InputMode::Remote {
+            *input_mode = InputMode::Local;
+        }
+    }
+}
+
+pub fn poll_coop(
+    mut coop: ResMut<CoopResource>,
+    lobby: Res<LobbyResource>,
+    mut server_events: EventReader<ServerEvent>,
+    (mut difficulty, mut seed): (ResMut<Difficulty>, ResMut<Seed>),
+    (current_state, mut next_state): (Res<State<GameState>>, ResMut<NextState<GameState>>),
+    (mut is_lobby_open, input_mode): (ResMut<IsLobbyOpen>, Res<InputMode>),
+    (grid, mut q_cells): (Res<Grid>, Query<&mut Cell>),
+) {
+    for ServerEvent(message) in server_events.iter() {
+        match message {
+            ServerMessage::CoopStart { difficulty: coop_difficulty, seed: coop_seed } => {
+                if let Some(coop_difficulty) = Difficulty::from_string(coop_difficulty) {
+                    *difficulty = coop_difficulty;
+                }
+                seed.start_session(*coop_seed);
+                coop.is_active = true;
+                coop.is_starting = true;
+                coop.clear();
+                is_lobby_open.0 = false;
+                next_state.set(GameState::Init);
+            },
+            ServerMessage::Tiles { tiles } if coop.is_active => {
+                for tile in tiles.iter() {
+                    let Some(entity) = grid.find_cell(tile.x, tile.y) else {
+                        continue;
+                    };
+                    if let Ok(mut cell) = q_cells.get_mut(entity) {
+                        coop.apply(tile, &mut cell);
+                    }
+                }
+                if *current_state == GameState::Ready {
+                    next_state.set(GameState::Playing);
+                }
+            },
+            ServerMessage::Cursor { id, x, y } => {
+                if *x == 0 {
+                    coop.cursors.remove(id);
+                } else {
+                    coop.cursors.insert(*id, (*x, *y));
+                }
+            },
+            ServerMessage::CoopOver { is_win, .. } if coop.is_active => {
+                coop.is_active = false;
+                coop.cursors.clear();
+                seed.end_session();
+                next_state.set(if *is_win { GameState::Win } else { GameState::Defeated });
+            },
+            _ => {},
+        }
+    }
+
+    if *input_mode == InputMode::Remote && !lobby.is_connected() {
+        seed.end_session();
+        next_state.set(GameState::Init);
+    }
+}
+
+pub fn send_actions(
+    coop: Res<CoopResource>,
+    lobby: Res<LobbyResource>,
+    input_mode: Res<InputMode>,
+    difficulty: Res<Difficulty>,
+    (grid, q_cells): (Res<Grid>, Query<&Cell>),
+    (mut move_events, mut restart_events): (EventReader<MoveEvent>, EventReader<RestartEvent>),
+) {
+    if *input_mode != InputMode::Remote {
+        move_events.clear();
+        restart_events.clear();
+        return;
+    }
+
+    for move_event in move_events.iter() {
+        if !coop.is_active {
+            continue;
+        }
+        let is_flagged = grid.find_cell(move_event.x, move_event.y)
+            .and_then(|entity| q_cells.get(entity).ok())
+            .map(|cell| cell.state == CellState::Flagged)
+            .unwrap_or(false);
+        lobby.send(&ClientMessage::Action {
+            x: move_event.x,
+            y: move_event.y,
+            action: move_event.action,
+            flagged: move_event.action == Action::Flag && !is_flagged,
+        });
+    }
+
+    for _ in restart_events.iter() {
+        lobby.send(&ClientMessage::StartCoop { difficulty: difficulty.to_string() });
+    }
+}
+
+pub fn send_cursor(
+    mut coop: ResMut<CoopResource>,
+    lobby: Res<LobbyResource>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_cells: Query<(&Cell, &Clickable)>,
+) {
+    if !coop.is_active {
+        return;
+    }
+
+    let hovered = q_windows.single().cursor_position()
+        .and_then(|position| q_cells.iter().find(|(_, clickable)| clickable.is_inside(position)))
+        .map(|(cell, _)| (cell.x, cell.y))
+        .unwrap_or((0, 0));
+    if hovered == coop.last_cursor {
+        return;
+    }
+    coop.last_cursor = hovered;
+    lobby.send(&ClientMessage::Cursor { x: hovered.0, y: hovered.1 });
+}
+
+pub fn color_flags(
+    coop: Res<CoopResource>,
+    mut q_cells: Query<(&Cell, &mut TextureAtlasSprite)>,
+) {
+    for (cell, mut sprite) in q_cells.iter_mut() {
+        let color = match coop.flag_owners.get(&(cell.x, cell.y)) {
+            Some(id) if cell.state == CellState::Flagged => {
+                let (r, g, b) = lobby::get_player_color(*id);
+                Color::rgb_u8(r, g, b)
+            },
+            _ => Color::WHITE,
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+pub fn draw_cursors(
+    mut contexts: EguiContexts,
+    coop: Res<CoopResource>,
+    lobby: Res<LobbyResource>,
+    q_cells: Query<(&Cell, &Clickable)>,
+) {
+    if !coop.is_active || coop.cursors.is_empty() {
+        return;
+    }
+
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("coop_cursors")));
+    for (cell, clickable) in q_cells.iter() {
+        for (id, _) in coop.cursors.iter().filter(|(_, position)| **position == (cell.x, cell.y)) {
+            let (r, g, b) = lobby::get_player_color(*id);
+            let color = egui::Color32::from_rgb(r, g, b);
+            let center = egui::pos2(clickable.0.x, clickable.0.y);
+            painter.rect_stroke(egui::Rect::from_center_size(center, egui::vec2(clickable.1, clickable.2)), 0.0, egui::Stroke::new(2.0, color));
+            if let Some(name) = lobby.get_player_name(*id) {
+                painter.text(center - egui::vec2(0.0, clickable.2 / 2.0), egui::Align2::CENTER_BOTTOM, name, egui::FontId::proportional(10.0), color);
+            }
+        }
+    }
+}
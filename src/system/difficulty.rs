@@ -1,11 +1,20 @@
 use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
 
-#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Default)]
+use crate::core::rules;
+
+#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum Difficulty {
     Easy,
     Normal,
     #[default]
     Hard,
+    /// A board of any size within `rules::is_valid_custom_size`. Not ranked.
+    Custom {
+        width: u32,
+        height: u32,
+        mines: u32,
+    },
 }
 
 impl Difficulty {
@@ -14,6 +23,38 @@ impl Difficulty {
             Self::Easy => "Easy".to_string(),
             Self::Normal => "Normal".to_string(),
             Self::Hard => "Hard".to_string(),
+            Self::Custom { width, height, mines } => rules::get_custom_size_name(*width, *height, *mines),
+        }
+    }
+
+    pub fn from_string(difficulty: &str) -> Option<Self> {
+        match difficulty {
+            "Easy" => Some(Self::Easy),
+            "Normal" => Some(Self::Normal),
+            "Hard" => Some(Self::Hard),
+            _ => {
+                let (width, height, mines) = rules::parse_custom_size(difficulty)?;
+                Some(Self::Custom { width, height, mines })
+            },
+        }
+    }
+
+    /// Width, height and number of mines.
+    pub fn get_size(&self) -> (u32, u32, u32) {
+        match self {
+            Self::Custom { width, height, mines } => (*width, *height, *mines),
+            _ => rules::get_difficulty_size(&self.to_string()).unwrap(),
         }
     }
+
+    pub fn is_standard(&self) -> bool {
+        !matches!(self, Self::Custom { .. })
+    }
+}
+
+// Custom boards have no leaderboard, and the verifier would reject them.
+pub fn run_if_standard_difficulty(
+    difficulty: Res<Difficulty>,
+) -> bool {
+    difficulty.is_standard()
 }
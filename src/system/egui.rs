@@ -1,13 +1,34 @@
 use bevy::prelude::*;
 use bevy::app::AppExit;
+use chrono::TimeZone;
 use bevy_egui::{egui::{self, PointerButton}, EguiContexts, EguiPlugin};
 
 use crate::system::difficulty::Difficulty;
-use crate::system::state::{GameState, MenuGameState, MenuInfoState, AboutWindowState, RankingWindowState, DataReadingState, LoginPopupState};
+use crate::system::state::{GameState, MenuGameState, MenuInfoState, AboutWindowState, RankingWindowState, LobbyWindowState, SettingsWindowState, LoginPopupState, LoginState};
 use crate::system::window::{init_window, init_window_with_ui};
-use crate::system::auth::{Config, initiate_google_login};
-use crate::system::uuid::UuidResource;
-use crate::system::firestore::{LoginDone, RankingDataResource, RankingData};
+use crate::system::auth;
+use crate::system::config::{self, Config, ConfigErrors};
+use crate::system::guest::GuestResource;
+use crate::system::firestore::{LoginCancelEvent, LoginDone, LoginFlow, LoginRequestEvent, RankingDataResource, RankingData, RankingRequestEvent, RankingView};
+use crate::system::outbox::OutboxResource;
+use crate::system::backend::{BackendResource, RankingQuery, MAX_PERIOD_ENTRIES};
+use crate::system::period::RankingPeriod;
+use crate::system::profile::{ProfileRequestEvent, ProfileResource};
+use crate::system::name::{self, ClaimNameEvent, NameResource, NAME_MAX_LEN};
+use crate::system::session::LogoutEvent;
+use crate::system::network::{BackendErrorEvent, BackendTask, ConnectionStatus, NetworkResource, RetryEvent};
+use crate::system::seed::Seed;
+use crate::system::mouse::InputMode;
+use crate::system::ghost::GhostResource;
+use crate::system::race::{RaceResource, RaceStatus};
+use crate::system::coop::CoopResource;
+use crate::system::lobby::{self, LobbyResource, LobbyStatus};
+use crate::system::spectate::SpectateResource;
+use crate::system::bot::{self, BotResource};
+use crate::system::settings::{KeyAction, KeyRebinding, Settings, Theme};
+use crate::asset::skin::SkinResource;
+use crate::net::protocol::ClientMessage;
+use crate::net::protocol::PlayerStatus;
 
 pub struct EguiMenuPlugin;
 
@@ -26,26 +47,38 @@ impl Plugin for EguiMenuPlugin {
             .init_resource::<IsAboutOpen>()
             .init_resource::<IsRankingOpen>()
             .init_resource::<IsLoginOpen>()
+            .init_resource::<IsLobbyOpen>()
+            .init_resource::<IsSettingsOpen>()
+            .init_resource::<ToastResource>()
             .add_state::<MenuGameState>()
             .add_state::<MenuInfoState>()
             .add_state::<AboutWindowState>()
             .add_state::<RankingWindowState>()
+            .add_state::<LobbyWindowState>()
+            .add_state::<SettingsWindowState>()
             .add_plugins(EguiPlugin)
             .add_systems(Startup, configure_visuals_system)
-            .add_systems(Update, (about_menu, ranking_menu, login_menu))
-            .add_systems(Update, ui_system.after(about_menu).after(ranking_menu).after(login_menu))
+            .add_systems(Update, configure_visuals_system.run_if(resource_changed::<Settings>()))
+            .add_systems(Update, (about_menu, ranking_menu, profile_menu.after(ranking_menu), login_menu, name_menu, lobby_menu, settings_menu, toast_menu, config_menu))
+            .add_systems(Update, ui_system.after(about_menu).after(profile_menu).after(login_menu).after(name_menu).after(lobby_menu).after(settings_menu).after(toast_menu))
             .add_systems(OnEnter(AboutWindowState::Opened), init_window_with_ui)
             .add_systems(OnEnter(AboutWindowState::Closed), init_window)
             .add_systems(OnEnter(RankingWindowState::Opened), init_window_with_ui)
-            .add_systems(OnEnter(RankingWindowState::Closed), init_window);
+            .add_systems(OnEnter(RankingWindowState::Closed), init_window)
+            .add_systems(OnEnter(LobbyWindowState::Opened), init_window_with_ui)
+            .add_systems(OnEnter(LobbyWindowState::Closed), init_window)
+            .add_systems(OnEnter(SettingsWindowState::Opened), init_window_with_ui)
+            .add_systems(OnEnter(SettingsWindowState::Closed), init_window);
     }
 }
 
-fn configure_visuals_system(mut contexts: EguiContexts) {
-    contexts.ctx_mut().set_visuals(egui::Visuals {
-        window_rounding: 0.0.into(),
-        ..Default::default()
-    });
+fn configure_visuals_system(
+    mut contexts: EguiContexts,
+    settings: Res<Settings>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    contexts.ctx_mut().set_visuals(settings.theme.get_visuals());
+    clear_color.0 = settings.theme.get_clear_color();
 }
 
 #[derive(Resource, Default)]
@@ -57,16 +90,99 @@ pub struct IsAboutOpen(pub bool);
 #[derive(Resource, Default)]
 pub struct IsRankingOpen(pub bool);
 
+#[derive(Resource, Default)]
+pub struct IsLobbyOpen(pub bool);
+
+#[derive(Resource, Default)]
+pub struct IsSettingsOpen(pub bool);
+
+const TOAST_DURATION_SEC: f64 = 8.0;
+const ERROR_COLOR: egui::Color32 = egui::Color32::LIGHT_RED;
+
+struct Toast {
+    task: BackendTask,
+    text: String,
+    expires_at: f64,
+}
+
+#[derive(Resource, Default)]
+pub struct ToastResource {
+    toasts: Vec<Toast>,
+    is_hovered: bool,
+}
+
+// Keeps a click on a toast from also landing on the board underneath.
+pub fn run_if_toast_not_hovered(
+    toasts: Res<ToastResource>,
+) -> bool {
+    !toasts.is_hovered
+}
+
+pub fn toast_menu(
+    mut contexts: EguiContexts,
+    mut toasts: ResMut<ToastResource>,
+    mut error_events: EventReader<BackendErrorEvent>,
+    mut retry_events: EventWriter<RetryEvent>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for event in error_events.iter() {
+        // A task failing again replaces its toast instead of stacking another one.
+        toasts.toasts.retain(|toast| toast.task != event.task);
+        toasts.toasts.push(Toast {
+            task: event.task,
+            text: format!("{} failed\n{}", event.task.get_name(), event.error),
+            expires_at: now + TOAST_DURATION_SEC,
+        });
+    }
+    toasts.toasts.retain(|toast| toast.expires_at > now);
+    if toasts.toasts.is_empty() {
+        toasts.is_hovered = false;
+        return;
+    }
+
+    let ctx: &mut egui::Context = contexts.ctx_mut();
+    let mut dismissed = None;
+    let response = egui::Area::new("toasts")
+    .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-4.0, -4.0))
+    .show(ctx, |ui| {
+        for (index, toast) in toasts.toasts.iter().enumerate() {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(200.0);
+                ui.colored_label(ERROR_COLOR, &toast.text);
+                ui.horizontal(|ui| {
+                    if ui.button("Retry").clicked() {
+                        retry_events.send(RetryEvent(toast.task));
+                        dismissed = Some(index);
+                    }
+                    if ui.button("Dismiss").clicked() {
+                        dismissed = Some(index);
+                    }
+                });
+            });
+        }
+    }).response;
+
+    toasts.is_hovered = response.hovered();
+    if let Some(index) = dismissed {
+        toasts.toasts.remove(index);
+    }
+}
+
 pub fn login_menu(
     mut contexts: EguiContexts,
     mut is_login_open: ResMut<IsLoginOpen>,
-    mut next_state: ResMut<NextState<LoginPopupState>>,
-    current_state: Res<State<LoginPopupState>>,
-    config: Res<Config>,
-    uuid: Res<UuidResource>,
+    (mut next_state, current_state): (ResMut<NextState<LoginPopupState>>, Res<State<LoginPopupState>>),
+    (login_state, login_flow, config, guest): (Res<State<LoginState>>, Res<LoginFlow>, Res<Config>, Res<GuestResource>),
+    (mut login_events, mut cancel_events): (EventWriter<LoginRequestEvent>, EventWriter<LoginCancelEvent>),
+    time: Res<Time>,
 ) {
     let ctx: &mut egui::Context = contexts.ctx_mut();
 
+    if *login_state == LoginState::Done {
+        is_login_open.0 = false;
+    }
+
     if is_login_open.0 {
         if *current_state == LoginPopupState::Closed {
             next_state.set(LoginPopupState::Opened);
@@ -77,25 +193,142 @@ pub fn login_menu(
         }
     }
 
-    let mut button_clicked = false;
+    let is_pending = *login_state == LoginState::Pending;
 
     egui::Window::new("Login Popup")
     .vscroll(true)
     .open(&mut is_login_open.0)
     .show(ctx, |ui| {
-        ui.label("Congratulations on your victory");
-        ui.label("Please login to save your score");
+        match login_state.get() {
+            LoginState::Not => {
+                // Also opened from the Game menu, not only after a victory.
+                ui.label(format!("Your scores are saved as {} for now", guest.guest.handle));
+                ui.label("Please login to keep them with your account");
+            },
+            LoginState::Pending => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Waiting for the login in your browser");
+                });
+                ui.label(format!("Gives up in {:.0}s", login_flow.get_remaining(time.elapsed_seconds_f64())));
+            },
+            LoginState::TimedOut => {
+                ui.label("The login timed out");
+            },
+            LoginState::Cancelled => {
+                ui.label("The login was cancelled");
+            },
+            LoginState::Failed => {
+                ui.colored_label(ERROR_COLOR, format!("The login failed: {}", login_flow.get_error().unwrap_or("unknown error")));
+            },
+            LoginState::Done => {},
+        }
 
-        if ui.button("Login").clicked() {
-            let config = &config;
-            let uuid = uuid.uuid.to_string();
-            initiate_google_login(config, uuid.as_str());
-            button_clicked = true;
+        if is_pending {
+            ui.horizontal(|ui| {
+                if let Some(url) = login_flow.get_url() {
+                    if ui.button("Open Again").on_hover_text("Open the login page in the browser again").clicked() {
+                        auth::open_url(&url);
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel_events.send(LoginCancelEvent);
+                }
+            });
+        } else {
+            let providers = config::platform::get_login_providers(&config);
+            if providers.is_empty() && !config.get_providers().is_empty() {
+                ui.colored_label(ERROR_COLOR, "Logins need session_endpoint in the config");
+            } else if providers.is_empty() {
+                ui.colored_label(ERROR_COLOR, "No login provider is configured");
+            }
+            // After a failed attempt any provider may be tried again, not only the last one.
+            for provider in providers {
+                let text = format!("Login with {}", provider.get_name());
+                if ui.button(text).on_hover_text("Start a new login in the browser").clicked() {
+                    login_events.send(LoginRequestEvent(provider));
+                }
+            }
         }
     });
 
-    if button_clicked {
-        is_login_open.0 = false;
+    // Nobody is waiting for an attempt whose popup was closed.
+    if is_pending && !is_login_open.0 {
+        cancel_events.send(LoginCancelEvent);
+    }
+}
+
+// Lists what was left out of the config, so a typo is not mistaken for a broken login.
+pub fn config_menu(
+    mut contexts: EguiContexts,
+    mut config_errors: ResMut<ConfigErrors>,
+) {
+    let ctx: &mut egui::Context = contexts.ctx_mut();
+    if !config_errors.is_open {
+        return;
+    }
+    let mut is_open = true;
+
+    egui::Window::new("Config Errors")
+    .collapsible(false)
+    .vscroll(true)
+    .open(&mut is_open)
+    .show(ctx, |ui| {
+        ui.label("These settings were left out, the defaults are used instead");
+        for e in config_errors.errors.iter() {
+            ui.colored_label(ERROR_COLOR, e.to_string());
+        }
+        if ui.button("Close").clicked() {
+            config_errors.is_open = false;
+        }
+    });
+
+    if !is_open {
+        config_errors.is_open = false;
+    }
+}
+
+pub fn name_menu(
+    mut contexts: EguiContexts,
+    mut name: ResMut<NameResource>,
+    mut claim_events: EventWriter<ClaimNameEvent>,
+) {
+    let ctx: &mut egui::Context = contexts.ctx_mut();
+    if !name.is_open {
+        return;
+    }
+    let mut is_open = true;
+
+    egui::Window::new("Display Name")
+    .collapsible(false)
+    .resizable(false)
+    .open(&mut is_open)
+    .show(ctx, |ui| {
+        ui.label("Choose the name shown on the leaderboard");
+        ui.label("Your email address is never shown to other players");
+
+        let is_claiming = name.is_claiming();
+        let response = ui.add_enabled(!is_claiming, egui::TextEdit::singleline(&mut name.input).char_limit(NAME_MAX_LEN));
+        let is_entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+        if let Some(error) = name.error.as_ref() {
+            ui.colored_label(ERROR_COLOR, error);
+        }
+
+        ui.horizontal(|ui| {
+            if is_claiming {
+                ui.spinner();
+            } else if ui.button("Save").clicked() || is_entered {
+                claim_events.send(ClaimNameEvent(name.input.clone()));
+            }
+            if ui.button("Later").clicked() {
+                name.is_open = false;
+            }
+        });
+    });
+
+    if !is_open {
+        name.is_open = false;
     }
 }
 
@@ -162,63 +395,86 @@ pub fn about_menu(
 }
 
 const MY_ID_COLOR: egui::Color32 = egui::Color32::from_rgb(150, 222, 150);
+const VERIFIED_COLOR: egui::Color32 = egui::Color32::from_rgb(120, 180, 255);
+const HIGHLIGHT_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 80, 20);
 
-fn display_rankings(ui: &mut egui::Ui, rankings: &Vec<RankingData>, my_id: Option<String>, offset: usize) {
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        for (index, data) in rankings.iter().enumerate() {
-            let is_my_id = match my_id.as_ref() {
-                Some(id) => id == &data.id,
-                None => false,
+fn display_ranking_row(ui: &mut egui::Ui, rank: usize, data: &RankingData, is_my_id: bool, highlight: f32, profile_events: &mut EventWriter<ProfileRequestEvent>) {
+    egui::Frame::none()
+    .fill(HIGHLIGHT_COLOR.gamma_multiply(highlight))
+    .show(ui, |ui| ui.horizontal(|ui| {
+        ui.vertical(|ui| {
+            if is_my_id {
+                ui.colored_label(MY_ID_COLOR, format!("{}", rank));
+            } else {
+                ui.label(format!("{}", rank));
+            }
+            ui.set_min_width(30.0);
+            ui.set_max_width(30.0);
+        });
+        ui.vertical(|ui| {
+            let name = if is_my_id {
+                egui::RichText::new(data.get_name()).color(MY_ID_COLOR)
+            } else {
+                egui::RichText::new(data.get_name())
             };
-            ui.horizontal(|ui| {
-                ui.vertical(|ui| {
-                    if is_my_id {
-                        ui.colored_label(MY_ID_COLOR, format!("{}", index + offset));
-                    } else {
-                        ui.label(format!("{}", index + offset));
-                    }
-                    ui.set_min_width(30.0);
-                    ui.set_max_width(30.0);
-                });
-                ui.vertical(|ui| {
-                    if is_my_id {
-                        ui.colored_label(MY_ID_COLOR, &data.id);
-                    } else {
-                        ui.label(&data.id);
-                    }
-                    ui.set_min_width(200.0);
-                    ui.set_max_width(200.0);
-                });
-                ui.vertical(|ui| {
-                    if is_my_id {
-                        ui.colored_label(MY_ID_COLOR, format!("{}", data.difficulty));
-                    } else {
-                        ui.label(format!("{}", data.difficulty));
-                    }
-                    ui.set_min_width(50.0);
-                    ui.set_max_width(50.0);
-                });
-                ui.vertical(|ui| {
-                    if is_my_id {
-                        ui.colored_label(MY_ID_COLOR, format!("{:.2}", data.time));
-                    } else {
-                        ui.label(format!("{:.2}", data.time));
-                    }
-                    ui.set_min_width(50.0);
-                    ui.set_max_width(50.0);
-                });
-                ui.vertical(|ui| {
-                    if is_my_id {
-                        ui.colored_label(MY_ID_COLOR, format!("{}", data.timestamp_to_date()));
-                    } else {
-                        ui.label(format!("{}", data.timestamp_to_date()));
-                    }
-                    ui.set_min_width(100.0);
-                    ui.set_max_width(100.0);
-                });
-            });
+            if ui.add(egui::Label::new(name).sense(egui::Sense::click())).on_hover_text("Show profile").clicked() {
+                profile_events.send(ProfileRequestEvent(data.id.clone()));
+            }
+            ui.set_min_width(200.0);
+            ui.set_max_width(200.0);
+        });
+        ui.vertical(|ui| {
+            if is_my_id {
+                ui.colored_label(MY_ID_COLOR, format!("{}", data.difficulty));
+            } else {
+                ui.label(format!("{}", data.difficulty));
+            }
+            ui.set_min_width(50.0);
+            ui.set_max_width(50.0);
+        });
+        ui.vertical(|ui| {
+            if is_my_id {
+                ui.colored_label(MY_ID_COLOR, format!("{:.2}", data.time));
+            } else {
+                ui.label(format!("{:.2}", data.time));
+            }
+            ui.set_min_width(50.0);
+            ui.set_max_width(50.0);
+        });
+        ui.vertical(|ui| {
+            if data.verified {
+                ui.colored_label(VERIFIED_COLOR, "✔").on_hover_text("Replay verified");
+            }
+            ui.set_min_width(15.0);
+            ui.set_max_width(15.0);
+        });
+        ui.vertical(|ui| {
+            if is_my_id {
+                ui.colored_label(MY_ID_COLOR, format!("{}", data.timestamp_to_date()));
+            } else {
+                ui.label(format!("{}", data.timestamp_to_date()));
+            }
+            ui.set_min_width(100.0);
+            ui.set_max_width(100.0);
+        });
+    }));
+}
+
+// Returns whether the next page was asked for.
+fn display_rankings(ui: &mut egui::Ui, view: &RankingView, my_id: Option<&str>, is_loading: bool, now: f64, profile_events: &mut EventWriter<ProfileRequestEvent>) -> bool {
+    let mut is_more = false;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for (index, data) in view.entries.iter().enumerate() {
+            display_ranking_row(ui, index + 1, data, my_id == Some(data.id.as_str()), view.get_highlight(data, now), profile_events);
+        }
+
+        if is_loading {
+            ui.spinner();
+        } else if !view.is_last && ui.button("Load More").clicked() {
+            is_more = true;
         }
     });
+    is_more
 }
 
 
@@ -227,41 +483,34 @@ pub fn ranking_menu(
     mut is_ranking_open: ResMut<IsRankingOpen>,
     current_difficulty: Res<Difficulty>,
     mut ranking_difficulty: Local<Difficulty>,
-    mut is_show_my_ranking: Local<bool>,
+    (mut ranking_period, mut period_offset, mut is_verified_only): (Local<RankingPeriod>, Local<u32>, Local<bool>),
     mut next_info_menu_state: ResMut<NextState<MenuInfoState>>,
     current_window_state: Res<State<RankingWindowState>>,
     mut next_window_state: ResMut<NextState<RankingWindowState>>,
-    current_data_reading_state: ResMut<State<DataReadingState>>,
-    mut next_data_reading_state: ResMut<NextState<DataReadingState>>,
-    ranking_data_resource: Res<RankingDataResource>,
+    (mut ranking_data_resource, mut request_events): (ResMut<RankingDataResource>, EventWriter<RankingRequestEvent>),
+    (mut profile, mut profile_events): (ResMut<ProfileResource>, EventWriter<ProfileRequestEvent>),
     mut ui_size: ResMut<UiSize>,
-    login_done: Res<LoginDone>,
+    (login_done, time): (Res<LoginDone>, Res<Time>),
+    mut retry_events: EventWriter<RetryEvent>,
 ) {
     let ctx: &mut egui::Context = contexts.ctx_mut();
 
-    let data_done = ranking_data_resource.is_done.clone();
-
     if is_ranking_open.0 {
         next_info_menu_state.set(MenuInfoState::Opened);
         if *current_window_state == RankingWindowState::Closed {
             next_window_state.set(RankingWindowState::Opened);
-            *ranking_difficulty = *current_difficulty;
-        }
-
-        if *current_data_reading_state == DataReadingState::Idle {
-            next_data_reading_state.set(DataReadingState::Ready);
+            // Custom boards have no leaderboard to show.
+            if current_difficulty.is_standard() {
+                *ranking_difficulty = *current_difficulty;
+            }
         }
     } else {
         if *current_window_state == RankingWindowState::Opened {
             next_window_state.set(RankingWindowState::Closed);
-        }
-
-        if *current_data_reading_state == DataReadingState::Done {
-            next_data_reading_state.set(DataReadingState::Idle);
-            *data_done.lock().unwrap() = false;
+            profile.close();
         }
     }
-    let is_login_done = *login_done.done.lock().unwrap();
+    let id = login_done.id.lock().unwrap().clone();
     
     egui::Window::new("Ranking")
     .vscroll(false)
@@ -272,29 +521,74 @@ pub fn ranking_menu(
             ui.selectable_value(&mut *ranking_difficulty, Difficulty::Normal, "normal");
             ui.selectable_value(&mut *ranking_difficulty, Difficulty::Hard, "hard");
 
-            ui.checkbox(&mut is_show_my_ranking, "Show My Ranking");
+            ui.checkbox(&mut is_verified_only, "Verified Only");
         });
 
-        if !*data_done.lock().unwrap() {
-            ui.spinner();
+        let local_now = chrono::Local::now();
+        ui.horizontal(|ui| {
+            for period in [RankingPeriod::Daily, RankingPeriod::Weekly, RankingPeriod::Monthly, RankingPeriod::AllTime] {
+                if ui.selectable_value(&mut *ranking_period, period, period.get_name()).clicked() {
+                    *period_offset = 0;
+                }
+            }
+
+            if *ranking_period != RankingPeriod::AllTime {
+                ui.separator();
+                if ui.button("◀").on_hover_text("Previous").clicked() {
+                    *period_offset += 1;
+                }
+                ui.label(ranking_period.get_label(*period_offset, local_now));
+                if ui.add_enabled(*period_offset > 0, egui::Button::new("▶")).on_hover_text("Next").clicked() {
+                    *period_offset -= 1;
+                }
+            }
+        });
+
+        // Pages are cached per leaderboard, so switching back and forth fetches nothing new.
+        let query = RankingQuery {
+            difficulty: ranking_difficulty.to_string(),
+            is_verified_only: *is_verified_only,
+            range: ranking_period.get_range(*period_offset, local_now),
+        };
+        let now = time.elapsed_seconds_f64();
+        // Only the current period gets new entries.
+        if *period_offset == 0 {
+            ranking_data_resource.watch(query.clone());
         } else {
-            let difficulty = match *ranking_difficulty {
-                Difficulty::Easy => "Easy",
-                Difficulty::Normal => "Normal",
-                Difficulty::Hard => "Hard",
-            };
-            let id = if is_login_done {
-                let id = login_done.id.lock().unwrap();
-                if let Some(id) = id.as_ref() {
-                    Some(id.clone())
-                } else {
-                    None
+            ranking_data_resource.unwatch();
+        }
+        let is_loading = ranking_data_resource.is_loading(&query);
+        let error = ranking_data_resource.get_error(&query);
+        if error.is_none() && !is_loading && !ranking_data_resource.is_fresh(&query, now) {
+            request_events.send(RankingRequestEvent { query: query.clone(), is_more: false });
+        }
+
+        if let Some(error) = error {
+            ui.colored_label(ERROR_COLOR, error.to_string());
+            if ui.button("Retry").clicked() {
+                retry_events.send(RetryEvent(BackendTask::Fetch));
+            }
+        }
+
+        match ranking_data_resource.get_view(&query) {
+            Some(view) => {
+                if view.is_truncated {
+                    ui.colored_label(ERROR_COLOR, format!("Ranked from the latest {} entries of this period only", MAX_PERIOD_ENTRIES));
                 }
-            } else {
-                None
-            };
-            let (data, offset) = ranking_data_resource.get_sorted_by_difficulty(difficulty, *is_show_my_ranking, id.clone());
-            display_rankings(ui, &data, id, offset);
+                if let Some(best) = view.best.as_ref() {
+                    ui.label("My Best");
+                    display_ranking_row(ui, best.rank as usize, &best.entry, true, 0.0, &mut profile_events);
+                    ui.separator();
+                }
+                if display_rankings(ui, view, id.as_deref(), is_loading, now, &mut profile_events) {
+                    request_events.send(RankingRequestEvent { query, is_more: true });
+                }
+            },
+            None => {
+                if error.is_none() {
+                    ui.spinner();
+                }
+            },
         }
 
         ui_size.width = 500.0;
@@ -303,6 +597,326 @@ pub fn ranking_menu(
 
 }
 
+fn difficulty_color(difficulty: &str) -> egui::Color32 {
+    match difficulty {
+        "Easy" => egui::Color32::from_rgb(150, 222, 150),
+        "Normal" => egui::Color32::from_rgb(240, 200, 100),
+        _ => egui::Color32::from_rgb(222, 120, 120),
+    }
+}
+
+// Times against the day they were set, one line per difficulty.
+fn display_progress_chart(ui: &mut egui::Ui, entries: &[RankingData]) {
+    const DAY_SEC: f64 = 86400.0;
+    let to_date = |day: f64| {
+        chrono::Local.timestamp_opt((day * DAY_SEC) as i64, 0).single()
+            .map_or_else(String::new, |datetime| datetime.format("%m-%d").to_string())
+    };
+
+    egui::plot::Plot::new("profile_chart")
+    .height(150.0)
+    .legend(egui::plot::Legend::default())
+    .allow_scroll(false)
+    .include_y(0.0)
+    .x_axis_formatter(move |day, _range| to_date(day))
+    .label_formatter(move |name, point| format!("{}\n{} {:.2}s", name, to_date(point.x), point.y))
+    .show(ui, |plot_ui| {
+        for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+            let difficulty = difficulty.to_string();
+            let points: Vec<[f64; 2]> = entries.iter()
+                .filter(|data| data.difficulty == difficulty)
+                .map(|data| [data.created_at as f64 / DAY_SEC, data.time as f64])
+                .collect();
+            if points.is_empty() {
+                continue;
+            }
+            let color = difficulty_color(&difficulty);
+            plot_ui.line(egui::plot::Line::new(egui::plot::PlotPoints::from(points.clone())).name(&difficulty).color(color));
+            plot_ui.points(egui::plot::Points::new(egui::plot::PlotPoints::from(points)).name(&difficulty).color(color).radius(2.0));
+        }
+    });
+}
+
+pub fn profile_menu(
+    mut contexts: EguiContexts,
+    mut profile: ResMut<ProfileResource>,
+    mut profile_events: EventWriter<ProfileRequestEvent>,
+) {
+    let ctx: &mut egui::Context = contexts.ctx_mut();
+    let Some(id) = profile.id.clone() else {
+        return;
+    };
+    let mut is_open = true;
+
+    egui::Window::new("Profile")
+    .vscroll(false)
+    .open(&mut is_open)
+    .show(ctx, |ui| {
+        // The latest entry has the current name, and the id is never shown.
+        let name = profile.get_profile(&id)
+            .and_then(|player| player.entries.last())
+            .map_or_else(|| name::get_pseudonym(&id), |data| data.get_name());
+        ui.heading(name);
+
+        if let Some(error) = profile.get_error(&id) {
+            ui.colored_label(ERROR_COLOR, error.to_string());
+            if ui.button("Retry").clicked() {
+                profile_events.send(ProfileRequestEvent(id.clone()));
+            }
+        }
+
+        let Some(player) = profile.get_profile(&id) else {
+            if profile.is_loading(&id) {
+                ui.spinner();
+            }
+            return;
+        };
+
+        egui::Grid::new("profile_bests").striped(true).show(ui, |ui| {
+            ui.strong("Difficulty");
+            ui.strong("Best");
+            ui.strong("Rank");
+            ui.strong("Games");
+            ui.strong("Date");
+            ui.end_row();
+
+            for best in player.bests.iter() {
+                let games = player.entries.iter().filter(|data| data.difficulty == best.entry.difficulty).count();
+                ui.colored_label(difficulty_color(&best.entry.difficulty), &best.entry.difficulty);
+                ui.label(format!("{:.2}", best.entry.time));
+                ui.label(format!("#{}", best.rank));
+                ui.label(games.to_string());
+                ui.label(best.entry.timestamp_to_date());
+                ui.end_row();
+            }
+        });
+
+        if player.entries.is_empty() {
+            ui.label("No records yet");
+        } else {
+            display_progress_chart(ui, &player.entries);
+        }
+    });
+
+    if !is_open {
+        profile.close();
+    }
+}
+
+const CLEARED_COLOR: egui::Color32 = egui::Color32::from_rgb(150, 222, 150);
+const DEAD_COLOR: egui::Color32 = egui::Color32::from_rgb(222, 120, 120);
+
+fn player_status_color(status: PlayerStatus) -> Option<egui::Color32> {
+    match status {
+        PlayerStatus::Cleared => Some(CLEARED_COLOR),
+        PlayerStatus::Dead => Some(DEAD_COLOR),
+        _ => None,
+    }
+}
+
+fn player_color(id: u32) -> egui::Color32 {
+    let (r, g, b) = lobby::get_player_color(id);
+    egui::Color32::from_rgb(r, g, b)
+}
+
+pub fn lobby_menu(
+    mut contexts: EguiContexts,
+    mut is_lobby_open: ResMut<IsLobbyOpen>,
+    mut next_game_menu_state: ResMut<NextState<MenuGameState>>,
+    current_window_state: Res<State<LobbyWindowState>>,
+    mut next_window_state: ResMut<NextState<LobbyWindowState>>,
+    mut lobby: ResMut<LobbyResource>,
+    (race, mut spectate): (Res<RaceResource>, ResMut<SpectateResource>),
+    (difficulty, mut game_state): (Res<Difficulty>, ResMut<NextState<GameState>>),
+    mut ui_size: ResMut<UiSize>,
+) {
+    let ctx: &mut egui::Context = contexts.ctx_mut();
+
+    if is_lobby_open.0 {
+        next_game_menu_state.set(MenuGameState::Opened);
+        if *current_window_state == LobbyWindowState::Closed {
+            next_window_state.set(LobbyWindowState::Opened);
+        }
+    } else {
+        if *current_window_state == LobbyWindowState::Opened {
+            next_window_state.set(LobbyWindowState::Closed);
+            next_game_menu_state.set(MenuGameState::Closed);
+        }
+    }
+
+    egui::Window::new("Multiplayer")
+    .vscroll(false)
+    .open(&mut is_lobby_open.0)
+    .show(ctx, |ui| {
+        if lobby.status == LobbyStatus::Disconnected {
+            ui.horizontal(|ui| {
+                ui.label("Server:");
+                ui.text_edit_singleline(&mut lobby.address);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut lobby.name);
+            });
+            if ui.button("Connect").clicked() {
+                lobby.connect();
+            }
+        } else if lobby.status == LobbyStatus::Connecting {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Connecting to {}", lobby.address));
+            });
+        } else {
+            ui.group(|ui| {
+                for player in lobby.players.iter() {
+                    let is_me = lobby.id == Some(player.id);
+                    let text = format!("{}{}  {:.0}%  {:?}", player.name, if is_me { " (me)" } else { "" }, player.percent, player.status);
+                    let color = player_status_color(player.status).unwrap_or(player_color(player.id));
+                    ui.horizontal(|ui| {
+                        ui.colored_label(color, text);
+                        if player.is_streaming && !is_me {
+                            if spectate.watching == Some(player.id) {
+                                if ui.button("Stop Watching").clicked() {
+                                    spectate.watch(&lobby, None);
+                                    game_state.set(GameState::Init);
+                                }
+                            } else if ui.button("Watch").clicked() {
+                                spectate.watch(&lobby, Some(player.id));
+                            }
+                        }
+                    });
+                }
+            });
+
+            let mut is_streaming = spectate.is_streaming;
+            if ui.checkbox(&mut is_streaming, "Broadcast my game").changed() {
+                spectate.set_streaming(&lobby, is_streaming);
+            }
+
+            if race.status == RaceStatus::Finished {
+                let winner = race.winner
+                    .and_then(|id| lobby.get_player_name(id))
+                    .unwrap_or("nobody");
+                ui.label(format!("Winner: {}", winner));
+            }
+
+            ui.horizontal(|ui| {
+                if race.status != RaceStatus::Racing {
+                    // The relay only starts races on Easy, Normal or Hard.
+                    let is_standard = !matches!(*difficulty, Difficulty::Custom { .. });
+                    let start_race = egui::Button::new(format!("Start Race ({})", difficulty.to_string()));
+                    if ui.add_enabled(is_standard, start_race).on_disabled_hover_text("Races use Easy, Normal or Hard").clicked() {
+                        lobby.send(&ClientMessage::StartRace { difficulty: difficulty.to_string() });
+                    }
+                    if ui.button(format!("Start Co-op ({})", difficulty.to_string())).clicked() {
+                        lobby.send(&ClientMessage::StartCoop { difficulty: difficulty.to_string() });
+                    }
+                }
+                if ui.button("Disconnect").clicked() {
+                    lobby.disconnect();
+                }
+            });
+        }
+
+        if let Some(error) = lobby.error.as_ref() {
+            ui.colored_label(DEAD_COLOR, error);
+        }
+
+        ui_size.width = 300.0;
+        ui_size.height = 200.0;
+    });
+}
+
+// Changes apply at once and are saved by `settings::save_settings`.
+pub fn settings_menu(
+    mut contexts: EguiContexts,
+    mut is_settings_open: ResMut<IsSettingsOpen>,
+    mut next_game_menu_state: ResMut<NextState<MenuGameState>>,
+    (current_window_state, mut next_window_state): (Res<State<SettingsWindowState>>, ResMut<NextState<SettingsWindowState>>),
+    (mut settings, mut rebinding): (ResMut<Settings>, ResMut<KeyRebinding>),
+    (mut difficulty, mut game_state): (ResMut<Difficulty>, ResMut<NextState<GameState>>),
+    mut ui_size: ResMut<UiSize>,
+) {
+    let ctx: &mut egui::Context = contexts.ctx_mut();
+
+    if is_settings_open.0 {
+        next_game_menu_state.set(MenuGameState::Opened);
+        if *current_window_state == SettingsWindowState::Closed {
+            next_window_state.set(SettingsWindowState::Opened);
+        }
+    } else {
+        if *current_window_state == SettingsWindowState::Opened {
+            next_window_state.set(SettingsWindowState::Closed);
+            next_game_menu_state.set(MenuGameState::Closed);
+            rebinding.0 = None;
+        }
+    }
+
+    egui::Window::new("Settings")
+    .collapsible(false)
+    .vscroll(false)
+    .open(&mut is_settings_open.0)
+    .show(ctx, |ui| {
+        egui::Grid::new("settings_grid").num_columns(2).show(ui, |ui| {
+            ui.label("Difficulty");
+            ui.horizontal(|ui| {
+                for option in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+                    if ui.selectable_label(*difficulty == option, option.to_string()).clicked() && *difficulty != option {
+                        *difficulty = option;
+                        game_state.set(GameState::Init);
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("Theme");
+            ui.horizontal(|ui| {
+                for theme in Theme::ALL {
+                    if ui.selectable_label(settings.theme == theme, theme.get_name()).clicked() && settings.theme != theme {
+                        settings.theme = theme;
+                    }
+                }
+            });
+            ui.end_row();
+        });
+
+        ui.separator();
+
+        // Only written when toggled, so an untouched window does not save.
+        let mut is_question_marks = settings.is_question_marks;
+        if ui.checkbox(&mut is_question_marks, "Question Marks").on_hover_text("Right click a flag to mark the cell with ?").changed() {
+            settings.is_question_marks = is_question_marks;
+        }
+        let mut is_safe_start = settings.is_safe_start;
+        if ui.checkbox(&mut is_safe_start, "Safe Start").on_hover_text("The first click opens an area\nThese games are not ranked").changed() {
+            settings.is_safe_start = is_safe_start;
+        }
+        let mut is_sound = settings.is_sound;
+        if ui.checkbox(&mut is_sound, "Sounds").changed() {
+            settings.is_sound = is_sound;
+        }
+
+        ui.separator();
+
+        egui::Grid::new("key_grid").num_columns(2).show(ui, |ui| {
+            for action in KeyAction::ALL {
+                ui.label(action.get_name());
+                let text = if rebinding.0 == Some(action) {
+                    "Press a key...".to_string()
+                } else {
+                    format!("{:?}", settings.key_bindings.get(action))
+                };
+                if ui.button(text).on_hover_text("Click, then press the new key, or Escape to keep the old one").clicked() {
+                    rebinding.0 = Some(action);
+                }
+                ui.end_row();
+            }
+        });
+
+        ui_size.width = 300.0;
+        ui_size.height = 320.0;
+    });
+}
+
 pub fn ui_system(
     mut contexts: EguiContexts,
     mut app_exit_events: ResMut<Events<AppExit>>,
@@ -314,9 +928,12 @@ pub fn ui_system(
     mut difficulty: ResMut<Difficulty>,
     mut is_about_open: ResMut<IsAboutOpen>,
     mut is_ranking_open: ResMut<IsRankingOpen>,
-    config: Res<Config>,
-    uuid: Res<UuidResource>,
-    login_done: Res<LoginDone>,
+    (login_done, mut outbox, time, skin_resource): (Res<LoginDone>, ResMut<OutboxResource>, Res<Time>, Res<SkinResource>),
+    (mut name, mut is_login_open, guest, mut is_settings_open): (ResMut<NameResource>, ResMut<IsLoginOpen>, Res<GuestResource>, ResMut<IsSettingsOpen>),
+    (backend, network, mut retry_events, input_mode): (Res<BackendResource>, Res<NetworkResource>, EventWriter<RetryEvent>, Res<InputMode>),
+    mut logout_events: EventWriter<LogoutEvent>,
+    (mut seed, mut ghost, mut bot, mut settings): (ResMut<Seed>, ResMut<GhostResource>, ResMut<BotResource>, ResMut<Settings>),
+    (lobby, race, coop, spectate, mut is_lobby_open): (Res<LobbyResource>, Res<RaceResource>, Res<CoopResource>, Res<SpectateResource>, ResMut<IsLobbyOpen>),
 ) {
     let ctx: &mut egui::Context = contexts.ctx_mut();
 
@@ -331,16 +948,31 @@ pub fn ui_system(
 
                 if is_login_done {
                     let id = login_done.id.lock().unwrap();
-                    if let Some(id) = id.as_ref() {
-                        ui.label(format!("{}", id));
+                    let display_name = login_done.name.lock().unwrap();
+                    if let Some(display_name) = display_name.as_ref() {
+                        ui.label(display_name);
+                    } else if id.is_some() {
+                        if ui.button("Choose Name").clicked() {
+                            name.is_open = true;
+                            ui.close_menu();
+                            next_game_menu_state.set(MenuGameState::Closed);
+                        }
                     } else {
                         ui.label("Login: Error");
                     }
+                    // The local user would only be logged in again at once.
+                    if backend.auth.is_remote() && ui.button("Log out").clicked() {
+                        logout_events.send(LogoutEvent);
+                        ui.close_menu();
+                        next_game_menu_state.set(MenuGameState::Closed);
+                    }
                 } else {
+                    if backend.auth.is_remote() {
+                        ui.label(format!("{} (guest)", guest.guest.handle));
+                    }
+                    // The popup asks for the provider.
                     if ui.button("Login").clicked() {
-                        let config = &config;
-                        let uuid = uuid.uuid.to_string();
-                        initiate_google_login(config, uuid.as_str());
+                        is_login_open.0 = true;
                         ui.close_menu();
                         next_game_menu_state.set(MenuGameState::Closed);
                     }
@@ -348,21 +980,24 @@ pub fn ui_system(
 
                 ui.separator();
 
-                if ui.selectable_label(*difficulty == Difficulty::Easy, "Easy").clicked() {
+                // A co-op board belongs to the server and a race to its seed, so they stay as they are.
+                let is_solo = *input_mode == InputMode::Local && !race.is_racing();
+
+                if ui.add_enabled(is_solo, egui::SelectableLabel::new(*difficulty == Difficulty::Easy, "Easy")).clicked() {
                     *difficulty = Difficulty::Easy;
                     game_state.set(GameState::Init);
                     ui.close_menu();
                     next_game_menu_state.set(MenuGameState::Closed);
                 }
 
-                if ui.selectable_label(*difficulty == Difficulty::Normal, "Normal").clicked() {
+                if ui.add_enabled(is_solo, egui::SelectableLabel::new(*difficulty == Difficulty::Normal, "Normal")).clicked() {
                     *difficulty = Difficulty::Normal;
                     game_state.set(GameState::Init);
                     ui.close_menu();
                     next_game_menu_state.set(MenuGameState::Closed);
                 }
 
-                if ui.selectable_label(*difficulty == Difficulty::Hard, "Hard").clicked() {
+                if ui.add_enabled(is_solo, egui::SelectableLabel::new(*difficulty == Difficulty::Hard, "Hard")).clicked() {
                     *difficulty = Difficulty::Hard;
                     game_state.set(GameState::Init);
                     ui.close_menu();
@@ -371,6 +1006,52 @@ pub fn ui_system(
 
                 ui.separator();
 
+                ui.horizontal(|ui| {
+                    ui.label("Seed:");
+                    if ui.add_enabled(is_solo, egui::DragValue::new(&mut seed.value).clamp_range(0..=u32::MAX)).changed() {
+                        seed.is_fixed = true;
+                        game_state.set(GameState::Ready);
+                    }
+                });
+                ui.add_enabled(is_solo, egui::Checkbox::new(&mut seed.is_fixed, "Replay Seed"));
+                ui.checkbox(&mut ghost.is_enabled, "Show Ghost");
+
+                ui.separator();
+
+                ui.add_enabled(is_solo, egui::Checkbox::new(&mut bot.is_enabled, "Play for me"));
+                ui.add(egui::Slider::new(&mut bot.moves_per_sec, bot::MIN_MOVES_PER_SEC..=bot::MAX_MOVES_PER_SEC).text("moves/s"));
+                ui.add_enabled(is_solo, egui::Checkbox::new(&mut bot.is_auto_restart, "Auto Restart"));
+
+                ui.separator();
+
+                if ui.button("Multiplayer").clicked() {
+                    is_lobby_open.0 = true;
+                    ui.close_menu();
+                }
+
+                if ui.button("Settings").clicked() {
+                    is_settings_open.0 = true;
+                    ui.close_menu();
+                }
+
+                ui.menu_button("Skin", |ui| {
+                    for skin in skin_resource.skins.iter() {
+                        if ui.selectable_label(settings.skin == skin.id, &skin.name).clicked() && settings.skin != skin.id {
+                            settings.skin = skin.id.clone();
+                        }
+                    }
+                    // Kept up to date while the skin is edited.
+                    if !skin_resource.errors.is_empty() {
+                        ui.separator();
+                        ui.label(format!("{} has problems:", skin_resource.get_name(&skin_resource.current)));
+                        for e in skin_resource.errors.iter() {
+                            ui.colored_label(ERROR_COLOR, e);
+                        }
+                    }
+                });
+
+                ui.separator();
+
                 if ui.button("Quit").clicked() {
                     app_exit_events.send(AppExit);
                 }
@@ -385,7 +1066,7 @@ pub fn ui_system(
             });
 
             menu_game.response.clicked_elsewhere().then(|| {
-                if *current_game_menu_state == MenuGameState::Opened {
+                if *current_game_menu_state == MenuGameState::Opened && !is_lobby_open.0 && !is_settings_open.0 {
                     next_game_menu_state.set(MenuGameState::Closed);
                 }
             });
@@ -417,6 +1098,90 @@ pub fn ui_system(
                     next_info_menu_state.set(MenuInfoState::Closed);
                 }
             });
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if backend.ranking.is_remote() {
+                    let (color, status) = match network.status {
+                        ConnectionStatus::Connecting => (egui::Color32::YELLOW, "Connecting"),
+                        ConnectionStatus::Online => (egui::Color32::GREEN, "Online"),
+                        ConnectionStatus::Offline => (ERROR_COLOR, "Offline"),
+                    };
+                    let indicator = egui::Label::new(egui::RichText::new("●").color(color)).sense(egui::Sense::click());
+                    let hover_text = match network.last_error.as_ref() {
+                        Some((task, error)) => format!("{}\n{} failed: {}\nClick to retry", status, task.get_name(), error),
+                        None => status.to_string(),
+                    };
+                    if ui.add(indicator).on_hover_text(hover_text).clicked() {
+                        if let Some((task, _)) = network.last_error.as_ref() {
+                            retry_events.send(RetryEvent(*task));
+                        }
+                    }
+                } else {
+                    ui.label("Local");
+                }
+
+                if !outbox.entries.is_empty() {
+                    let num_pending = outbox.entries.len();
+                    if outbox.is_sending() {
+                        ui.label(format!("Submitting {}...", num_pending));
+                    } else if let Some(error) = outbox.get_last_error() {
+                        let retry_in = outbox.get_retry_in(time.elapsed_seconds_f64());
+                        let text = format!("{} unsent, retry in {:.0}s", num_pending, retry_in);
+                        let button = egui::Button::new(egui::RichText::new(text).color(egui::Color32::LIGHT_RED)).frame(false);
+                        if ui.add(button).on_hover_text(format!("{}\nClick to retry now", error)).clicked() {
+                            outbox.retry_now();
+                        }
+                    } else if !*login_done.done.lock().unwrap() {
+                        ui.label(format!("{} waiting for login", num_pending));
+                    } else if login_done.name.lock().unwrap().is_none() {
+                        ui.label(format!("{} waiting for display name", num_pending));
+                    } else {
+                        ui.label(format!("{} pending", num_pending));
+                    }
+                }
+
+                if !outbox.failed.is_empty() {
+                    let text = format!("{} rejected", outbox.failed.len());
+                    let errors = outbox.failed.iter()
+                        .map(|entry| format!("{} {:.3}s: {}", entry.submission.difficulty, entry.submission.time_ms as f64 / 1000.0, entry.last_error.as_deref().unwrap_or_default()))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let button = egui::Button::new(egui::RichText::new(text).color(egui::Color32::LIGHT_RED)).frame(false);
+                    if ui.add(button).on_hover_text(format!("{}\nClick to send again", errors)).clicked() {
+                        outbox.retry_failed();
+                    }
+                }
+
+                if race.is_in_race() {
+                    for player in lobby.players.iter().filter(|player| lobby.id != Some(player.id)) {
+                        let text = format!("{} {:.0}%", player.name, player.percent);
+                        match player_status_color(player.status) {
+                            Some(color) => ui.colored_label(color, text),
+                            None => ui.label(text),
+                        };
+                    }
+                }
+
+                if coop.is_active {
+                    for player in lobby.players.iter() {
+                        ui.colored_label(player_color(player.id), player.name.as_str());
+                    }
+                }
+
+                if let Some(id) = spectate.watching {
+                    ui.label(format!("Watching {}", lobby.get_player_name(id).unwrap_or("...")));
+                }
+
+                if bot.is_enabled {
+                    ui.label(format!("Bot: {}", bot.get_player_name()));
+                }
+
+                if ghost.is_enabled {
+                    if let Some((percent, time)) = ghost.get_progress() {
+                        ui.label(format!("Ghost {:.0}% / {:.2}s", percent, time));
+                    }
+                }
+            });
         });
     });
 }
\ No newline at end of file
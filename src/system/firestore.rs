@@ -1,15 +1,30 @@
 use bevy::prelude::*;
 use std::sync::{Arc, Mutex};
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
-use chrono::{NaiveDateTime, DateTime, Utc};
+use chrono::{DateTime, Local, TimeZone, Utc};
 
+use crate::core::verify::Submission;
+use crate::system::config::Config;
+use crate::system::identity::IdentityProvider;
+use crate::system::backend::{BackendError, BackendResource, BackendResult, LoginRequest, LoginUser, RankingBest, RankingPage, RankingQuery, RankingSink};
+use crate::system::guest;
+use crate::system::network::{BackendTask, NetworkReporter, NetworkResource, RetryEvent};
 use crate::system::uuid::UuidResource;
 use crate::system::difficulty;
-use crate::system::state::{GameState, DataReadingState, LoginPopupState, LoginState};
+use crate::system::replay::MoveLog;
+use crate::system::seed::Seed;
+use crate::system::state::{GameState, LoginPopupState, LoginState, RankingWindowState};
 use crate::system::egui::IsLoginOpen;
+use crate::system::mouse::run_if_local_input;
+use crate::system::outbox::{flush_outbox, OutboxResource};
+use crate::system::bot::run_if_unassisted;
+use crate::system::layout::run_if_seed_board;
+use crate::system::difficulty::run_if_standard_difficulty;
+use crate::system::name::{self, NameResource};
+use crate::system::session;
 
-pub const PROJECT_ID: &str = "minesweeper-86284";
 pub struct FirestorePlugin;
 
 impl Plugin for FirestorePlugin {
@@ -17,23 +32,33 @@ impl Plugin for FirestorePlugin {
         self.build_default(app);
         app
             .init_resource::<LoginDone>()
+            .init_resource::<LoginFlow>()
             .init_resource::<RankingDataResource>()
-            .init_resource::<RankingDataTempResource>()
-            .add_state::<DataReadingState>()
+            .init_resource::<OutboxResource>()
+            .add_event::<RankingRequestEvent>()
+            .add_event::<LoginRequestEvent>()
+            .add_event::<LoginCancelEvent>()
             .add_state::<LoginPopupState>()
             .add_state::<LoginState>()
-            .add_systems(Startup, platform::init_firestore)
-            .add_systems(Update, login_check)
-            .add_systems(OnEnter(GameState::Win), platform::add_ranking)
-            .add_systems(OnEnter(DataReadingState::Ready), platform::read_ranking)
-            .add_systems(OnEnter(LoginState::Done), platform::login_done);
+            .add_systems(Startup, init_login)
+            .add_systems(Update, (login_check, start_login, stop_login, poll_login, flush_outbox, retry_backend, read_ranking, poll_ranking, listen_ranking))
+            .add_systems(OnEnter(GameState::Win), add_ranking.after(crate::core::update::time::stop)
+                .run_if(run_if_local_input)
+                .run_if(run_if_unassisted)
+                .run_if(run_if_seed_board)
+                .run_if(run_if_standard_difficulty))
+            .add_systems(OnExit(RankingWindowState::Opened), unlisten_ranking)
+            .add_systems(OnEnter(LoginState::Done), login_done);
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 pub struct LoginDone{
     pub done: Arc<Mutex<bool>>,
     pub id: Arc<Mutex<Option<String>>>,
+    pub name: Arc<Mutex<Option<String>>>,
+    pub session: Arc<Mutex<Option<String>>>,
+    pub is_restoring: Arc<Mutex<bool>>,
 }
 
 impl Default for LoginDone {
@@ -41,24 +66,105 @@ impl Default for LoginDone {
         Self {
             done: Arc::new(Mutex::new(false)),
             id: Arc::new(Mutex::new(None)),
+            name: Arc::new(Mutex::new(None)),
+            session: Arc::new(Mutex::new(None)),
+            is_restoring: Arc::new(Mutex::new(false)),
         }
     }
 }
 
+impl LoginDone {
+    // Keeps the session for the next launch before the rest of the game sees the login.
+    fn set_user(&self, user: LoginUser) {
+        info!("login done: {}", user.user_id);
+        if let Some(token) = user.session.clone() {
+            session::save(&session::Session {
+                user_id: user.user_id.clone(),
+                token,
+            });
+        }
+        *self.id.lock().unwrap() = Some(user.user_id);
+        *self.name.lock().unwrap() = user.name;
+        *self.session.lock().unwrap() = user.session;
+        *self.done.lock().unwrap() = true;
+    }
+}
+
+// A login in the browser is given up after this many seconds.
+pub const LOGIN_TIMEOUT_SEC: f64 = 180.0;
+
+/// Starts a login with the provider in the browser with a fresh state, replacing the attempt in
+/// progress.
+#[derive(Event, Debug, Clone)]
+pub struct LoginRequestEvent(pub IdentityProvider);
+
+/// Stops waiting for the login in progress.
+#[derive(Event, Debug, Clone)]
+pub struct LoginCancelEvent;
+
+type LoginResult = (String, BackendResult<LoginUser>);
+
+/// The login attempt in progress. How the last attempt ended is kept in `LoginState`.
+#[derive(Resource, Default)]
+pub struct LoginFlow {
+    uuid: Option<String>,
+    url: Arc<Mutex<Option<String>>>,
+    started_at: f64,
+    error: Option<String>,
+    results: Arc<Mutex<Vec<LoginResult>>>,
+}
+
+impl LoginFlow {
+    /// The login page of the attempt in progress, once the backend has opened it.
+    pub fn get_url(&self) -> Option<String> {
+        self.uuid.as_ref()?;
+        self.url.lock().unwrap().clone()
+    }
+
+    pub fn get_remaining(&self, now: f64) -> f64 {
+        (self.started_at + LOGIN_TIMEOUT_SEC - now).max(0.0)
+    }
+
+    pub fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RankingData {
+    /// The opaque key of the user, or the email address of rows from before display names.
     pub id: String,
+    #[serde(default)]
+    pub name: String,
     pub time: f32,
     pub difficulty: String,
     pub created_at: u64,
+    // Set by the verifier once the move log replays to a win, never by the client.
+    #[serde(default)]
+    pub verified: bool,
+    /// Submitted by a guest, until it logs in and the entry moves to its account.
+    #[serde(default)]
+    pub guest: bool,
+    /// The id of the document, which orders entries of the same time and second.
+    #[serde(default)]
+    pub doc_id: String,
 }
 
 impl RankingData {
+    pub fn get_name(&self) -> String {
+        if self.guest {
+            format!("{} (guest)", self.name)
+        } else if self.name.is_empty() {
+            name::get_pseudonym(&self.id)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    // In the local time zone, like the boundaries of the period leaderboards.
     pub fn timestamp_to_date(&self) -> String {
-        let ts = self.created_at;
-        let naive_datetime = NaiveDateTime::from_timestamp_opt(ts as i64, 0).unwrap();
-        let utc_datetime: DateTime<Utc> = DateTime::from_naive_utc_and_offset(naive_datetime, Utc);
-        utc_datetime.format("%Y-%m-%d").to_string()
+        let local_datetime = Local.timestamp_opt(self.created_at as i64, 0).single().unwrap_or_default();
+        local_datetime.format("%Y-%m-%d").to_string()
     }
 }
 
@@ -72,73 +178,150 @@ impl Ord for RankingData {
     fn cmp(&self, other: &Self) -> Ordering {
         self.time.partial_cmp(&other.time).unwrap_or(Ordering::Equal)
             .then_with(|| self.created_at.cmp(&other.created_at))
+            .then_with(|| self.doc_id.cmp(&other.doc_id))
     }
 }
 
 impl PartialEq for RankingData {
     fn eq(&self, other: &Self) -> bool {
-        self.time == other.time && self.created_at == other.created_at
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 impl Eq for RankingData {}
 
-#[derive(Resource)]
-pub struct RankingDataTempResource {
-    pub time: f32,
-    pub difficulty: String,
-    pub saved: bool,
+fn get_submission(
+    difficulty: &difficulty::Difficulty,
+    seed: &Seed,
+    move_log: &MoveLog,
+    timer: &crate::system::timer::platform::Timer,
+) -> Submission {
+    Submission {
+        difficulty: difficulty.to_string(),
+        seed: seed.value,
+        time_ms: timer.get_milli_sec() as u64,
+        moves: move_log.moves.clone(),
+    }
 }
 
-impl Default for RankingDataTempResource {
-    fn default() -> Self 
-    {
-        Self {
-            time: 0.0,
-            difficulty: difficulty::Difficulty::Hard.to_string(),
-            saved: false,
-        }
-    }
+pub const RANKING_PAGE_SIZE: u32 = 20;
+// Reopening the ranking window within this many seconds shows the cached pages without a fetch.
+const RANKING_CACHE_TTL_SEC: f64 = 60.0;
+// Entries that arrive live stay highlighted this long, fading out.
+const RANKING_HIGHLIGHT_SEC: f64 = 5.0;
+
+/// Asks for the first page of a leaderboard, or with `is_more` the page after the cached ones.
+#[derive(Event, Debug, Clone)]
+pub struct RankingRequestEvent {
+    pub query: RankingQuery,
+    pub is_more: bool,
 }
 
-#[derive(Debug, Resource)]
-pub struct RankingDataResource {
-    pub data: Arc<Mutex<Vec<RankingData>>>,
-    pub is_done: Arc<Mutex<bool>>,
+/// The pages of a leaderboard fetched so far, and the best entry of the user on it.
+#[derive(Debug, Clone, Default)]
+pub struct RankingView {
+    pub entries: Vec<RankingData>,
+    pub is_last: bool,
+    /// Ranked from only the latest entries of a busy period.
+    pub is_truncated: bool,
+    pub best: Option<RankingBest>,
+    fetched_at: f64,
+    // When each live entry arrived, by user id and creation time.
+    highlights: HashMap<(String, u64), f64>,
 }
 
-impl Default for RankingDataResource {
-    fn default() -> Self {
-        Self {
-            data: Arc::new(Mutex::new(Vec::new())),
-            is_done: Arc::new(Mutex::new(false)),
+impl RankingView {
+    /// How strongly to highlight an entry that arrived live, from 1 when it arrives down to 0.
+    pub fn get_highlight(&self, data: &RankingData, now: f64) -> f32 {
+        self.highlights.get(&(data.id.clone(), data.created_at))
+            .map_or(0.0, |arrived_at| (1.0 - (now - arrived_at) / RANKING_HIGHLIGHT_SEC).max(0.0) as f32)
+    }
+
+    // Puts an entry where it ranks, replacing the copy it updates. Entries that rank below the
+    // loaded pages are left for the page that will hold them.
+    fn add_live(&mut self, entry: RankingData, my_id: Option<&str>, now: f64) {
+        self.entries.retain(|data| data.id != entry.id || data.created_at != entry.created_at);
+        let index = self.entries.partition_point(|data| *data < entry);
+        if index == self.entries.len() && !self.is_last {
+            return;
+        }
+
+        let is_best = my_id == Some(entry.id.as_str())
+            && self.best.iter().all(|best| entry <= best.entry);
+        if is_best {
+            self.best = Some(RankingBest { entry: entry.clone(), rank: index as u32 + 1 });
         }
+        self.highlights.insert((entry.id.clone(), entry.created_at), now);
+        self.entries.insert(index, entry);
     }
 }
 
+// The listener following the leaderboard on screen.
+struct RankingLive {
+    listen_id: u32,
+    query: RankingQuery,
+    sink: RankingSink,
+}
+
+struct RankingFetched {
+    page: RankingPage,
+    is_more: bool,
+    best: Option<RankingBest>,
+}
+
+type RankingResult = (RankingQuery, BackendResult<RankingFetched>);
+
+#[derive(Resource, Default)]
+pub struct RankingDataResource {
+    views: HashMap<RankingQuery, RankingView>,
+    loading: HashSet<RankingQuery>,
+    results: Arc<Mutex<Vec<RankingResult>>>,
+    error: Option<(RankingQuery, BackendError)>,
+    watched: Option<RankingQuery>,
+    live: Option<RankingLive>,
+    next_listen_id: u32,
+}
+
 impl RankingDataResource {
-    pub fn get_sorted_by_difficulty(&self, difficulty: &str, is_show_my_ranking: bool, id: Option<String>) -> (Vec<RankingData>, usize) {
-        let lock = self.data.lock().unwrap();
-        let mut sorted_data: Vec<RankingData> = lock.iter().filter(|&item| item.difficulty == difficulty).cloned().collect();
-        
-        sorted_data.sort();
-        if is_show_my_ranking && id.is_some() {
-            let id = id.clone();
-            let id = id.unwrap();
-            if let Some(index) = sorted_data.iter().position(|item| item.id == id) {
-                let start = if index < 9 { 0 } else { index - 9 };
-                let end = usize::min(start + 20, sorted_data.len());
-
-                let truncated_data = sorted_data[start..end].to_vec();
-
-                return (truncated_data, start + 1);
-            } else {
-                sorted_data.truncate(100);
-                (sorted_data, 1)
+    pub fn get_view(&self, query: &RankingQuery) -> Option<&RankingView> {
+        self.views.get(query)
+    }
+
+    pub fn is_loading(&self, query: &RankingQuery) -> bool {
+        self.loading.contains(query)
+    }
+
+    // Past periods are archived: once fetched they cannot change, so they stay until expired.
+    pub fn is_fresh(&self, query: &RankingQuery, now: f64) -> bool {
+        let is_past = query.range.is_some_and(|range| range.end <= Utc::now().timestamp() as u64);
+        self.views.get(query).is_some_and(|view| {
+            now - view.fetched_at < RANKING_CACHE_TTL_SEC || (is_past && view.fetched_at.is_finite())
+        })
+    }
+
+    pub fn get_error(&self, query: &RankingQuery) -> Option<&BackendError> {
+        self.error.as_ref().filter(|(error_query, _)| error_query == query).map(|(_, error)| error)
+    }
+
+    /// Follows `query` live until the ranking window closes.
+    pub fn watch(&mut self, query: RankingQuery) {
+        self.watched = Some(query);
+    }
+
+    pub fn unwatch(&mut self) {
+        self.watched = None;
+    }
+
+    pub fn clear_error(&mut self) {
+        self.error = None;
+    }
+
+    // Stale views stay on screen until the next fetch replaces them.
+    pub fn expire(&mut self, difficulty: Option<&str>) {
+        for (query, view) in self.views.iter_mut() {
+            if difficulty.iter().all(|difficulty| query.difficulty == *difficulty) {
+                view.fetched_at = f64::NEG_INFINITY;
             }
-        } else {
-            sorted_data.truncate(100);
-            (sorted_data, 1)
         }
     }
 }
@@ -150,166 +333,638 @@ pub fn login_check(
 ) {
     let login_done = login_done.done.clone();
     let login_done = login_done.lock().unwrap();
-    if *login_done && *current_state == LoginState::Not {
+    if *login_done && *current_state != LoginState::Done {
         next_state.set(LoginState::Done);
     }
 }
 
+// Connects, then restores the session saved by an earlier launch in the background. A failure
+// ends the task, and a retry starts a new one.
+fn spawn_restore(runtime: &platform::TasksRuntime, backend: &BackendResource, login_done: &LoginDone, reporter: NetworkReporter) {
+    let Some(saved) = session::load() else {
+        return;
+    };
+    {
+        let mut is_restoring = login_done.is_restoring.lock().unwrap();
+        if *is_restoring || *login_done.done.lock().unwrap() {
+            return;
+        }
+        *is_restoring = true;
+    }
+
+    let auth = backend.auth.clone();
+    let login_done = login_done.clone();
+
+    runtime.spawn_background_task(move |_ctx| async move {
+        let connected = auth.connect().await;
+        reporter.report(BackendTask::Connect, &connected);
+        if connected.is_ok() {
+            let restored = auth.restore_session(saved.token).await;
+            reporter.report(BackendTask::Login, &restored);
+            match restored {
+                Ok(Some(user)) => login_done.set_user(user),
+                Ok(None) => {
+                    info!("session expired");
+                    session::clear();
+                },
+                Err(_) => {},
+            }
+        }
+        *login_done.is_restoring.lock().unwrap() = false;
+    });
+}
+
+// The local backend has nobody to wait for, so it logs in at once.
+pub fn init_login(
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done, network): (Res<BackendResource>, Res<LoginDone>, Res<NetworkResource>),
+    mut login_events: EventWriter<LoginRequestEvent>,
+) {
+    if backend.auth.is_remote() {
+        spawn_restore(&runtime, &backend, &login_done, network.reporter.clone());
+    } else {
+        login_events.send(LoginRequestEvent(IdentityProvider::default()));
+    }
+}
+
+pub fn start_login(
+    mut login_events: EventReader<LoginRequestEvent>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, config, network): (Res<BackendResource>, Res<Config>, Res<NetworkResource>),
+    (login_done, mut login_flow, mut uuid): (Res<LoginDone>, ResMut<LoginFlow>, ResMut<UuidResource>),
+    mut next_state: ResMut<NextState<LoginState>>,
+    time: Res<Time>,
+) {
+    let Some(LoginRequestEvent(provider)) = login_events.iter().last().cloned() else {
+        return;
+    };
+    if *login_done.done.lock().unwrap() {
+        return;
+    }
+
+    if let Some(previous) = login_flow.uuid.take() {
+        backend.auth.cancel_login(previous);
+    }
+    // An answer left under an earlier state must not answer this attempt.
+    *uuid = UuidResource::default();
+    // The login function reads the provider from the state it gets back.
+    let state = format!("{}.{}", provider.get_id(), uuid.uuid);
+    let request = LoginRequest::new(state.clone(), provider, config.clone());
+    login_flow.url = request.url.clone();
+    login_flow.uuid = Some(state.clone());
+    login_flow.started_at = time.elapsed_seconds_f64();
+    login_flow.error = None;
+    next_state.set(LoginState::Pending);
+
+    let auth = backend.auth.clone();
+    let reporter = network.reporter.clone();
+    let results = login_flow.results.clone();
+    runtime.spawn_background_task(move |_ctx| async move {
+        let connected = auth.connect().await;
+        reporter.report(BackendTask::Connect, &connected);
+        let user = match connected {
+            Ok(()) => auth.listen_login(request).await,
+            Err(e) => Err(e),
+        };
+        results.lock().unwrap().push((state, user));
+    });
+}
+
+pub fn stop_login(
+    mut cancel_events: EventReader<LoginCancelEvent>,
+    backend: Res<BackendResource>,
+    mut login_flow: ResMut<LoginFlow>,
+    mut next_state: ResMut<NextState<LoginState>>,
+) {
+    if cancel_events.iter().count() == 0 {
+        return;
+    }
+    let Some(state) = login_flow.uuid.take() else {
+        return;
+    };
+    info!("login cancelled");
+    backend.auth.cancel_login(state);
+    next_state.set(LoginState::Cancelled);
+}
+
+pub fn poll_login(
+    (backend, network, login_done): (Res<BackendResource>, Res<NetworkResource>, Res<LoginDone>),
+    mut login_flow: ResMut<LoginFlow>,
+    current_state: Res<State<LoginState>>,
+    mut next_state: ResMut<NextState<LoginState>>,
+    time: Res<Time>,
+) {
+    let results = std::mem::take(&mut *login_flow.results.lock().unwrap());
+    for (state, result) in results {
+        // Answers to attempts that were cancelled, timed out or replaced since.
+        if login_flow.uuid.as_deref() != Some(state.as_str()) {
+            continue;
+        }
+        login_flow.uuid = None;
+        network.reporter.report(BackendTask::Login, &result);
+        match result {
+            Ok(user) => login_done.set_user(user),
+            Err(e) => {
+                login_flow.error = Some(e.to_string());
+                next_state.set(LoginState::Failed);
+            },
+        }
+    }
+
+    if *current_state == LoginState::Pending && login_flow.get_remaining(time.elapsed_seconds_f64()) <= 0.0 {
+        if let Some(state) = login_flow.uuid.take() {
+            info!("login timed out");
+            backend.auth.cancel_login(state);
+            next_state.set(LoginState::TimedOut);
+        }
+    }
+}
+
+pub fn retry_backend(
+    mut retry_events: EventReader<RetryEvent>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done, network): (Res<BackendResource>, Res<LoginDone>, Res<NetworkResource>),
+    mut outbox: ResMut<OutboxResource>,
+    mut ranking_data: ResMut<RankingDataResource>,
+) {
+    for RetryEvent(task) in retry_events.iter() {
+        match task {
+            // A login in the browser is retried from the login popup instead.
+            BackendTask::Connect | BackendTask::Login => {
+                spawn_restore(&runtime, &backend, &login_done, network.reporter.clone());
+            },
+            BackendTask::Submit => outbox.retry_now(),
+            // An open ranking window fetches again once the error is gone.
+            BackendTask::Fetch => ranking_data.clear_error(),
+        }
+    }
+}
+
+// Every win goes through the outbox, so it is kept until the backend has it. A guest submits it
+// under its own id, so only a player without one is asked to log in.
+pub fn add_ranking(
+    (difficulty, seed, move_log): (Res<difficulty::Difficulty>, Res<Seed>, Res<MoveLog>),
+    timer: Res<crate::system::timer::platform::Timer>,
+    (backend, login_done): (Res<BackendResource>, Res<LoginDone>),
+    mut outbox: ResMut<OutboxResource>,
+    (mut is_login_open, mut name): (ResMut<IsLoginOpen>, ResMut<NameResource>),
+) {
+    outbox.push(get_submission(&difficulty, &seed, &move_log, &timer));
+    if guest::is_guest_active(&backend, &login_done) {
+        return;
+    }
+    if !*login_done.done.lock().unwrap() {
+        *is_login_open = IsLoginOpen(true);
+    } else if login_done.name.lock().unwrap().is_none() {
+        name.is_open = true;
+    }
+}
+
+pub fn login_done(
+    mut outbox: ResMut<OutboxResource>,
+    mut ranking_data: ResMut<RankingDataResource>,
+) {
+    // Cached views were fetched without the best entry of the user.
+    ranking_data.expire(None);
+    if !outbox.entries.is_empty() {
+        info!("login done with {} pending results", outbox.entries.len());
+        outbox.retry_now();
+    }
+}
+
+pub fn read_ranking(
+    mut request_events: EventReader<RankingRequestEvent>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done, network): (Res<BackendResource>, Res<LoginDone>, Res<NetworkResource>),
+    mut ranking_data: ResMut<RankingDataResource>,
+) {
+    for RankingRequestEvent { query, is_more } in request_events.iter() {
+        if ranking_data.is_loading(query) {
+            continue;
+        }
+        let after = if *is_more {
+            match ranking_data.get_view(query) {
+                Some(view) if !view.is_last => view.entries.last().cloned(),
+                _ => continue,
+            }
+        } else {
+            None
+        };
+        // The best entry only changes with the first page, so later pages keep the cached one.
+        let id = if *is_more { None } else { login_done.id.lock().unwrap().clone() };
+
+        ranking_data.loading.insert(query.clone());
+        ranking_data.clear_error();
+        let ranking = backend.ranking.clone();
+        let reporter = network.reporter.clone();
+        let results = ranking_data.results.clone();
+        let query = query.clone();
+        let is_more = *is_more;
+
+        runtime.spawn_background_task(move |_ctx| async move {
+            let fetched = async {
+                let page = ranking.fetch_page(query.clone(), after, RANKING_PAGE_SIZE).await?;
+                // A first page that is also the last holds the whole leaderboard.
+                let best = match id {
+                    Some(id) if page.is_last => RankingBest::find(&page.entries, &id),
+                    Some(id) => ranking.fetch_best(query.clone(), id).await?,
+                    None => None,
+                };
+                Ok::<_, BackendError>(RankingFetched { page, is_more, best })
+            }.await;
+            reporter.report(BackendTask::Fetch, &fetched);
+            results.lock().unwrap().push((query, fetched));
+        });
+    }
+}
+
+pub fn poll_ranking(
+    mut ranking_data: ResMut<RankingDataResource>,
+    time: Res<Time>,
+) {
+    let results = std::mem::take(&mut *ranking_data.results.lock().unwrap());
+    for (query, result) in results {
+        ranking_data.loading.remove(&query);
+        match result {
+            Ok(fetched) => {
+                let view = ranking_data.views.entry(query).or_default();
+                if fetched.is_more {
+                    view.entries.extend(fetched.page.entries);
+                } else {
+                    view.entries = fetched.page.entries;
+                    view.best = fetched.best;
+                    view.fetched_at = time.elapsed_seconds_f64();
+                }
+                view.is_last = fetched.page.is_last;
+                view.is_truncated = fetched.page.is_truncated;
+            },
+            Err(e) => ranking_data.error = Some((query, e)),
+        }
+    }
+}
+
+// Swaps the listener whenever the ranking window shows another leaderboard.
+pub fn listen_ranking(
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done, network): (Res<BackendResource>, Res<LoginDone>, Res<NetworkResource>),
+    mut ranking_data: ResMut<RankingDataResource>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    if ranking_data.live.as_ref().map(|live| &live.query) != ranking_data.watched.as_ref() {
+        if let Some(live) = ranking_data.live.take() {
+            backend.ranking.unlisten(live.listen_id);
+        }
+        if let Some(query) = ranking_data.watched.clone() {
+            ranking_data.next_listen_id += 1;
+            let live = RankingLive {
+                listen_id: ranking_data.next_listen_id,
+                query,
+                sink: RankingSink::default(),
+            };
+
+            let ranking = backend.ranking.clone();
+            let reporter = network.reporter.clone();
+            let (listen_id, query, sink) = (live.listen_id, live.query.clone(), live.sink.clone());
+            runtime.spawn_background_task(move |_ctx| async move {
+                let listened = ranking.listen(listen_id, query, sink).await;
+                reporter.report(BackendTask::Fetch, &listened);
+            });
+            ranking_data.live = Some(live);
+        }
+    }
+
+    let Some(live) = ranking_data.live.as_ref() else {
+        return;
+    };
+    let query = live.query.clone();
+    let added = std::mem::take(&mut *live.sink.lock().unwrap());
+    let my_id = login_done.id.lock().unwrap().clone();
+    if let Some(view) = ranking_data.views.get_mut(&query) {
+        view.highlights.retain(|_, arrived_at| now - *arrived_at < RANKING_HIGHLIGHT_SEC);
+        for entry in added {
+            view.add_live(entry, my_id.as_deref(), now);
+        }
+    }
+}
+
+pub fn unlisten_ranking(
+    backend: Res<BackendResource>,
+    mut ranking_data: ResMut<RankingDataResource>,
+) {
+    ranking_data.unwatch();
+    if let Some(live) = ranking_data.live.take() {
+        backend.ranking.unlisten(live.listen_id);
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub mod platform {
     use super::*;
+    use std::path::Path;
+    use std::time::Duration;
     use firestore::*;
-    use bevy_tokio_tasks::TokioTasksRuntime;
-    use bevy_tokio_tasks::TaskContext;
     use chrono::Utc;
 
-    const TARGET_ID_BY_DOC_IDS: FirestoreListenerTarget = FirestoreListenerTarget::new(17_u32);
-    const LOGIN_COLLECTION: &str = "login";
+    use crate::system::backend::{AuthBackend, BackendError, BackendFuture, LocalBackend, LoginRequest, LoginUser, PeriodCache, RankingBackend, RankingPeriod, MAX_PERIOD_ENTRIES};
+    use crate::system::oauth;
+    use crate::system::period::RankingRange;
+
+    pub type TasksRuntime = bevy_tokio_tasks::TokioTasksRuntime;
+
+    const TARGET_ID_RANKING: FirestoreListenerTarget = FirestoreListenerTarget::new(18_u32);
     const RANKING_COLLECTION: &str = "ranking";
+    const USERS_COLLECTION: &str = "users";
+    const SESSIONS_COLLECTION: &str = "sessions";
+    const GUESTS_COLLECTION: &str = "guests";
     
     impl FirestorePlugin {
         pub fn build_default(&self, app: &mut App) {
-            app
-                .add_plugins(bevy_tokio_tasks::TokioTasksPlugin::default())
-                .init_resource::<FirestoreResource>();
+            app.add_plugins(bevy_tokio_tasks::TokioTasksPlugin::default());
+            if !app.world.contains_resource::<BackendResource>() {
+                let config = app.world.get_resource::<Config>().cloned().unwrap_or_default();
+                app.insert_resource(get_default_backend(&config));
+            }
         }
     }
 
-    #[derive(Resource)]
-    pub struct FirestoreResource {
-        pub db: Arc<Mutex<Option<FirestoreDb>>>,
+    // Without a service account key Firestore is out of reach, so rankings stay on this machine.
+    // The login only needs the session endpoint, and still works when there is one.
+    pub fn get_default_backend(config: &Config) -> BackendResource {
+        if Path::new(&config.key_path).exists() {
+            return BackendResource::new(FirestoreBackend::new(config));
+        }
+        warn!("{} not found, keeping rankings locally", config.key_path);
+        let local = LocalBackend::with_storage("Local", "ranking");
+        if config.session_endpoint.is_empty() {
+            return BackendResource::new(local);
+        }
+        let local = Arc::new(local);
+        BackendResource {
+            ranking: local.clone(),
+            auth: Arc::new(oauth::SessionBackend::new(config, local)),
+        }
+    }
+
+    /// The project and key to connect with, and the connection once made.
+    #[derive(Clone)]
+    pub struct DbConnection {
+        db: Arc<Mutex<Option<FirestoreDb>>>,
+        project_id: String,
+        key_path: String,
     }
 
-    impl Default for FirestoreResource {
-        fn default() -> Self {
+    pub struct FirestoreBackend {
+        db: DbConnection,
+        // Listeners asked to stop, by listen id.
+        closed: Arc<Mutex<HashSet<u32>>>,
+        // Logins given up on, by state.
+        cancelled: Arc<Mutex<HashSet<String>>>,
+        periods: PeriodCache,
+        session_endpoint: String,
+    }
+
+    impl FirestoreBackend {
+        pub fn new(config: &Config) -> Self {
             Self {
-                db: Arc::new(Mutex::new(None)),
+                db: DbConnection {
+                    db: Arc::new(Mutex::new(None)),
+                    project_id: config.project_id.clone(),
+                    key_path: config.key_path.clone(),
+                },
+                closed: Arc::new(Mutex::new(HashSet::new())),
+                cancelled: Arc::new(Mutex::new(HashSet::new())),
+                periods: PeriodCache::default(),
+                session_endpoint: config.session_endpoint.clone(),
             }
         }
     }
     
+    // `sessions/{token}`, written by the login function next to the login document.
     #[derive(Debug, Clone, Deserialize, Serialize)]
-    struct LoginStructure {
-        #[serde(alias = "_firestore_id")]
-        doc_id: Option<String>,
+    struct SessionStructure {
         user_id: String,
+        expires_at: firestore::FirestoreTimestamp,
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    struct UserStructure {
+        name: String,
+    }
+
+    // `guests/{guest id}` holds the token of the guest, and who it was merged into with the token
+    // as proof, which the rules check before entries may move.
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    struct GuestStructure {
+        token: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        claimed_by: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        proof: Option<String>,
+    }
+
+    // The fields of a ranking entry a merge moves, with the id of its document.
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    struct GuestEntryStructure {
+        #[serde(alias = "_firestore_id", skip_serializing)]
+        doc_id: Option<String>,
+        id: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        guest: bool,
     }
 
     #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct RankingStructure {
         pub id: String,
+        #[serde(default)]
+        pub name: String,
         pub time: f32,
         pub difficulty: String,
         pub created_at: firestore::FirestoreTimestamp,
+        #[serde(default)]
+        pub seed: u64,
+        #[serde(default)]
+        pub moves: Vec<crate::core::rules::Move>,
+        #[serde(default)]
+        pub verified: bool,
+        #[serde(default)]
+        pub guest: bool,
     }
 
-    pub fn init_firestore(
-        runtime: ResMut<TokioTasksRuntime>,
-        firestore: Res<FirestoreResource>,
-        login_done: Res<LoginDone>,
-        uuid: Res<UuidResource>,
-    ) {
-        let db = firestore.db.clone();
-        let id = login_done.id.clone();
-        let login_done = login_done.done.clone();
-        let uuid = uuid.uuid.to_string();
-        
-        runtime.spawn_background_task(move |mut ctx| async move {
-            init(db.clone()).await.expect("Init and Listen failed");
-            listen_login(db.clone(), uuid.as_str(), &mut ctx, id.clone()).await.expect("Init and Listen failed");
-            let mut login_done: std::sync::MutexGuard<'_, bool> = login_done.lock().unwrap();
-            *login_done = true;
-
-            info!("login done: {}", *id.clone().lock().unwrap().as_ref().unwrap());
-        });
-    }
+    // Connects on first use, so a backend can be created before the tokio runtime is running.
+    pub async fn get_db(db: DbConnection) -> Result<FirestoreDb, Box<dyn std::error::Error>> {
+        if let Some(firestore_db) = db.db.lock().unwrap().as_ref() {
+            return Ok(firestore_db.clone());
+        }
 
-    pub async fn init(db: Arc<Mutex<Option<FirestoreDb>>>) -> Result<(), Box<dyn std::error::Error>> {
-        let firebase_db = FirestoreDb::with_options_service_account_key_file(
-            FirestoreDbOptions::new(PROJECT_ID.to_string()),
-            "./key/firebase_key.json".into(),
+        let firestore_db = FirestoreDb::with_options_service_account_key_file(
+            FirestoreDbOptions::new(db.project_id.clone()),
+            db.key_path.clone().into(),
         )
         .await?;
 
-        let mut locked_db = db.lock().unwrap();
-        *locked_db = Some(firebase_db);
-        Ok(())
+        *db.db.lock().unwrap() = Some(firestore_db.clone());
+        Ok(firestore_db)
     }
 
-    pub async fn listen_login(db: Arc<Mutex<Option<FirestoreDb>>>, uuid: &str, ctx: &mut TaskContext, id: Arc<Mutex<Option<String>>>) -> Result<(), Box<dyn std::error::Error>> {
-        info!("listen start: {}", uuid);
-        let firestore_db = {
-            let locked_db = db.lock().unwrap();
-            locked_db.as_ref().ok_or_else(|| Box::<dyn std::error::Error>::from("FirestoreDb is None"))?.clone()
+    pub async fn restore_session(db: DbConnection, token: String) -> BackendResult<Option<LoginUser>> {
+        let firestore_db = get_db(db).await.map_err(get_error)?;
+
+        let session: Option<SessionStructure> = firestore_db.fluent()
+            .select()
+            .by_id_in(SESSIONS_COLLECTION)
+            .obj::<SessionStructure>()
+            .one(&token)
+            .await
+            .map_err(|e| get_error(e.into()))?;
+        let Some(session) = session.filter(|session| session.expires_at.0 > Utc::now()) else {
+            return Ok(None);
         };
-        
-        let mut listener = firestore_db.create_listener(
-            FirestoreTempFilesListenStateStorage::new() // or FirestoreMemListenStateStorage or your own implementation 
-        ).await?;
-        
+
+        // The name may have been chosen on another machine since.
+        let user: Option<UserStructure> = firestore_db.fluent()
+            .select()
+            .by_id_in(USERS_COLLECTION)
+            .obj::<UserStructure>()
+            .one(&session.user_id)
+            .await
+            .map_err(|e| get_error(e.into()))?;
+        Ok(Some(LoginUser {
+            user_id: session.user_id,
+            name: user.map(|user| user.name),
+            session: Some(token),
+        }))
+    }
+
+    pub async fn end_session(db: DbConnection, token: String) -> BackendResult<()> {
+        let firestore_db = get_db(db).await.map_err(get_error)?;
         firestore_db.fluent()
-        .select()
-        .by_id_in(LOGIN_COLLECTION)
-        .batch_listen([uuid])
-        .add_target(TARGET_ID_BY_DOC_IDS, &mut listener)?;
+            .delete()
+            .from(SESSIONS_COLLECTION)
+            .document_id(&token)
+            .execute()
+            .await
+            .map_err(|e| get_error(e.into()))
+    }
 
-        let wait_done = Arc::new(Mutex::new(false));
-        let wait_done_clone = wait_done.clone();
-        let id_clone = id.clone();
+    async fn get_guest(firestore_db: &FirestoreDb, guest_id: &str) -> BackendResult<Option<GuestStructure>> {
+        firestore_db.fluent()
+            .select()
+            .by_id_in(GUESTS_COLLECTION)
+            .obj::<GuestStructure>()
+            .one(guest_id)
+            .await
+            .map_err(|e| get_error(e.into()))
+    }
 
-        listener
-            .start(move |event|  {
-            let wait_done = wait_done_clone.clone();
-            let id = id_clone.clone();
-            async move {
-                match event {
-                    FirestoreListenEvent::DocumentChange(ref doc_change) => {
-                        info!("Doc changed: {:?}", doc_change);
-                        if let Some(doc) = &doc_change.document {
-                            let obj = FirestoreDb::deserialize_doc_to::<LoginStructure>(doc);
-                            if obj.is_err() {
-                                return Ok(());
-                            }
-                            let obj = obj.unwrap();
-                            info!("As object: {:?}", obj);
-                            *wait_done.lock().unwrap() = true;
-                            *id.lock().unwrap() = Some(obj.user_id.clone());
-                        }
-                    }
-                    _ => { }
+    pub async fn register_guest(db: DbConnection, guest_id: String, token: String) -> BackendResult<()> {
+        let firestore_db = get_db(db).await.map_err(get_error)?;
+
+        let inserted: FirestoreResult<GuestStructure> = firestore_db.fluent()
+            .insert()
+            .into(GUESTS_COLLECTION)
+            .document_id(&guest_id)
+            .object(&GuestStructure { token: token.clone(), claimed_by: None, proof: None })
+            .execute()
+            .await;
+        match inserted {
+            Ok(_) => Ok(()),
+            // Already ours when an earlier registration went through but its answer was lost.
+            Err(FirestoreError::DataConflictError(_)) => {
+                let guest = get_guest(&firestore_db, &guest_id).await?;
+                if guest.iter().any(|guest| guest.token == token) {
+                    Ok(())
+                } else {
+                    Err(BackendError::Conflict(guest_id))
                 }
-        
-                Ok(())
-            }
-        })
-        .await?;
-        
-        while *wait_done.lock().unwrap() == false {
-            ctx.sleep_updates(10).await;
+            },
+            Err(e) => Err(get_error(e.into())),
         }
+    }
 
-        listener.shutdown().await?;
-        info!("listen done");
-        
-        Ok(())
+    // The guest is claimed first, so a merge cut short is finished by trying again.
+    pub async fn merge_guest(
+        db: DbConnection,
+        guest_id: String,
+        token: String,
+        user_id: String,
+        name: Option<String>,
+    ) -> BackendResult<u32> {
+        let firestore_db = get_db(db).await.map_err(get_error)?;
+
+        let Some(mut guest) = get_guest(&firestore_db, &guest_id).await? else {
+            return Err(BackendError::Conflict(guest_id));
+        };
+        let is_claimed_by_other = guest.claimed_by.iter().any(|claimed_by| *claimed_by != user_id);
+        if guest.token != token || is_claimed_by_other {
+            return Err(BackendError::Conflict(guest_id));
+        }
+        guest.claimed_by = Some(user_id.clone());
+        guest.proof = Some(token);
+        let _guest: GuestStructure = firestore_db.fluent()
+            .update()
+            .in_col(GUESTS_COLLECTION)
+            .document_id(&guest_id)
+            .object(&guest)
+            .execute()
+            .await
+            .map_err(|e| get_error(e.into()))?;
+
+        let docs = firestore_db.fluent()
+            .select()
+            .from(RANKING_COLLECTION)
+            .filter(|q| q.for_all([q.field("id").eq(guest_id.clone())]))
+            .query()
+            .await
+            .map_err(|e| get_error(e.into()))?;
+
+        let mut count = 0;
+        for doc in docs.iter() {
+            let Ok(mut entry) = FirestoreDb::deserialize_doc_to::<GuestEntryStructure>(doc) else {
+                continue;
+            };
+            let Some(doc_id) = entry.doc_id.clone() else {
+                continue;
+            };
+            entry.id = user_id.clone();
+            entry.name = name.clone().unwrap_or_default();
+            entry.guest = false;
+            let _updated: GuestEntryStructure = firestore_db.fluent()
+                .update()
+                .fields(["id", "name", "guest"])
+                .in_col(RANKING_COLLECTION)
+                .document_id(&doc_id)
+                .object(&entry)
+                .execute()
+                .await
+                .map_err(|e| get_error(e.into()))?;
+            count += 1;
+        }
+        Ok(count)
     }
 
     pub async fn add_ranking_to_db(
-        db: Arc<Mutex<Option<FirestoreDb>>>,
-        id: Arc<Mutex<Option<String>>>,
-        time: f32,
-        difficulty: String,
-    ) { 
-        let firestore_db = {
-            let locked_db = db.lock().unwrap();
-            locked_db.as_ref().ok_or_else(|| Box::<dyn std::error::Error>::from("FirestoreDb is None")).expect("Firestore Db Initialize Exception").clone()
-        };
+        db: DbConnection,
+        id: String,
+        name: String,
+        is_guest: bool,
+        submission: Submission,
+    ) -> Result<(), Box<dyn std::error::Error>> { 
+        let firestore_db = get_db(db).await?;
 
         let ranking_structure = RankingStructure {
-            id: id.lock().unwrap().clone().unwrap(),
-            time: time,
-            difficulty: difficulty,
+            id,
+            name,
+            time: submission.time_ms as f32 / 1000.0,
+            difficulty: submission.difficulty,
             created_at: firestore::FirestoreTimestamp(Utc::now()),
+            seed: submission.seed,
+            moves: submission.moves,
+            verified: false,
+            guest: is_guest,
         };
 
         let _object_returned: RankingStructure = firestore_db.fluent()
@@ -318,119 +973,354 @@ pub mod platform {
             .generate_document_id()
             .object(&ranking_structure)
             .execute()
-            .await.expect("Insert failed");
-    }
-
-    pub fn add_ranking(
-        runtime: ResMut<TokioTasksRuntime>,
-        firestore: Res<FirestoreResource>,
-        login_done: Res<LoginDone>,
-        difficulty: Res<difficulty::Difficulty>,
-        timer: Res<crate::system::timer::platform::Timer>,
-        mut ranking_data_temp: ResMut<RankingDataTempResource>,
-        mut is_login_open: ResMut<IsLoginOpen>,
-    ) {
-        let db = firestore.db.clone();
-        let id = login_done.id.clone();
-        let login_done = login_done.done.clone();
-        let time = timer.get_milli_sec() as f32 / 1000.0;
-        let difficulty = difficulty.clone();
-        
-        if login_done.lock().unwrap().clone() {
-            runtime.spawn_background_task(move |_ctx| async move {     
-                add_ranking_to_db(db, id, time, difficulty.to_string()).await;
-            });
-        } else {
-            *ranking_data_temp = RankingDataTempResource {
-                time: time,
-                difficulty: difficulty.to_string(),
-                saved: true,
-            };
-            *is_login_open = IsLoginOpen(true);
-        }
-    }
-
-    pub fn login_done(
-        runtime: ResMut<TokioTasksRuntime>,
-        firestore: Res<FirestoreResource>,
-        login_done: Res<LoginDone>,
-        ranking_data_temp: Res<RankingDataTempResource>
-    ) {
-        let has_temp = ranking_data_temp.saved;
-        if has_temp {
-            info!("login done with temp data");
-            let db = firestore.db.clone();
-            let id = login_done.id.clone();
-            let time = ranking_data_temp.time;
-            let difficulty = ranking_data_temp.difficulty.clone();
-            runtime.spawn_background_task(move |_ctx| async move {     
-                add_ranking_to_db(db, id, time, difficulty).await;
-            });
-        }
+            .await?;
+        Ok(())
     }
     
-    pub async fn read_ranking_from_db(db: Arc<Mutex<Option<FirestoreDb>>>) -> Result<Vec<RankingData>, Box<dyn std::error::Error>> {
-        info!("read ranking start");
-        let firestore_db = {
-            let locked_db = db.lock().unwrap();
-            locked_db.as_ref().ok_or_else(|| Box::<dyn std::error::Error>::from("FirestoreDb is None"))?.clone()
+    // Orders by the document, after the fields, so entries equal in them keep their place.
+    const DOCUMENT_ID_FIELD: &str = "__name__";
+
+    fn get_ranking_reference(firestore_db: &FirestoreDb, doc_id: &str) -> FirestoreReference {
+        FirestoreReference(format!("{}/{}/{}", firestore_db.get_documents_path(), RANKING_COLLECTION, doc_id))
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct RankingCount {
+        count: u32,
+    }
+
+    fn to_ranking_data(doc: &firestore::Document) -> Option<RankingData> {
+        let obj = FirestoreDb::deserialize_doc_to::<RankingStructure>(doc).ok()?;
+        let doc_id = doc.name.rsplit('/').next().unwrap_or_default().to_string();
+        Some(RankingData {
+            id: obj.id,
+            name: obj.name,
+            time: obj.time,
+            difficulty: obj.difficulty,
+            created_at: obj.created_at.0.timestamp() as u64,
+            verified: obj.verified,
+            guest: obj.guest,
+            doc_id,
+        }).filter(|item| item.created_at != 0)
+    }
+
+    // The latest entries of the period, one more than are kept to tell whether there are more.
+    pub async fn read_period_from_db(
+        db: DbConnection,
+        periods: &PeriodCache,
+        query: &RankingQuery,
+        range: RankingRange,
+    ) -> Result<RankingPeriod, Box<dyn std::error::Error>> {
+        if let Some(period) = periods.get(query) {
+            return Ok(period);
+        }
+        info!("read ranking period start: {:?}", query);
+        let firestore_db = get_db(db).await?;
+        let start = DateTime::from_timestamp(range.start as i64, 0).unwrap_or_default();
+        let end = DateTime::from_timestamp(range.end as i64, 0).unwrap_or_default();
+
+        let mut entries = firestore_db.fluent()
+            .select()
+            .from(RANKING_COLLECTION)
+            .filter(|q| q.for_all([
+                q.field("difficulty").eq(query.difficulty.clone()),
+                q.field("created_at").greater_than_or_equal(firestore::FirestoreTimestamp(start)),
+                q.field("created_at").less_than(firestore::FirestoreTimestamp(end)),
+                if query.is_verified_only { q.field("verified").eq(true) } else { None },
+            ]))
+            .order_by([("created_at", FirestoreQueryDirection::Descending)])
+            .limit(MAX_PERIOD_ENTRIES + 1)
+            .query()
+            .await?
+            .iter()
+            .filter_map(to_ranking_data)
+            .collect::<Vec<RankingData>>();
+        let is_truncated = entries.len() > MAX_PERIOD_ENTRIES as usize;
+        entries.truncate(MAX_PERIOD_ENTRIES as usize);
+        let period = RankingPeriod { entries, is_truncated };
+        periods.insert(query.clone(), period.clone());
+        Ok(period)
+    }
+
+    // Needs the composite indexes in `firestore.indexes.json`.
+    pub async fn read_page_from_db(
+        db: DbConnection,
+        periods: PeriodCache,
+        query: RankingQuery,
+        after: Option<RankingData>,
+        limit: u32,
+    ) -> Result<RankingPage, Box<dyn std::error::Error>> {
+        if let Some(range) = query.range {
+            let period = read_period_from_db(db, &periods, &query, range).await?;
+            return Ok(RankingPage::from_period(period, after, limit));
+        }
+
+        info!("read ranking page start: {:?}", query);
+        let firestore_db = get_db(db).await?;
+
+        let mut select = firestore_db.fluent()
+            .select()
+            .from(RANKING_COLLECTION)
+            .filter(|q| q.for_all([
+                q.field("difficulty").eq(query.difficulty.clone()),
+                if query.is_verified_only { q.field("verified").eq(true) } else { None },
+            ]))
+            .order_by([
+                ("time", FirestoreQueryDirection::Ascending),
+                ("created_at", FirestoreQueryDirection::Ascending),
+                (DOCUMENT_ID_FIELD, FirestoreQueryDirection::Ascending),
+            ])
+            // One more than asked for tells whether another page follows.
+            .limit(limit + 1);
+        if let Some(after) = after {
+            let created_at = DateTime::from_timestamp(after.created_at as i64, 0).unwrap_or_default();
+            select = select.start_at(FirestoreQueryCursor::AfterValue(vec![
+                after.time.into(),
+                firestore::FirestoreTimestamp(created_at).into(),
+                get_ranking_reference(&firestore_db, &after.doc_id).into(),
+            ]));
+        }
+
+        let mut entries = select.query().await?.iter().filter_map(to_ranking_data).collect::<Vec<RankingData>>();
+        let is_last = entries.len() <= limit as usize;
+        entries.truncate(limit as usize);
+        Ok(RankingPage { entries, is_last, is_truncated: false })
+    }
+
+    pub async fn read_best_from_db(
+        db: DbConnection,
+        periods: PeriodCache,
+        query: RankingQuery,
+        id: String,
+    ) -> Result<Option<RankingBest>, Box<dyn std::error::Error>> {
+        if let Some(range) = query.range {
+            let period = read_period_from_db(db, &periods, &query, range).await?;
+            return Ok(RankingBest::find(&period.entries, &id));
+        }
+
+        let firestore_db = get_db(db).await?;
+
+        let best = firestore_db.fluent()
+            .select()
+            .from(RANKING_COLLECTION)
+            .filter(|q| q.for_all([
+                q.field("difficulty").eq(query.difficulty.clone()),
+                q.field("id").eq(id.clone()),
+                if query.is_verified_only { q.field("verified").eq(true) } else { None },
+            ]))
+            .order_by([
+                ("time", FirestoreQueryDirection::Ascending),
+                ("created_at", FirestoreQueryDirection::Ascending),
+                (DOCUMENT_ID_FIELD, FirestoreQueryDirection::Ascending),
+            ])
+            .limit(1)
+            .query()
+            .await?
+            .iter()
+            .find_map(to_ranking_data);
+        let Some(entry) = best else {
+            return Ok(None);
         };
 
-        let results = firestore_db.fluent()
+        // Counted on the server, so the rank costs three reads however far down the entry is: the
+        // faster entries, the equal times made before it, and those of the same second with an
+        // earlier document, in the order of the pages.
+        let created_at = DateTime::from_timestamp(entry.created_at as i64, 0).unwrap_or_default();
+        let mut rank = 1;
+        for tie in 0..3 {
+            let ahead = firestore_db.fluent()
+                .select()
+                .from(RANKING_COLLECTION)
+                .filter(|q| q.for_all([
+                    q.field("difficulty").eq(query.difficulty.clone()),
+                    if tie == 0 { q.field("time").less_than(entry.time) } else { q.field("time").eq(entry.time) },
+                    match tie {
+                        1 => q.field("created_at").less_than(firestore::FirestoreTimestamp(created_at)),
+                        2 => q.field("created_at").eq(firestore::FirestoreTimestamp(created_at)),
+                        _ => None,
+                    },
+                    if tie == 2 { q.field(DOCUMENT_ID_FIELD).less_than(get_ranking_reference(&firestore_db, &entry.doc_id)) } else { None },
+                    if query.is_verified_only { q.field("verified").eq(true) } else { None },
+                ]))
+                .aggregate(|a| a.fields([a.field("count").count()]))
+                .obj::<RankingCount>()
+                .query()
+                .await?;
+            rank += ahead.first().map_or(0, |ahead| ahead.count);
+        }
+
+        Ok(Some(RankingBest { entry, rank }))
+    }
+
+    // The profile shows this many of the latest entries of a user, oldest first.
+    const MAX_USER_ENTRIES: u32 = 500;
+
+    pub async fn read_user_from_db(
+        db: DbConnection,
+        id: String,
+    ) -> Result<Vec<RankingData>, Box<dyn std::error::Error>> {
+        info!("read user start: {}", id);
+        let firestore_db = get_db(db).await?;
+
+        // Read newest first, so the limit leaves out the oldest entries.
+        let mut entries = firestore_db.fluent()
             .select()
             .from(RANKING_COLLECTION)
+            .filter(|q| q.for_all([q.field("id").eq(id.clone())]))
+            .order_by([("created_at", FirestoreQueryDirection::Descending)])
+            .limit(MAX_USER_ENTRIES)
             .query()
-            .await?;
+            .await?
+            .iter()
+            .filter_map(to_ranking_data)
+            .collect::<Vec<RankingData>>();
+        entries.reverse();
+        Ok(entries)
+    }
 
-        let results = results.iter().map(|doc| {
-            let obj = FirestoreDb::deserialize_doc_to::<RankingStructure>(doc);
-            if obj.is_err() {
-                return RankingData {
-                    id: String::new(),
-                    time: 0.0,
-                    difficulty: String::new(),
-                    created_at: 0,
-                };
-            }
+    pub async fn listen_ranking(
+        db: DbConnection,
+        closed: Arc<Mutex<HashSet<u32>>>,
+        listen_id: u32,
+        query: RankingQuery,
+        sink: RankingSink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        info!("listen ranking start: {:?}", query);
+        let firestore_db = get_db(db).await?;
 
-            let obj = obj.unwrap();
-            RankingData {
-                id: obj.id,
-                time: obj.time,
-                difficulty: obj.difficulty,
-                created_at: obj.created_at.0.timestamp() as u64,
+        let mut listener = firestore_db.create_listener(
+            FirestoreMemListenStateStorage::new()
+        ).await?;
+
+        // Only entries from now on, so the first snapshot does not repeat the fetched pages.
+        let since = Utc::now();
+        firestore_db.fluent()
+            .select()
+            .from(RANKING_COLLECTION)
+            .filter(|q| q.for_all([
+                q.field("difficulty").eq(query.difficulty.clone()),
+                q.field("created_at").greater_than_or_equal(firestore::FirestoreTimestamp(since)),
+                if query.is_verified_only { q.field("verified").eq(true) } else { None },
+            ]))
+            .listen()
+            .add_target(TARGET_ID_RANKING, &mut listener)?;
+
+        listener
+            .start(move |event| {
+            let sink = sink.clone();
+            async move {
+                if let FirestoreListenEvent::DocumentChange(ref doc_change) = event {
+                    if let Some(entry) = doc_change.document.as_ref().and_then(to_ranking_data) {
+                        sink.lock().unwrap().push(entry);
+                    }
+                }
+                Ok(())
             }
-        }).collect::<Vec<RankingData>>();
+        })
+        .await?;
 
-        let results = results.iter().filter_map(|item| {
-            if item.created_at == 0 {
-                None
-            } else {
-                Some(item.clone())
+        loop {
+            let is_closed = closed.lock().unwrap().remove(&listen_id);
+            if is_closed {
+                break;
             }
-        }).collect::<Vec<RankingData>>();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
 
-        info!("read ranking done");
-        
-        Ok(results)
+        listener.shutdown().await?;
+        info!("listen ranking done");
+        Ok(())
     }
 
-    pub fn read_ranking(
-        runtime: ResMut<TokioTasksRuntime>,
-        firestore: Res<FirestoreResource>,
-        data_resource: ResMut<RankingDataResource>,
-        mut next_state: ResMut<NextState<DataReadingState>>,
-    ) {
-        let db = firestore.db.clone();
-        let data_done = data_resource.is_done.clone();
-        let data_resource = data_resource.data.clone();
-        next_state.set(DataReadingState::Done);
+    // Everything the firestore crate reports comes from the database or the network on the way to it.
+    // Requests the database refuses for good, like a permission denied by the rules, are rejected.
+    fn get_error(e: Box<dyn std::error::Error>) -> BackendError {
+        match e.downcast_ref::<FirestoreError>() {
+            Some(FirestoreError::DatabaseError(error)) if !error.retry_possible => BackendError::Rejected(e.to_string()),
+            Some(FirestoreError::InvalidParametersError(_) | FirestoreError::SerializeError(_)) => BackendError::Rejected(e.to_string()),
+            _ => BackendError::Connection(e.to_string()),
+        }
+    }
 
-        runtime.spawn_background_task(move |_ctx| async move {
-            let result = read_ranking_from_db(db.clone()).await.expect("read_ranking_from_db async failed");
-            *data_resource.lock().unwrap() = result;
-            *data_done.lock().unwrap() = true;
-        });
+    impl RankingBackend for FirestoreBackend {
+        fn submit(&self, user_id: String, name: String, is_guest: bool, submission: Submission) -> BackendFuture<()> {
+            let db = self.db.clone();
+            let periods = self.periods.clone();
+            Box::pin(async move {
+                let difficulty = submission.difficulty.clone();
+                add_ranking_to_db(db, user_id, name, is_guest, submission).await.map_err(get_error)?;
+                periods.expire(&difficulty);
+                Ok(())
+            })
+        }
+
+        fn fetch_page(&self, query: RankingQuery, after: Option<RankingData>, limit: u32) -> BackendFuture<RankingPage> {
+            let db = self.db.clone();
+            let periods = self.periods.clone();
+            Box::pin(async move { read_page_from_db(db, periods, query, after, limit).await.map_err(get_error) })
+        }
+
+        fn fetch_best(&self, query: RankingQuery, id: String) -> BackendFuture<Option<RankingBest>> {
+            let db = self.db.clone();
+            let periods = self.periods.clone();
+            Box::pin(async move { read_best_from_db(db, periods, query, id).await.map_err(get_error) })
+        }
+
+        fn fetch_user(&self, id: String) -> BackendFuture<Vec<RankingData>> {
+            let db = self.db.clone();
+            Box::pin(async move { read_user_from_db(db, id).await.map_err(get_error) })
+        }
+
+        fn listen(&self, listen_id: u32, query: RankingQuery, sink: RankingSink) -> BackendFuture<()> {
+            let db = self.db.clone();
+            let closed = self.closed.clone();
+            Box::pin(async move { listen_ranking(db, closed, listen_id, query, sink).await.map_err(get_error) })
+        }
+
+        fn unlisten(&self, listen_id: u32) {
+            self.closed.lock().unwrap().insert(listen_id);
+        }
+    }
+
+    impl AuthBackend for FirestoreBackend {
+        fn connect(&self) -> BackendFuture<()> {
+            let db = self.db.clone();
+            Box::pin(async move { get_db(db).await.map(|_| ()).map_err(get_error) })
+        }
+
+        // Native builds log in with PKCE and a loopback redirect, without the login function.
+        fn listen_login(&self, request: LoginRequest) -> BackendFuture<LoginUser> {
+            Box::pin(oauth::login(request, self.cancelled.clone()))
+        }
+
+        fn cancel_login(&self, state: String) {
+            self.cancelled.lock().unwrap().insert(state);
+        }
+
+        fn restore_session(&self, session: String) -> BackendFuture<Option<LoginUser>> {
+            let db = self.db.clone();
+            Box::pin(restore_session(db, session))
+        }
+
+        fn end_session(&self, session: String) -> BackendFuture<()> {
+            let db = self.db.clone();
+            Box::pin(end_session(db, session))
+        }
+
+        // Clients may not write names, so the session endpoint checks the session and claims it.
+        fn claim_name(&self, session: String, name: String) -> BackendFuture<()> {
+            Box::pin(oauth::claim_name(self.session_endpoint.clone(), session, name))
+        }
+
+        fn register_guest(&self, guest_id: String, token: String) -> BackendFuture<()> {
+            let db = self.db.clone();
+            Box::pin(register_guest(db, guest_id, token))
+        }
+
+        fn merge_guest(&self, guest_id: String, token: String, user_id: String, name: Option<String>) -> BackendFuture<u32> {
+            let db = self.db.clone();
+            Box::pin(merge_guest(db, guest_id, token, user_id, name))
+        }
     }
 }
 
@@ -439,139 +1329,316 @@ pub mod platform {
 pub mod platform {
     use super::*;
     use bevy_wasm_tasks::WASMTasksPlugin;
-    use bevy_wasm_tasks::WASMTasksRuntime;
     use wasm_bindgen::prelude::*;
     use js_sys::JsString;
     use wasm_bindgen_futures::JsFuture;
-    use serde_wasm_bindgen::from_value;
+    use serde_wasm_bindgen::{from_value, to_value};
+
+    use crate::system::auth;
+    use crate::system::backend::{AuthBackend, BackendError, BackendFuture, BackendResult, LoginRequest, LoginUser, PeriodCache, RankingBackend, RankingPeriod, MAX_PERIOD_ENTRIES};
+    use crate::system::period::RankingRange;
+
+    pub type TasksRuntime = bevy_wasm_tasks::WASMTasksRuntime;
     
     impl FirestorePlugin {
         pub fn build_default(&self, app: &mut App) {
-            app
-                .add_plugins(WASMTasksPlugin);
+            app.add_plugins(WASMTasksPlugin);
+            if !app.world.contains_resource::<BackendResource>() {
+                let config = app.world.get_resource::<Config>().cloned().unwrap_or_default();
+                app.insert_resource(get_default_backend(&config));
+            }
         }
     }
 
+    pub fn get_default_backend(config: &Config) -> BackendResource {
+        BackendResource::new(JsBackend {
+            periods: PeriodCache::default(),
+            session_endpoint: config.session_endpoint.clone(),
+        })
+    }
+
+    /// Calls the Firebase web SDK through the `*_js` functions in `out/main.js`.
+    pub struct JsBackend {
+        periods: PeriodCache,
+        session_endpoint: String,
+    }
+
     #[wasm_bindgen]
     extern "C" {
         fn listen_login_js(uuid: JsString) -> js_sys::Promise;
+        fn cancel_login_js(uuid: JsString);
     }
 
-    pub async fn listen_login(uuid: String, id: Arc<Mutex<Option<String>>>) -> Result<JsValue, JsValue> {
-        let uuid_js = JsString::from(uuid);
+    // Codes of Firebase errors that come back the same however often the request is sent.
+    const REJECTED_CODES: [&str; 3] = ["permission-denied", "invalid-argument", "failed-precondition"];
+
+    // A rejected promise means Firebase could not be reached or refused the request. `out/main.js`
+    // passes the code of the Firebase error on, if there is one.
+    fn get_error(e: JsValue) -> BackendError {
+        let code = js_sys::Reflect::get(&e, &JsValue::from_str("code")).ok().and_then(|code| code.as_string());
+        let message = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+        match code {
+            Some(code) if REJECTED_CODES.contains(&code.as_str()) => BackendError::Rejected(message),
+            _ => BackendError::Connection(message),
+        }
+    }
+
+    // The browser goes to the login function, which has the client secret and writes the user to
+    // `login/{state}`.
+    pub async fn listen_login(request: LoginRequest) -> BackendResult<LoginUser> {
+        let url = auth::get_login_url(&request.provider, &request.state, &request.config.redirect_uri, &[]);
+        *request.url.lock().unwrap() = Some(url.clone());
+        auth::open_url(&url);
+
+        let uuid_js = JsString::from(request.state);
         let promise = listen_login_js(uuid_js);
-        let result = JsFuture::from(promise).await?;
+        let result = JsFuture::from(promise).await.map_err(get_error)?;
         info!("listen done: {:?}", result);
-        *id.lock().unwrap() = result.as_string();
-        Ok(result)
-    }
-
-    pub fn init_firestore(
-        runtime: ResMut<WASMTasksRuntime>,
-        login_done: Res<LoginDone>,
-        uuid: Res<UuidResource>,
-    ) {
-        let id = login_done.id.clone();
-        let login_done = login_done.done.clone();
-        let uuid = uuid.uuid.to_string();
-        
-        runtime.spawn_background_task(move |_ctx| async move {      
-            listen_login(uuid, id.clone()).await.expect("call js async failed");
-            let mut login_done = login_done.lock().unwrap();
-            *login_done = true;
-
-            info!("login done: {}", *id.clone().lock().unwrap().as_ref().unwrap());
-        });
+        from_value(result).map_err(|e| {
+            BackendError::InvalidData(format!("Failed to deserialize: {:?}", e))
+        })
     }
-    
+
     #[wasm_bindgen]
     extern "C" {
-        fn add_ranking_js(id: JsString, time: f32, difficulty: JsString) -> js_sys::Promise;
+        fn restore_session_js(session: JsString) -> js_sys::Promise;
+        fn end_session_js(session: JsString) -> js_sys::Promise;
     }
 
-    pub async fn add_ranking_to_db(id: String, time: f32, difficulty: String) -> Result<JsValue, JsValue> {
-        let id = JsString::from(id);
-        let difficulty = JsString::from(difficulty);
-        let promise = add_ranking_js(id, time, difficulty);
-        let result = JsFuture::from(promise).await?;
-        Ok(result)
-    }
-
-    pub fn add_ranking(
-        runtime: ResMut<WASMTasksRuntime>,
-        login_done: Res<LoginDone>,
-        difficulty: Res<difficulty::Difficulty>,
-        timer: Res<crate::system::timer::platform::Timer>,
-        mut ranking_data_temp: ResMut<RankingDataTempResource>,
-        mut is_login_open: ResMut<IsLoginOpen>,
-    ) {
-        let id = login_done.id.clone();
-        let login_done = login_done.done.clone();
-        let time = timer.get_milli_sec() as f32 / 1000.0;
-        let difficulty = difficulty.clone();
-        
-        if login_done.lock().unwrap().clone() {
-            runtime.spawn_background_task(move |_ctx| async move {        
-                add_ranking_to_db(id.lock().unwrap().clone().unwrap(), time, difficulty.to_string()).await.expect("Insert failed");
-            });
-        } else {
-            *ranking_data_temp = RankingDataTempResource {
-                time: time,
-                difficulty: difficulty.to_string(),
-                saved: true,
-            };
-            *is_login_open = IsLoginOpen(true);
+    // The promise resolves to null when the session has expired or ended.
+    pub async fn restore_session(session: String) -> BackendResult<Option<LoginUser>> {
+        let promise = restore_session_js(JsString::from(session));
+        let result = JsFuture::from(promise).await.map_err(get_error)?;
+        from_value(result).map_err(|e| {
+            BackendError::InvalidData(format!("Failed to deserialize: {:?}", e))
+        })
+    }
+
+    pub async fn end_session(session: String) -> BackendResult<()> {
+        let promise = end_session_js(JsString::from(session));
+        JsFuture::from(promise).await.map_err(get_error)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    extern "C" {
+        fn claim_name_js(session_endpoint: JsString, session: JsString, name: JsString) -> js_sys::Promise;
+    }
+
+    // The session endpoint checks the session and claims the name, since clients may not write
+    // names. The promise resolves to false when another user has the name.
+    pub async fn claim_name(session_endpoint: String, session: String, name: String) -> BackendResult<()> {
+        let promise = claim_name_js(JsString::from(session_endpoint), JsString::from(session), JsString::from(name.clone()));
+        let result = JsFuture::from(promise).await.map_err(get_error)?;
+        match result.as_bool() {
+            Some(true) => Ok(()),
+            Some(false) => Err(BackendError::Conflict(name)),
+            None => Err(BackendError::InvalidData("Name claim is not a bool".to_string())),
         }
     }
     
-    pub fn login_done(
-        runtime: ResMut<WASMTasksRuntime>,
-        login_done: Res<LoginDone>,
-        ranking_data_temp: Res<RankingDataTempResource>
-    ) {
-        let has_temp = ranking_data_temp.saved;
-        if has_temp {
-            info!("login done with temp data");
-            let id = login_done.id.clone();
-            let time = ranking_data_temp.time;
-            let difficulty = ranking_data_temp.difficulty.clone();
-            
-            runtime.spawn_background_task(move |_ctx| async move {        
-                add_ranking_to_db(id.lock().unwrap().clone().unwrap(), time, difficulty).await.expect("Insert failed");
-            });
+    #[wasm_bindgen]
+    extern "C" {
+        fn register_guest_js(guest_id: JsString, token: JsString) -> js_sys::Promise;
+        fn merge_guest_js(guest_id: JsString, token: JsString, user_id: JsString, name: JsString) -> js_sys::Promise;
+    }
+
+    // The promise resolves to false when the guest was registered with another token.
+    pub async fn register_guest(guest_id: String, token: String) -> BackendResult<()> {
+        let promise = register_guest_js(JsString::from(guest_id.clone()), JsString::from(token));
+        let result = JsFuture::from(promise).await.map_err(get_error)?;
+        match result.as_bool() {
+            Some(true) => Ok(()),
+            Some(false) => Err(BackendError::Conflict(guest_id)),
+            None => Err(BackendError::InvalidData("Guest registration is not a bool".to_string())),
+        }
+    }
+
+    // The promise resolves to the number of entries moved, or to null when the token is wrong.
+    pub async fn merge_guest(guest_id: String, token: String, user_id: String, name: Option<String>) -> BackendResult<u32> {
+        let promise = merge_guest_js(
+            JsString::from(guest_id.clone()),
+            JsString::from(token),
+            JsString::from(user_id),
+            JsString::from(name.unwrap_or_default()),
+        );
+        let result = JsFuture::from(promise).await.map_err(get_error)?;
+        if result.is_null() {
+            return Err(BackendError::Conflict(guest_id));
         }
+        result.as_f64().map(|count| count as u32).ok_or_else(|| {
+            BackendError::InvalidData("Guest merge count is not a number".to_string())
+        })
     }
 
     #[wasm_bindgen]
     extern "C" {
-        fn read_ranking_js() -> js_sys::Promise;
+        fn add_ranking_js(id: JsString, name: JsString, is_guest: bool, time: f32, difficulty: JsString, seed: u32, moves: JsValue) -> js_sys::Promise;
     }
 
-    pub async fn read_ranking_from_db() -> Result<Vec<RankingData>, JsValue> {
-        let promise = read_ranking_js();
-        let result_jsvalue = JsFuture::from(promise).await?;
-        
-        let result: Vec<RankingData> = from_value(result_jsvalue).map_err(|e| {
-            JsValue::from_str(&format!("Failed to deserialize: {:?}", e))
+    pub async fn add_ranking_to_db(id: String, name: String, is_guest: bool, submission: Submission) -> BackendResult<()> {
+        let id = JsString::from(id);
+        let name = JsString::from(name);
+        let time = submission.time_ms as f32 / 1000.0;
+        let difficulty = JsString::from(submission.difficulty);
+        let moves = to_value(&submission.moves).map_err(|e| {
+            BackendError::InvalidData(format!("Failed to serialize: {:?}", e))
         })?;
-        
-        info!("read ranking done");
-        Ok(result)
+        let promise = add_ranking_js(id, name, is_guest, time, difficulty, submission.seed as u32, moves);
+        JsFuture::from(promise).await.map_err(get_error)?;
+        Ok(())
     }
 
-    pub fn read_ranking(
-        runtime: ResMut<WASMTasksRuntime>,
-        data_resource: ResMut<RankingDataResource>,
-        mut next_state: ResMut<NextState<DataReadingState>>,
-    ) {
-        let data_done = data_resource.is_done.clone();
-        let data_resource = data_resource.data.clone();
-        next_state.set(DataReadingState::Done);
+    #[wasm_bindgen]
+    extern "C" {
+        fn read_ranking_page_js(difficulty: JsString, is_verified_only: bool, after_time: Option<f32>, after_created_at: Option<f64>, after_doc_id: Option<JsString>, limit: u32) -> js_sys::Promise;
+        fn read_ranking_best_js(difficulty: JsString, is_verified_only: bool, id: JsString) -> js_sys::Promise;
+        fn read_ranking_period_js(difficulty: JsString, is_verified_only: bool, start: f64, end: f64, limit: u32) -> js_sys::Promise;
+        fn read_ranking_user_js(id: JsString) -> js_sys::Promise;
+    }
 
-        runtime.spawn_background_task(move |_ctx| async move {
-            let result = read_ranking_from_db().await.expect("call js async failed");
-            *data_resource.lock().unwrap() = result;
-            *data_done.lock().unwrap() = true;
+    pub async fn read_user_from_db(id: String) -> BackendResult<Vec<RankingData>> {
+        let promise = read_ranking_user_js(JsString::from(id));
+        let result_jsvalue = JsFuture::from(promise).await.map_err(get_error)?;
+
+        from_value(result_jsvalue).map_err(|e| {
+            BackendError::InvalidData(format!("Failed to deserialize: {:?}", e))
+        })
+    }
+
+    pub async fn read_period_from_db(periods: &PeriodCache, query: &RankingQuery, range: RankingRange) -> BackendResult<RankingPeriod> {
+        if let Some(period) = periods.get(query) {
+            return Ok(period);
+        }
+        let difficulty = JsString::from(query.difficulty.clone());
+        let promise = read_ranking_period_js(difficulty, query.is_verified_only, range.start as f64, range.end as f64, MAX_PERIOD_ENTRIES);
+        let result_jsvalue = JsFuture::from(promise).await.map_err(get_error)?;
+
+        let period: RankingPeriod = from_value(result_jsvalue).map_err(|e| {
+            BackendError::InvalidData(format!("Failed to deserialize: {:?}", e))
+        })?;
+        periods.insert(query.clone(), period.clone());
+        Ok(period)
+    }
+
+    pub async fn read_page_from_db(periods: PeriodCache, query: RankingQuery, after: Option<RankingData>, limit: u32) -> BackendResult<RankingPage> {
+        if let Some(range) = query.range {
+            let period = read_period_from_db(&periods, &query, range).await?;
+            return Ok(RankingPage::from_period(period, after, limit));
+        }
+
+        let difficulty = JsString::from(query.difficulty);
+        let after_time = after.as_ref().map(|after| after.time);
+        let after_created_at = after.as_ref().map(|after| after.created_at as f64);
+        let after_doc_id = after.as_ref().map(|after| JsString::from(after.doc_id.as_str()));
+        let promise = read_ranking_page_js(difficulty, query.is_verified_only, after_time, after_created_at, after_doc_id, limit);
+        let result_jsvalue = JsFuture::from(promise).await.map_err(get_error)?;
+
+        from_value(result_jsvalue).map_err(|e| {
+            BackendError::InvalidData(format!("Failed to deserialize: {:?}", e))
+        })
+    }
+
+    pub async fn read_best_from_db(periods: PeriodCache, query: RankingQuery, id: String) -> BackendResult<Option<RankingBest>> {
+        if let Some(range) = query.range {
+            let period = read_period_from_db(&periods, &query, range).await?;
+            return Ok(RankingBest::find(&period.entries, &id));
+        }
+
+        let difficulty = JsString::from(query.difficulty);
+        let promise = read_ranking_best_js(difficulty, query.is_verified_only, JsString::from(id));
+        let result_jsvalue = JsFuture::from(promise).await.map_err(get_error)?;
+
+        from_value(result_jsvalue).map_err(|e| {
+            BackendError::InvalidData(format!("Failed to deserialize: {:?}", e))
+        })
+    }
+
+    #[wasm_bindgen]
+    extern "C" {
+        fn listen_ranking_js(listen_id: u32, difficulty: JsString, is_verified_only: bool, on_change: &Closure<dyn FnMut(JsValue)>) -> js_sys::Promise;
+        fn unlisten_ranking_js(listen_id: u32);
+    }
+
+    // Resolves once `unlisten_ranking_js` stops the listener, which keeps `on_change` alive until then.
+    pub async fn listen_ranking(listen_id: u32, query: RankingQuery, sink: RankingSink) -> BackendResult<()> {
+        let on_change = Closure::<dyn FnMut(JsValue)>::new(move |value: JsValue| {
+            match from_value::<RankingData>(value) {
+                Ok(entry) => sink.lock().unwrap().push(entry),
+                Err(e) => warn!("Failed to deserialize: {:?}", e),
+            }
         });
+        let difficulty = JsString::from(query.difficulty);
+        let promise = listen_ranking_js(listen_id, difficulty, query.is_verified_only, &on_change);
+        JsFuture::from(promise).await.map_err(get_error)?;
+        Ok(())
     }
-}
\ No newline at end of file
+
+    impl RankingBackend for JsBackend {
+        fn submit(&self, user_id: String, name: String, is_guest: bool, submission: Submission) -> BackendFuture<()> {
+            let periods = self.periods.clone();
+            Box::pin(async move {
+                let difficulty = submission.difficulty.clone();
+                add_ranking_to_db(user_id, name, is_guest, submission).await?;
+                periods.expire(&difficulty);
+                Ok(())
+            })
+        }
+
+        fn fetch_page(&self, query: RankingQuery, after: Option<RankingData>, limit: u32) -> BackendFuture<RankingPage> {
+            Box::pin(read_page_from_db(self.periods.clone(), query, after, limit))
+        }
+
+        fn fetch_best(&self, query: RankingQuery, id: String) -> BackendFuture<Option<RankingBest>> {
+            Box::pin(read_best_from_db(self.periods.clone(), query, id))
+        }
+
+        fn fetch_user(&self, id: String) -> BackendFuture<Vec<RankingData>> {
+            Box::pin(read_user_from_db(id))
+        }
+
+        fn listen(&self, listen_id: u32, query: RankingQuery, sink: RankingSink) -> BackendFuture<()> {
+            Box::pin(listen_ranking(listen_id, query, sink))
+        }
+
+        fn unlisten(&self, listen_id: u32) {
+            unlisten_ranking_js(listen_id);
+        }
+    }
+
+    impl AuthBackend for JsBackend {
+        // The web SDK connects lazily and reports failures on each call instead.
+        fn connect(&self) -> BackendFuture<()> {
+            Box::pin(async move { Ok(()) })
+        }
+
+        fn listen_login(&self, request: LoginRequest) -> BackendFuture<LoginUser> {
+            Box::pin(listen_login(request))
+        }
+
+        // The pending `listen_login_js` promise rejects.
+        fn cancel_login(&self, state: String) {
+            cancel_login_js(JsString::from(state));
+        }
+
+        fn restore_session(&self, session: String) -> BackendFuture<Option<LoginUser>> {
+            Box::pin(restore_session(session))
+        }
+
+        fn end_session(&self, session: String) -> BackendFuture<()> {
+            Box::pin(end_session(session))
+        }
+
+        fn claim_name(&self, session: String, name: String) -> BackendFuture<()> {
+            Box::pin(claim_name(self.session_endpoint.clone(), session, name))
+        }
+
+        fn register_guest(&self, guest_id: String, token: String) -> BackendFuture<()> {
+            Box::pin(register_guest(guest_id, token))
+        }
+
+        fn merge_guest(&self, guest_id: String, token: String, user_id: String, name: Option<String>) -> BackendFuture<u32> {
+            Box::pin(merge_guest(guest_id, token, user_id, name))
+        }
+    }
+}
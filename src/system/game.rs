@@ -13,11 +13,17 @@ impl Plugin for GamePlugin {
         app
             .add_state::<system::state::GameState>()
             .init_resource::<asset::loader::TextureAtlasResource>()
+            .init_resource::<asset::skin::SkinResource>()
             .init_resource::<system::timer::platform::Timer>()
             .init_resource::<system::uuid::UuidResource>()
             .init_resource::<component::mine::TotalMine>()
             .init_resource::<component::mine::RemainingMine>()
             .init_resource::<component::grid::Grid>()
+            .init_resource::<system::seed::Seed>()
+            .init_resource::<system::replay::MoveLog>()
+            .init_resource::<system::mouse::InputMode>()
+            .add_event::<system::replay::MoveEvent>()
+            .add_event::<system::mouse::RestartEvent>()
             .add_systems(Startup, asset::loader::setup)
             .add_systems(PostStartup, core::init::camera::init)
             .add_systems(OnEnter(system::state::GameState::Init), (
@@ -27,23 +33,28 @@ impl Plugin for GamePlugin {
             ))
             .add_systems(Update, (
                 system::mouse::mouse_events_system.after(system::egui::ui_system)
-                    .run_if(system::state::run_if_all_menu_closed),
+                    .run_if(system::state::run_if_all_menu_closed)
+                    .run_if(system::egui::run_if_toast_not_hovered),
+                system::replay::record.after(system::mouse::mouse_events_system),
                 core::update::smiles::update,
                 core::update::time::update,
             ))
             .add_systems(OnEnter(system::state::GameState::Ready), (
+                system::seed::roll,
+                system::replay::reset,
                 core::update::cells::reset,
                 core::update::time::reset,
             ))
             .add_systems(Update, (
                 core::update::cells::texture_for_ready,
-                core::update::cells::first_click.after(core::update::cells::texture_for_ready),
+                core::update::cells::first_click.after(core::update::cells::texture_for_ready)
+                    .run_if(system::mouse::run_if_local_input),
                 core::update::mines::update_for_ready.after(core::update::cells::first_click),
             )
             .run_if(in_state(system::state::GameState::Ready)))
             .add_systems(OnEnter(system::state::GameState::Playing), core::update::time::start)
             .add_systems(Update, (
-                core::update::cells::update,
+                core::update::cells::update.run_if(system::mouse::run_if_local_input),
                 core::update::cells::texture_for_playing.after(core::update::cells::update),
                 core::update::mines::update_for_playing.after(core::update::cells::texture_for_playing),
             ).run_if(in_state(system::state::GameState::Playing)))
@@ -58,6 +69,6 @@ impl Plugin for GamePlugin {
                 core::update::time::stop,
                 core::update::smiles::set_win,
             ))
-            .insert_resource(system::difficulty::Difficulty::Hard);
+            .init_resource::<system::difficulty::Difficulty>();
     }
 }
\ No newline at end of file
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::asset::texture_type::CellType;
+use crate::component::ghost::GhostCell;
+use crate::component::grid::Grid;
+use crate::component::mine::TotalMine;
+use crate::core::rules::{Board, Move, TileState};
+use crate::system::difficulty::Difficulty;
+use crate::system::mouse::run_if_local_input;
+use crate::system::bot::run_if_unassisted;
+use crate::system::layout::run_if_seed_board;
+use crate::system::replay::MoveLog;
+use crate::system::seed::{self, Seed};
+use crate::system::state::GameState;
+use crate::system::timer::platform::Timer;
+use crate::utils::storage;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GhostResource>()
+            .add_systems(OnEnter(GameState::Ready), load_ghost.after(seed::roll))
+            .add_systems(Update, update_ghost)
+            .add_systems(OnEnter(GameState::Win), save_best_run.after(crate::core::update::time::stop)
+                .run_if(run_if_local_input)
+                .run_if(run_if_unassisted)
+                .run_if(run_if_seed_board));
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GhostRun {
+    pub difficulty: String,
+    pub seed: u64,
+    pub time_ms: u64,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Resource)]
+pub struct GhostResource {
+    pub is_enabled: bool,
+    pub run: Option<GhostRun>,
+    board: Option<Board>,
+    next_move: usize,
+}
+
+impl Default for GhostResource {
+    fn default() -> Self {
+        Self {
+            is_enabled: true,
+            run: None,
+            board: None,
+            next_move: 0,
+        }
+    }
+}
+
+impl GhostResource {
+    pub fn get_key(difficulty: &Difficulty, seed: u64) -> String {
+        format!("ghost_{}_{}", difficulty.to_string().to_lowercase(), seed)
+    }
+
+    // Percentage of safe cells the ghost has revealed so far, and its winning time in seconds.
+    pub fn get_progress(&self) -> Option<(f32, f32)> {
+        let run = self.run.as_ref()?;
+        let board = self.board.as_ref()?;
+        let num_safe = board.width * board.height - board.num_mines;
+        let percent = if num_safe == 0 {
+            100.0
+        } else {
+            board.num_revealed() as f32 * 100.0 / num_safe as f32
+        };
+        Some((percent, run.time_ms as f32 / 1000.0))
+    }
+
+    fn advance(&mut self, time_ms: u64) {
+        let (Some(run), Some(board)) = (self.run.as_ref(), self.board.as_mut()) else {
+            return;
+        };
+        while let Some(ghost_move) = run.moves.get(self.next_move) {
+            if ghost_move.time_ms > time_ms {
+                break;
+            }
+            board.apply(ghost_move.action, ghost_move.x, ghost_move.y);
+            self.next_move += 1;
+        }
+    }
+}
+
+pub fn load_ghost(
+    mut ghost: ResMut<GhostResource>,
+    difficulty: Res<Difficulty>,
+    seed: Res<Seed>,
+    grid: Res<Grid>,
+    mines: Res<TotalMine>,
+) {
+    let key = GhostResource::get_key(&difficulty, seed.value);
+    ghost.run = storage::load_yaml::<GhostRun>(&key);
+    ghost.board = Some(Board::new(grid.width, grid.height, mines.0, seed.value));
+    ghost.next_move = 0;
+}
+
+pub fn update_ghost(
+    mut ghost: ResMut<GhostResource>,
+    timer: Res<Timer>,
+    current_state: Res<State<GameState>>,
+    mut q_ghost_cells: Query<(&GhostCell, &mut TextureAtlasSprite, &mut Visibility)>,
+) {
+    if *current_state == GameState::Playing || *current_state == GameState::Win || *current_state == GameState::Defeated {
+        ghost.advance(timer.get_milli_sec() as u64);
+    }
+
+    let board = if ghost.is_enabled && ghost.run.is_some() {
+        ghost.board.as_ref()
+    } else {
+        None
+    };
+
+    for (ghost_cell, mut sprite, mut visibility) in q_ghost_cells.iter_mut() {
+        let index = match board {
+            Some(board) if board.contains(ghost_cell.x, ghost_cell.y) => {
+                match board.state(ghost_cell.x, ghost_cell.y) {
+                    TileState::Hidden => None,
+                    TileState::Revealed => Some(CellType::get_revealed_num(board.get_num_mines_around(ghost_cell.x, ghost_cell.y)) as usize),
+                    TileState::Flagged => Some(CellType::Flag as usize),
+                    TileState::WrongFlagged => Some(CellType::WrongMine as usize),
+                    TileState::Exploded => Some(CellType::MineBombed as usize),
+                }
+            },
+            _ => None,
+        };
+
+        match index {
+            Some(index) => {
+                if sprite.index != index {
+                    sprite.index = index;
+                }
+                if *visibility != Visibility::Inherited {
+                    *visibility = Visibility::Inherited;
+                }
+            },
+            None => {
+                if *visibility != Visibility::Hidden {
+                    *visibility = Visibility::Hidden;
+                }
+            },
+        }
+    }
+}
+
+pub fn save_best_run(
+    move_log: Res<MoveLog>,
+    timer: Res<Timer>,
+    difficulty: Res<Difficulty>,
+    seed: Res<Seed>,
+) {
+    let key = GhostResource::get_key(&difficulty, seed.value);
+    let time_ms = timer.get_milli_sec() as u64;
+    let best = storage::load_yaml::<GhostRun>(&key);
+    if let Some(best) = best {
+        if best.time_ms <= time_ms {
+            return;
+        }
+    }
+
+    let run = GhostRun {
+        difficulty: difficulty.to_string(),
+        seed: seed.value,
+        time_ms,
+        moves: move_log.moves.clone(),
+    };
+    if let Err(e) = storage::save_yaml(&key, &run) {
+        warn!("{}", e);
+    }
+}
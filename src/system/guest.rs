@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::system::backend::{BackendError, BackendResource, BackendResult};
+use crate::system::firestore::{platform, LoginDone, RankingDataResource};
+use crate::system::network::{BackendTask, NetworkResource};
+use crate::system::outbox::OutboxResource;
+use crate::utils::storage;
+
+const GUEST_KEY: &str = "guest";
+// A failed registration or merge is tried again after this many seconds.
+const RETRY_SEC: f64 = 30.0;
+
+pub struct GuestPlugin;
+
+impl Plugin for GuestPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GuestResource>()
+            .add_systems(Update, (register_guest, merge_guest, poll_guest));
+    }
+}
+
+/// The anonymous identity wins are submitted under while logged out. Only the holder of `token`
+/// can merge its entries into a real account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Guest {
+    pub id: String,
+    pub handle: String,
+    pub token: String,
+    /// The backend knows the token, so entries may be submitted.
+    #[serde(default)]
+    pub is_registered: bool,
+}
+
+impl Guest {
+    fn new() -> Self {
+        let id = Uuid::new_v4().simple().to_string();
+        Self {
+            handle: format!("Guest-{}", &id[..6]),
+            id: format!("guest-{}", id),
+            token: Uuid::new_v4().simple().to_string(),
+            is_registered: false,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct GuestResource {
+    pub guest: Guest,
+    is_busy: bool,
+    retry_at: f64,
+    registered: Arc<Mutex<Option<BackendResult<()>>>>,
+    merged: Arc<Mutex<Option<BackendResult<u32>>>>,
+}
+
+impl Default for GuestResource {
+    fn default() -> Self {
+        let guest = storage::load_yaml::<Guest>(GUEST_KEY).unwrap_or_else(|| {
+            let guest = Guest::new();
+            save(&guest);
+            guest
+        });
+        Self {
+            guest,
+            is_busy: false,
+            retry_at: 0.0,
+            registered: Arc::new(Mutex::new(None)),
+            merged: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+// The token is as good as the guest itself, like a session.
+fn save(guest: &Guest) {
+    if let Err(e) = storage::save_private_yaml(GUEST_KEY, guest) {
+        warn!("{}", e);
+    }
+}
+
+/// Whether wins go out under the guest: when logins go through a provider, while nobody is logged
+/// in or being logged in from a saved session.
+pub fn is_guest_active(backend: &BackendResource, login_done: &LoginDone) -> bool {
+    backend.auth.is_remote()
+        && !*login_done.done.lock().unwrap()
+        && !*login_done.is_restoring.lock().unwrap()
+}
+
+// Registers the guest once it has a win to submit, before the entry shows its id to everyone.
+pub fn register_guest(
+    mut guest: ResMut<GuestResource>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done, network): (Res<BackendResource>, Res<LoginDone>, Res<NetworkResource>),
+    outbox: Res<OutboxResource>,
+    time: Res<Time>,
+) {
+    if guest.guest.is_registered || guest.is_busy || time.elapsed_seconds_f64() < guest.retry_at
+        || outbox.entries.is_empty() || !is_guest_active(&backend, &login_done) {
+        return;
+    }
+
+    guest.is_busy = true;
+    let auth = backend.auth.clone();
+    let (id, token) = (guest.guest.id.clone(), guest.guest.token.clone());
+    let reporter = network.reporter.clone();
+    let registered = guest.registered.clone();
+    runtime.spawn_background_task(move |_ctx| async move {
+        let result = auth.register_guest(id, token).await;
+        reporter.report(BackendTask::Submit, &result);
+        *registered.lock().unwrap() = Some(result);
+    });
+}
+
+// Moves the entries of the guest to whoever logged in, then starts a fresh guest for the next time
+// nobody is. A win still on its way as the guest is waited for, or it would be left behind.
+pub fn merge_guest(
+    mut guest: ResMut<GuestResource>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done, outbox): (Res<BackendResource>, Res<LoginDone>, Res<OutboxResource>),
+    time: Res<Time>,
+) {
+    if !guest.guest.is_registered || guest.is_busy || time.elapsed_seconds_f64() < guest.retry_at
+        || outbox.is_sending() || !*login_done.done.lock().unwrap() {
+        return;
+    }
+    let Some(user_id) = login_done.id.lock().unwrap().clone() else {
+        return;
+    };
+
+    guest.is_busy = true;
+    let auth = backend.auth.clone();
+    let (id, token) = (guest.guest.id.clone(), guest.guest.token.clone());
+    let name = login_done.name.lock().unwrap().clone();
+    let merged = guest.merged.clone();
+    runtime.spawn_background_task(move |_ctx| async move {
+        let result = auth.merge_guest(id, token, user_id, name).await;
+        *merged.lock().unwrap() = Some(result);
+    });
+}
+
+pub fn poll_guest(
+    mut guest: ResMut<GuestResource>,
+    mut ranking_data: ResMut<RankingDataResource>,
+    time: Res<Time>,
+) {
+    let registered = guest.registered.lock().unwrap().take();
+    match registered {
+        Some(Ok(())) => {
+            guest.is_busy = false;
+            guest.guest.is_registered = true;
+            save(&guest.guest);
+        },
+        Some(Err(e)) => {
+            warn!("Unable to register the guest: {}", e);
+            guest.is_busy = false;
+            guest.retry_at = time.elapsed_seconds_f64() + RETRY_SEC;
+        },
+        None => {},
+    }
+
+    let merged = guest.merged.lock().unwrap().take();
+    match merged {
+        Some(Ok(count)) => {
+            info!("merged {} guest results", count);
+            guest.is_busy = false;
+            guest.guest = Guest::new();
+            save(&guest.guest);
+            ranking_data.expire(None);
+        },
+        // Someone else registered the id first, so its entries cannot be told apart from ours.
+        Some(Err(BackendError::Conflict(_))) => {
+            warn!("The guest belongs to someone else, starting a new one");
+            guest.is_busy = false;
+            guest.guest = Guest::new();
+            save(&guest.guest);
+        },
+        // The guest is kept, so the merge is tried again, on the next login at the latest.
+        Some(Err(e)) => {
+            warn!("Unable to merge the guest results: {}", e);
+            guest.is_busy = false;
+            guest.retry_at = time.elapsed_seconds_f64() + RETRY_SEC;
+        },
+        None => {},
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Serialize, Deserialize};
+
+const GOOGLE_AUTH_ENDPOINT: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const GOOGLE_TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+const GITHUB_AUTH_ENDPOINT: &str = "https://github.com/login/oauth/authorize";
+const GITHUB_TOKEN_ENDPOINT: &str = "https://github.com/login/oauth/access_token";
+const OIDC_SCOPE: &str = "openid email profile";
+// GitHub has no OpenID Connect; the login function reads the user from the API instead.
+const GITHUB_SCOPE: &str = "read:user";
+
+/// Where a player logs in, as listed under `providers` in `config.yaml`. The login functions have
+/// the matching entry, which tells them how to turn an access token into the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdentityProvider {
+    Google {
+        client_id: String,
+    },
+    #[serde(rename = "github")]
+    GitHub {
+        client_id: String,
+    },
+    /// Any OpenID Connect provider, e.g. the single sign-on of a company.
+    Oidc {
+        id: String,
+        name: String,
+        client_id: String,
+        auth_endpoint: String,
+        token_endpoint: String,
+        #[serde(default = "get_oidc_scope")]
+        scope: String,
+    },
+}
+
+fn get_oidc_scope() -> String {
+    OIDC_SCOPE.to_string()
+}
+
+// The local backend logs in without a provider, so any will do.
+impl Default for IdentityProvider {
+    fn default() -> Self {
+        IdentityProvider::Google {
+            client_id: String::new(),
+        }
+    }
+}
+
+impl IdentityProvider {
+    /// Names the provider to the login functions, and starts the state of its logins.
+    pub fn get_id(&self) -> &str {
+        match self {
+            IdentityProvider::Google { .. } => "google",
+            IdentityProvider::GitHub { .. } => "github",
+            IdentityProvider::Oidc { id, .. } => id,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            IdentityProvider::Google { .. } => "Google",
+            IdentityProvider::GitHub { .. } => "GitHub",
+            IdentityProvider::Oidc { name, .. } => name,
+        }
+    }
+
+    pub fn get_client_id(&self) -> &str {
+        match self {
+            IdentityProvider::Google { client_id }
+            | IdentityProvider::GitHub { client_id }
+            | IdentityProvider::Oidc { client_id, .. } => client_id,
+        }
+    }
+
+    pub fn get_auth_endpoint(&self) -> &str {
+        match self {
+            IdentityProvider::Google { .. } => GOOGLE_AUTH_ENDPOINT,
+            IdentityProvider::GitHub { .. } => GITHUB_AUTH_ENDPOINT,
+            IdentityProvider::Oidc { auth_endpoint, .. } => auth_endpoint,
+        }
+    }
+
+    pub fn get_token_endpoint(&self) -> &str {
+        match self {
+            IdentityProvider::Google { .. } => GOOGLE_TOKEN_ENDPOINT,
+            IdentityProvider::GitHub { .. } => GITHUB_TOKEN_ENDPOINT,
+            IdentityProvider::Oidc { token_endpoint, .. } => token_endpoint,
+        }
+    }
+
+    /// Google and GitHub want the secret of the client at the token endpoint even with PKCE, so
+    /// native builds have the session function, which holds it, exchange their code.
+    pub fn is_secret_required(&self) -> bool {
+        !matches!(self, IdentityProvider::Oidc { .. })
+    }
+
+    pub fn get_scope(&self) -> &str {
+        match self {
+            IdentityProvider::Google { .. } => OIDC_SCOPE,
+            IdentityProvider::GitHub { .. } => GITHUB_SCOPE,
+            IdentityProvider::Oidc { scope, .. } => scope,
+        }
+    }
+}
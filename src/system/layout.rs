@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+
+use crate::component::grid::Grid;
+use crate::core::layout::Layout;
+use crate::system::difficulty::Difficulty;
+use crate::system::race::RaceResource;
+use crate::system::settings::Settings;
+use crate::system::state::GameState;
+use crate::core;
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LayoutResource>()
+            .add_systems(Update, drop_layout.run_if(resource_changed::<Difficulty>()))
+            .add_systems(Update, prepare_board
+                .before(core::update::cells::first_click)
+                .run_if(in_state(GameState::Ready)));
+    }
+}
+
+/// A board loaded from a file, played instead of the board of the seed until another difficulty
+/// is chosen.
+#[derive(Resource, Default)]
+pub struct LayoutResource {
+    pub layout: Option<Layout>,
+}
+
+impl LayoutResource {
+    pub fn new(layout: Layout) -> Self {
+        Self {
+            layout: Some(layout),
+        }
+    }
+
+    pub fn get_difficulty(&self) -> Option<Difficulty> {
+        let (width, height, mines) = self.layout.as_ref()?.get_size();
+        Some(Difficulty::Custom { width, height, mines })
+    }
+}
+
+pub fn drop_layout(
+    mut layout: ResMut<LayoutResource>,
+    difficulty: Res<Difficulty>,
+) {
+    if layout.layout.is_some() && layout.get_difficulty() != Some(*difficulty) {
+        layout.layout = None;
+    }
+}
+
+/// Tells the grid how to plant its mines on the first click.
+pub fn prepare_board(
+    mut grid: ResMut<Grid>,
+    layout: Res<LayoutResource>,
+    settings: Res<Settings>,
+    race: Res<RaceResource>,
+) {
+    // Everyone in a race plays the board of the seed.
+    let in_race = race.is_in_race();
+    let next_layout = layout.layout.as_ref()
+        .filter(|_| !in_race)
+        .map(|layout| layout.get_mine_positions());
+    let wants_safe_start = settings.is_safe_start && !in_race;
+    if grid.next_layout != next_layout || grid.wants_safe_start != wants_safe_start {
+        grid.next_layout = next_layout;
+        grid.wants_safe_start = wants_safe_start;
+    }
+}
+
+// Games with a safe start or a loaded layout are played on another board than their seed gives,
+// so the verifier would reject them and their ghost would not match.
+pub fn run_if_seed_board(
+    grid: Res<Grid>,
+) -> bool {
+    !grid.is_safe_start && !grid.is_layout
+}
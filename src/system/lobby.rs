@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+
+use crate::net::protocol::{self, ClientMessage, ServerMessage, PlayerInfo, DEFAULT_ADDRESS};
+use crate::net::socket::platform::Connection;
+
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LobbyResource>()
+            .add_event::<ServerEvent>()
+            .add_systems(Update, poll_lobby);
+    }
+}
+
+// Every message from the relay is forwarded so the race and co-op plugins can share one connection.
+#[derive(Event, Debug, Clone)]
+pub struct ServerEvent(pub ServerMessage);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LobbyStatus {
+    Disconnected,
+    Connecting,
+    Connected,
+}
+
+#[derive(Resource)]
+pub struct LobbyResource {
+    pub address: String,
+    pub name: String,
+    pub connection: Option<Connection>,
+    pub status: LobbyStatus,
+    pub id: Option<u32>,
+    pub players: Vec<PlayerInfo>,
+    pub error: Option<String>,
+}
+
+impl Default for LobbyResource {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_ADDRESS.to_string(),
+            name: String::new(),
+            connection: None,
+            status: LobbyStatus::Disconnected,
+            id: None,
+            players: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+impl LobbyResource {
+    pub fn connect(&mut self) {
+        self.connection = Some(Connection::connect(&self.address));
+        self.status = LobbyStatus::Connecting;
+        self.id = None;
+        self.players.clear();
+        self.error = None;
+    }
+
+    pub fn disconnect(&mut self) {
+        self.connection = None;
+        self.status = LobbyStatus::Disconnected;
+        self.id = None;
+        self.players.clear();
+    }
+
+    pub fn send(&self, message: &ClientMessage) {
+        if let Some(connection) = self.connection.as_ref() {
+            connection.send(protocol::to_text(message));
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.status == LobbyStatus::Connected
+    }
+
+    pub fn get_player_name(&self, id: u32) -> Option<&str> {
+        self.players.iter()
+            .find(|player| player.id == id)
+            .map(|player| player.name.as_str())
+    }
+}
+
+const PLAYER_COLORS: [(u8, u8, u8); 6] = [
+    (230, 80, 80),
+    (80, 140, 230),
+    (80, 190, 90),
+    (220, 170, 40),
+    (170, 90, 220),
+    (40, 190, 190),
+];
+
+pub fn get_player_color(id: u32) -> (u8, u8, u8) {
+    PLAYER_COLORS[id as usize % PLAYER_COLORS.len()]
+}
+
+pub fn poll_lobby(
+    mut lobby: ResMut<LobbyResource>,
+    mut server_events: EventWriter<ServerEvent>,
+) {
+    let Some(connection) = lobby.connection.as_ref() else {
+        return;
+    };
+
+    let is_open = connection.state.is_open();
+    let is_closed = connection.state.is_closed();
+    let messages = connection.state.poll();
+    // Written before the socket is marked closed.
+    let reason = if is_closed { connection.state.take_error() } else { None };
+
+    if lobby.status == LobbyStatus::Connecting && is_open {
+        lobby.status = LobbyStatus::Connected;
+        let name = if lobby.name.is_empty() { "player".to_string() } else { lobby.name.clone() };
+        lobby.send(&ClientMessage::Join { name });
+    }
+
+    for message in messages {
+        let Some(message) = protocol::from_text::<ServerMessage>(&message) else {
+            warn!("invalid lobby message: {}", message);
+            continue;
+        };
+        match &message {
+            ServerMessage::Welcome { id } => {
+                lobby.id = Some(*id);
+            },
+            ServerMessage::Players { players } => {
+                lobby.players = players.clone();
+            },
+            ServerMessage::Error { message } => {
+                lobby.error = Some(message.clone());
+            },
+            _ => {},
+        }
+        server_events.send(ServerEvent(message));
+    }
+
+    if is_closed {
+        if let Some(reason) = reason.as_ref() {
+            warn!("lobby connection to {}: {}", lobby.address, reason);
+        }
+        let reason = reason.map(|reason| format!(": {}", reason)).unwrap_or_default();
+        if lobby.status == LobbyStatus::Connecting {
+            lobby.error = Some(format!("Unable to connect to {}{}", lobby.address, reason));
+        } else if !reason.is_empty() {
+            lobby.error = Some(format!("Connection lost{}", reason));
+        }
+        lobby.disconnect();
+    }
+}
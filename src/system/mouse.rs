@@ -1,8 +1,30 @@
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
-use crate::component::cell::Cell;
+use crate::component::cell::{Cell, CellState};
 use crate::component::smile::SmileComponent;
+use crate::core::rules::Action;
 use crate::system::state::GameState;
+use crate::system::replay::MoveEvent;
+use crate::system::settings::Settings;
+
+/// Where clicks on the board go: straight into the local cells, only out as `MoveEvent`s
+/// for a server that owns the board, or nowhere while watching someone else play.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputMode {
+    #[default]
+    Local,
+    Remote,
+    Disabled,
+}
+
+#[derive(Event)]
+pub struct RestartEvent;
+
+pub fn run_if_local_input(
+    input_mode: Res<InputMode>,
+) -> bool {
+    *input_mode == InputMode::Local
+}
 
 #[derive(Component)]
 pub struct Clickable(pub Vec3, pub f32, pub f32);
@@ -18,13 +40,65 @@ impl Clickable {
     }
 }
 
+/// The move a left click on the cell makes, as recorded in the move log.
+pub fn get_release_action(cell: &Cell) -> Option<Action> {
+    match cell.state {
+        CellState::Pressed | CellState::Questioned => Some(Action::Reveal),
+        CellState::Revealed if !cell.is_mine => Some(Action::Chord),
+        _ => None,
+    }
+}
+
+/// The move a right click on the cell makes. A question mark is hidden for the rules, so only
+/// the flag it replaces is a move.
+pub fn get_flag_action(cell: &Cell) -> Option<Action> {
+    matches!(cell.state, CellState::Hidden | CellState::Pressed | CellState::Flagged).then_some(Action::Flag)
+}
+
+// The mouse and the bot both go through these, so every move takes the same path into the
+// cells, the move log and, in co-op, the server.
+pub fn release_cell(
+    cell: &mut Cell,
+    input_mode: &InputMode,
+    move_events: &mut EventWriter<MoveEvent>,
+) {
+    let action = get_release_action(cell);
+    match input_mode {
+        InputMode::Local => cell.left_released(),
+        InputMode::Remote | InputMode::Disabled => cell.left_cancel(),
+    }
+    if let Some(action) = action {
+        move_events.send(MoveEvent { x: cell.x, y: cell.y, action });
+    }
+}
+
+pub fn flag_cell(
+    cell: &mut Cell,
+    input_mode: &InputMode,
+    is_question_marks: bool,
+    move_events: &mut EventWriter<MoveEvent>,
+) {
+    if let Some(action) = get_flag_action(cell) {
+        move_events.send(MoveEvent { x: cell.x, y: cell.y, action });
+    }
+    if *input_mode == InputMode::Local {
+        cell.right_just_pressed(is_question_marks);
+    }
+}
+
 pub fn mouse_events_system(
     buttons: Res<Input<MouseButton>>,
     q_windows: Query<&Window, With<PrimaryWindow>>,
     mut q_cells: Query<(&mut Cell, &Clickable)>,
     mut q_smiles: Query<(&mut SmileComponent, &Clickable)>,
     mut next_state: ResMut<NextState<GameState>>,
+    (mut move_events, mut restart_events): (EventWriter<MoveEvent>, EventWriter<RestartEvent>),
+    (input_mode, settings): (Res<InputMode>, Res<Settings>),
 ) {
+    if *input_mode == InputMode::Disabled {
+        return;
+    }
+
     if buttons.pressed(MouseButton::Left) {
         if let Some(position) = q_windows.single().cursor_position() {
             for (mut cell, clickable) in q_cells.iter_mut() {
@@ -52,16 +126,20 @@ pub fn mouse_events_system(
     if buttons.just_released(MouseButton::Left) {
         if let Some(position) = q_windows.single().cursor_position() {
             for (mut cell, clickable) in q_cells.iter_mut() {
-                if clickable.is_inside(position) {
-                    if cell.is_left_pressed {
-                        cell.left_released();
-                    }
+                if clickable.is_inside(position) && cell.is_left_pressed {
+                    release_cell(&mut cell, &input_mode, &mut move_events);
                 }
             }
 
             for (mut smile, _) in q_smiles.iter_mut() {
                 if smile.is_pressed {
-                    smile.released(&mut next_state);
+                    match *input_mode {
+                        InputMode::Local => smile.released(&mut next_state),
+                        InputMode::Remote | InputMode::Disabled => {
+                            smile.cancel();
+                            restart_events.send(RestartEvent);
+                        },
+                    }
                 }
             }
         }
@@ -71,7 +149,7 @@ pub fn mouse_events_system(
         if let Some(position) = q_windows.single().cursor_position() {
             for (mut cell, clickable) in q_cells.iter_mut() {
                 if clickable.is_inside(position) {
-                    cell.right_just_pressed();
+                    flag_cell(&mut cell, &input_mode, settings.is_question_marks, &mut move_events);
                 }
             }
         }
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+
+use crate::system::backend::{BackendError, BackendResource, BackendResult};
+use crate::system::firestore::{platform, LoginDone};
+use crate::system::outbox::OutboxResource;
+use crate::system::state::LoginState;
+
+pub const NAME_MIN_LEN: usize = 3;
+pub const NAME_MAX_LEN: usize = 16;
+
+pub struct NamePlugin;
+
+impl Plugin for NamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<NameResource>()
+            .add_event::<ClaimNameEvent>()
+            .add_systems(Update, (claim_name, poll_name))
+            .add_systems(OnEnter(LoginState::Done), open_name_popup);
+    }
+}
+
+/// Asks the backend for a display name for the logged in user.
+#[derive(Event, Debug, Clone)]
+pub struct ClaimNameEvent(pub String);
+
+/// The display name popup, shown after the first login until a name is claimed.
+#[derive(Resource, Default)]
+pub struct NameResource {
+    pub is_open: bool,
+    pub input: String,
+    pub error: Option<String>,
+    is_claiming: bool,
+    result: Arc<Mutex<Option<BackendResult<String>>>>,
+}
+
+impl NameResource {
+    pub fn is_claiming(&self) -> bool {
+        self.is_claiming
+    }
+}
+
+/// Letters, digits, `_` and `-`, so a name cannot pass for an email address or another player.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let len = name.chars().count();
+    if !(NAME_MIN_LEN..=NAME_MAX_LEN).contains(&len) {
+        return Err(format!("Use {} to {} characters", NAME_MIN_LEN, NAME_MAX_LEN));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err("Use only letters, digits, _ and -".to_string());
+    }
+    Ok(())
+}
+
+/// Shown for entries without a display name, including rows still keyed by an email address,
+/// which must not be shown as is.
+pub fn get_pseudonym(user_id: &str) -> String {
+    // FNV-1a, stable across builds and platforms unlike the std hasher.
+    let hash = user_id.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("Player-{:06x}", hash & 0xffffff)
+}
+
+pub fn open_name_popup(
+    login_done: Res<LoginDone>,
+    mut name: ResMut<NameResource>,
+) {
+    if login_done.name.lock().unwrap().is_none() {
+        name.is_open = true;
+    }
+}
+
+pub fn claim_name(
+    mut claim_events: EventReader<ClaimNameEvent>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done): (Res<BackendResource>, Res<LoginDone>),
+    mut name: ResMut<NameResource>,
+) {
+    for ClaimNameEvent(new_name) in claim_events.iter() {
+        if name.is_claiming {
+            continue;
+        }
+        let new_name = new_name.trim().to_string();
+        if let Err(e) = validate_name(&new_name) {
+            name.error = Some(e);
+            continue;
+        }
+        if login_done.id.lock().unwrap().is_none() {
+            continue;
+        }
+        // The backend names whoever the session belongs to. The local user has none, and needs none.
+        let session = login_done.session.lock().unwrap().clone().unwrap_or_default();
+
+        name.is_claiming = true;
+        name.error = None;
+        let auth = backend.auth.clone();
+        let result = name.result.clone();
+        runtime.spawn_background_task(move |_ctx| async move {
+            let claimed = auth.claim_name(session, new_name.clone()).await.map(|_| new_name);
+            *result.lock().unwrap() = Some(claimed);
+        });
+    }
+}
+
+pub fn poll_name(
+    mut name: ResMut<NameResource>,
+    login_done: Res<LoginDone>,
+    mut outbox: ResMut<OutboxResource>,
+) {
+    let Some(result) = name.result.lock().unwrap().take() else {
+        return;
+    };
+    name.is_claiming = false;
+    match result {
+        Ok(new_name) => {
+            info!("display name: {}", new_name);
+            *login_done.name.lock().unwrap() = Some(new_name);
+            name.is_open = false;
+            outbox.retry_now();
+        },
+        Err(BackendError::Conflict(_)) => name.error = Some("That name is taken".to_string()),
+        Err(e) => name.error = Some(e.to_string()),
+    }
+}
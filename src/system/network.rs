@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+
+use crate::system::backend::{BackendError, BackendResult};
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<NetworkResource>()
+            .add_event::<BackendErrorEvent>()
+            .add_event::<RetryEvent>()
+            .add_systems(Update, poll_network);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendTask {
+    Connect,
+    Login,
+    Submit,
+    Fetch,
+}
+
+impl BackendTask {
+    pub fn get_name(&self) -> &str {
+        match self {
+            BackendTask::Connect => "Connect",
+            BackendTask::Login => "Login",
+            BackendTask::Submit => "Ranking submit",
+            BackendTask::Fetch => "Ranking fetch",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionStatus {
+    #[default]
+    Connecting,
+    Online,
+    Offline,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct BackendErrorEvent {
+    pub task: BackendTask,
+    pub error: BackendError,
+}
+
+/// Asks for a failed task to run again, from a toast or the connection indicator.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RetryEvent(pub BackendTask);
+
+type Report = (BackendTask, BackendResult<()>);
+
+/// Handed to background tasks, which cannot send Bevy events, to pass their results back.
+#[derive(Clone, Default)]
+pub struct NetworkReporter {
+    reports: Arc<Mutex<Vec<Report>>>,
+}
+
+impl NetworkReporter {
+    pub fn report<T>(&self, task: BackendTask, result: &BackendResult<T>) {
+        let result = result.as_ref().map(|_| ()).map_err(|e| e.clone());
+        self.reports.lock().unwrap().push((task, result));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct NetworkResource {
+    pub status: ConnectionStatus,
+    pub last_error: Option<(BackendTask, BackendError)>,
+    pub reporter: NetworkReporter,
+}
+
+pub fn poll_network(
+    mut network: ResMut<NetworkResource>,
+    mut error_events: EventWriter<BackendErrorEvent>,
+) {
+    let reports = std::mem::take(&mut *network.reporter.reports.lock().unwrap());
+    for (task, result) in reports {
+        match result {
+            Ok(()) => {
+                network.status = ConnectionStatus::Online;
+                if network.last_error.as_ref().is_some_and(|(last_task, _)| *last_task == task) {
+                    network.last_error = None;
+                }
+            },
+            Err(error) => {
+                warn!("{} failed: {}", task.get_name(), error);
+                if matches!(error, BackendError::Connection(_)) {
+                    network.status = ConnectionStatus::Offline;
+                }
+                network.last_error = Some((task, error.clone()));
+                error_events.send(BackendErrorEvent { task, error });
+            },
+        }
+    }
+}
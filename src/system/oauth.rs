@@ -0,0 +1,664 @@
+// The installed-app login of native builds: the authorization code flow with PKCE, redirecting
+// to a listener on `127.0.0.1`. The client holds no secret; the session endpoint turns the
+// access token into the user and a session, so email addresses never reach the client. Providers
+// that want a secret anyway get the code and the verifier at the session endpoint instead.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::system::auth;
+use crate::system::config::Config;
+use crate::system::backend::{AuthBackend, BackendError, BackendFuture, BackendResult, LocalBackend, LoginRequest, LoginUser};
+use crate::system::identity::IdentityProvider;
+
+const LOOPBACK_POLL_MS: u64 = 50;
+// A browser that connected has sent its request well within this.
+const REQUEST_READ_TIMEOUT_SEC: u64 = 5;
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+/// The code verifier kept by the client and the challenge sent with the authorization request.
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn new() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let verifier = URL_SAFE_NO_PAD.encode(bytes);
+        let challenge = get_challenge(&verifier);
+        Self { verifier, challenge }
+    }
+}
+
+impl Default for Pkce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// S256: the unpadded base64url of the SHA-256 of the verifier.
+fn get_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+/// Opens the login page and resolves to the user once the browser comes back with a code, or
+/// fails once `state` is put in `cancelled`.
+pub async fn login(request: LoginRequest, cancelled: Arc<Mutex<HashSet<String>>>) -> BackendResult<LoginUser> {
+    login_with(request, cancelled, auth::open_url).await
+}
+
+/// Like `login`, with `open` in place of the browser, e.g. `MockProvider::visit` in tests.
+pub async fn login_with(request: LoginRequest, cancelled: Arc<Mutex<HashSet<String>>>, open: impl FnOnce(&str)) -> BackendResult<LoginUser> {
+    let provider = request.provider;
+    let pkce = Pkce::new();
+    let loopback = Loopback::bind()?;
+    let redirect_uri = loopback.get_redirect_uri();
+
+    let url = auth::get_login_url(&provider, &request.state, &redirect_uri, &[
+        ("code_challenge", pkce.challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ]);
+    *request.url.lock().unwrap() = Some(url.clone());
+    open(&url);
+
+    let state = request.state;
+    let code = loopback.wait_for_code(&state, || cancelled.lock().unwrap().remove(&state)).await?;
+    info!("login code received");
+
+    let session_endpoint = request.config.session_endpoint;
+    // ureq blocks, so the requests leave the async workers free.
+    tokio::task::spawn_blocking(move || {
+        if provider.is_secret_required() {
+            return sign_in_with_code(&session_endpoint, &provider, &code, &pkce.verifier, &redirect_uri);
+        }
+        let token = exchange_code(&provider, &code, &pkce.verifier, &redirect_uri)?;
+        sign_in(&session_endpoint, &provider, &token.access_token)
+    })
+    .await
+    .map_err(|e| BackendError::Connection(e.to_string()))?
+}
+
+/// Makes `name` the display name of the user `session` belongs to, failing with `Conflict` when
+/// another user has it.
+pub async fn claim_name(session_endpoint: String, session: String, name: String) -> BackendResult<()> {
+    let body = serde_json::json!({ "session": session, "action": "name", "name": name });
+    call_session(session_endpoint, body, move |response| match response {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(409, _)) => Err(BackendError::Conflict(name)),
+        Err(e) => Err(get_error(e)),
+    }).await
+}
+
+// ureq blocks, so the request leaves the async workers free.
+async fn call_session<T: Send + 'static>(
+    session_endpoint: String,
+    body: serde_json::Value,
+    read: impl FnOnce(Result<ureq::Response, ureq::Error>) -> BackendResult<T> + Send + 'static,
+) -> BackendResult<T> {
+    tokio::task::spawn_blocking(move || read(ureq::post(&session_endpoint).send_json(body)))
+        .await
+        .map_err(|e| BackendError::Connection(e.to_string()))?
+}
+
+/// Logs in through the session endpoint alone, for native builds without a service account key,
+/// which cannot reach Firestore. Rankings stay on this machine, and with them the entries of the
+/// guest, which a login merges locally.
+pub struct SessionBackend {
+    session_endpoint: String,
+    cancelled: Arc<Mutex<HashSet<String>>>,
+    local: Arc<LocalBackend>,
+}
+
+impl SessionBackend {
+    pub fn new(config: &Config, local: Arc<LocalBackend>) -> Self {
+        Self {
+            session_endpoint: config.session_endpoint.clone(),
+            cancelled: Arc::new(Mutex::new(HashSet::new())),
+            local,
+        }
+    }
+}
+
+impl AuthBackend for SessionBackend {
+    // Each request reaches the endpoint on its own.
+    fn connect(&self) -> BackendFuture<()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn listen_login(&self, request: LoginRequest) -> BackendFuture<LoginUser> {
+        Box::pin(login(request, self.cancelled.clone()))
+    }
+
+    fn cancel_login(&self, state: String) {
+        self.cancelled.lock().unwrap().insert(state);
+    }
+
+    // An expired or ended session is refused with 401.
+    fn restore_session(&self, session: String) -> BackendFuture<Option<LoginUser>> {
+        let body = serde_json::json!({ "session": session, "action": "restore" });
+        Box::pin(call_session(self.session_endpoint.clone(), body, |response| match response {
+            Ok(response) => response.into_json::<LoginUser>().map(Some)
+                .map_err(|e| BackendError::InvalidData(format!("Unable to parse the session response: {}", e))),
+            Err(ureq::Error::Status(401, _)) => Ok(None),
+            Err(e) => Err(get_error(e)),
+        }))
+    }
+
+    fn end_session(&self, session: String) -> BackendFuture<()> {
+        let body = serde_json::json!({ "session": session, "action": "end" });
+        Box::pin(call_session(self.session_endpoint.clone(), body, |response| match response {
+            Ok(_) | Err(ureq::Error::Status(401, _)) => Ok(()),
+            Err(e) => Err(get_error(e)),
+        }))
+    }
+
+    fn claim_name(&self, session: String, name: String) -> BackendFuture<()> {
+        Box::pin(claim_name(self.session_endpoint.clone(), session, name))
+    }
+
+    fn register_guest(&self, guest_id: String, token: String) -> BackendFuture<()> {
+        self.local.register_guest(guest_id, token)
+    }
+
+    fn merge_guest(&self, guest_id: String, token: String, user_id: String, name: Option<String>) -> BackendFuture<u32> {
+        self.local.merge_guest(guest_id, token, user_id, name)
+    }
+}
+
+fn exchange_code(provider: &IdentityProvider, code: &str, verifier: &str, redirect_uri: &str) -> BackendResult<TokenResponse> {
+    // GitHub answers with a form unless asked for JSON.
+    let response = ureq::post(provider.get_token_endpoint())
+        .set("Accept", "application/json")
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", provider.get_client_id()),
+            ("code_verifier", verifier),
+        ])
+        .map_err(get_error)?;
+    response.into_json::<TokenResponse>()
+        .map_err(|e| BackendError::InvalidData(format!("Unable to parse the token response: {}", e)))
+}
+
+fn sign_in(session_endpoint: &str, provider: &IdentityProvider, access_token: &str) -> BackendResult<LoginUser> {
+    let response = ureq::post(session_endpoint)
+        .send_json(serde_json::json!({ "provider": provider.get_id(), "access_token": access_token }))
+        .map_err(get_error)?;
+    response.into_json::<LoginUser>()
+        .map_err(|e| BackendError::InvalidData(format!("Unable to parse the session response: {}", e)))
+}
+
+fn sign_in_with_code(session_endpoint: &str, provider: &IdentityProvider, code: &str, verifier: &str, redirect_uri: &str) -> BackendResult<LoginUser> {
+    let response = ureq::post(session_endpoint)
+        .send_json(serde_json::json!({
+            "provider": provider.get_id(),
+            "code": code,
+            "code_verifier": verifier,
+            "redirect_uri": redirect_uri,
+        }))
+        .map_err(get_error)?;
+    response.into_json::<LoginUser>()
+        .map_err(|e| BackendError::InvalidData(format!("Unable to parse the session response: {}", e)))
+}
+
+// A refusal carries the reason in its body, e.g. `invalid_grant`.
+fn get_error(e: ureq::Error) -> BackendError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            BackendError::InvalidData(format!("{} {}", status, body))
+        },
+        ureq::Error::Transport(transport) => BackendError::Connection(transport.to_string()),
+    }
+}
+
+/// The redirect target: a listener on a free port of `127.0.0.1`, which takes the one request
+/// carrying the authorization code and is closed when dropped.
+pub struct Loopback {
+    listener: TcpListener,
+    addr: SocketAddr,
+}
+
+impl Loopback {
+    pub fn bind() -> BackendResult<Self> {
+        let bind = || {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            listener.set_nonblocking(true)?;
+            let addr = listener.local_addr()?;
+            Ok::<_, std::io::Error>(Self { listener, addr })
+        };
+        bind().map_err(|e| BackendError::Connection(format!("Unable to listen for the login: {}", e)))
+    }
+
+    pub fn get_redirect_uri(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    // Requests without our state, like a favicon or a stale tab, are answered and ignored.
+    async fn wait_for_code(&self, state: &str, mut is_cancelled: impl FnMut() -> bool) -> BackendResult<String> {
+        loop {
+            match self.listener.accept() {
+                Ok((mut stream, _)) => {
+                    let Some(target) = read_request(&mut stream).map(|(target, _)| target) else {
+                        continue;
+                    };
+                    let Some(params) = get_query(&target) else {
+                        respond(&mut stream, "404 Not Found", "text/plain", "Not found");
+                        continue;
+                    };
+                    if params.get("state").map(String::as_str) != Some(state) {
+                        respond(&mut stream, "400 Bad Request", "text/plain", "Unknown login");
+                        continue;
+                    }
+                    if let Some(error) = params.get("error") {
+                        respond(&mut stream, "200 OK", "text/html", &get_page("Login failed. Please return to the game."));
+                        return Err(BackendError::Connection(format!("Login refused: {}", error)));
+                    }
+                    if let Some(code) = params.get("code") {
+                        respond(&mut stream, "200 OK", "text/html", &get_page("Successfully login. Please close this window."));
+                        return Ok(code.clone());
+                    }
+                    respond(&mut stream, "400 Bad Request", "text/plain", "Missing auth code");
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    if is_cancelled() {
+                        return Err(BackendError::Connection("Login cancelled".to_string()));
+                    }
+                    tokio::time::sleep(Duration::from_millis(LOOPBACK_POLL_MS)).await;
+                },
+                Err(e) => return Err(BackendError::Connection(format!("Unable to receive the login: {}", e))),
+            }
+        }
+    }
+}
+
+fn get_page(message: &str) -> String {
+    format!("<!DOCTYPE html><html><body><p>{}</p></body></html>", message)
+}
+
+// The request target and body of one HTTP/1.1 request, or `None` if it is cut short.
+fn read_request(stream: &mut TcpStream) -> Option<(String, String)> {
+    stream.set_nonblocking(false).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(REQUEST_READ_TIMEOUT_SEC))).ok()?;
+
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    let header_end = loop {
+        if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break index + 4;
+        }
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return None;
+        }
+        let read = stream.read(&mut chunk).ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let target = head.split_whitespace().nth(1)?.to_string();
+    let content_length = head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(MAX_REQUEST_BYTES);
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body = String::from_utf8_lossy(&buffer[header_end..header_end + content_length]).to_string();
+    Some((target, body))
+}
+
+fn get_query(target: &str) -> Option<HashMap<String, String>> {
+    let url = Url::parse(&format!("http://127.0.0.1{}", target)).ok()?;
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    if params.is_empty() {
+        None
+    } else {
+        Some(params)
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    respond_with(stream, status, &[("Content-Type", content_type)], body);
+}
+
+fn respond_with(stream: &mut TcpStream, status: &str, headers: &[(&str, &str)], body: &str) {
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    if let Err(e) = stream.write_all(response.as_bytes()) {
+        warn!("Unable to answer the browser: {}", e);
+    }
+}
+
+/// An identity provider on a free port of `127.0.0.1` that signs everyone in as `user_id`, for
+/// tests and offline runs. It serves `/authorize`, which redirects straight back with a code,
+/// `/token`, which checks the code verifier against the challenge like a real provider, and
+/// `/session`, which opens sessions and acts on them like the session function. It stops when
+/// dropped.
+///
+/// ```ignore
+/// let mock = MockProvider::start("mock-user")?;
+/// let request = LoginRequest::new(state, mock.get_provider(), mock.get_config());
+/// let user = oauth::login_with(request, cancelled, MockProvider::visit).await?;
+/// ```
+pub struct MockProvider {
+    addr: SocketAddr,
+    is_stopped: Arc<Mutex<bool>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    // Issued codes, with the challenge and redirect they were issued for.
+    codes: HashMap<String, (String, String)>,
+    access_tokens: HashSet<String>,
+    // Open sessions, with the name chosen for the user.
+    sessions: HashMap<String, Option<String>>,
+}
+
+impl MockState {
+    // A code is good for one exchange, like with a real provider.
+    fn take_code(&mut self, code: Option<&String>, verifier: Option<&String>, redirect_uri: Option<&String>) -> bool {
+        let issued = code.and_then(|code| self.codes.remove(code));
+        issued.is_some_and(|(challenge, issued_redirect_uri)| {
+            redirect_uri == Some(&issued_redirect_uri)
+                && verifier.is_some_and(|verifier| get_challenge(verifier) == challenge)
+        })
+    }
+}
+
+impl MockProvider {
+    pub fn start(user_id: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let is_stopped = Arc::new(Mutex::new(false));
+
+        let user_id = user_id.to_string();
+        let stopped = is_stopped.clone();
+        std::thread::spawn(move || {
+            let mut state = MockState::default();
+            while !*stopped.lock().unwrap() {
+                match listener.accept() {
+                    Ok((mut stream, _)) => serve_mock(&mut stream, &mut state, &user_id),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        std::thread::sleep(Duration::from_millis(LOOPBACK_POLL_MS));
+                    },
+                    Err(e) => {
+                        warn!("Mock provider stopped: {}", e);
+                        break;
+                    },
+                }
+            }
+        });
+        Ok(Self { addr, is_stopped })
+    }
+
+    pub fn get_provider(&self) -> IdentityProvider {
+        IdentityProvider::Oidc {
+            id: "mock".to_string(),
+            name: "Mock".to_string(),
+            client_id: "mock-client".to_string(),
+            auth_endpoint: format!("http://{}/authorize", self.addr),
+            token_endpoint: format!("http://{}/token", self.addr),
+            scope: "openid".to_string(),
+        }
+    }
+
+    /// A config offering only this provider, with the session endpoint pointing at it too.
+    pub fn get_config(&self) -> Config {
+        Config {
+            session_endpoint: format!("http://{}/session", self.addr),
+            providers: vec![self.get_provider()],
+            ..Default::default()
+        }
+    }
+
+    /// Follows the login page like a browser would, from another thread, since the game is
+    /// waiting for the redirect.
+    pub fn visit(url: &str) {
+        let url = url.to_string();
+        std::thread::spawn(move || {
+            if let Err(e) = ureq::get(&url).call() {
+                warn!("Mock login failed: {}", e);
+            }
+        });
+    }
+}
+
+impl Drop for MockProvider {
+    fn drop(&mut self) {
+        *self.is_stopped.lock().unwrap() = true;
+    }
+}
+
+fn serve_mock(stream: &mut TcpStream, state: &mut MockState, user_id: &str) {
+    let Some((target, body)) = read_request(stream) else {
+        return;
+    };
+    let path = target.split('?').next().unwrap_or_default();
+    let query = get_query(&target).unwrap_or_default();
+    let form: HashMap<String, String> = url::form_urlencoded::parse(body.as_bytes()).into_owned().collect();
+
+    match path {
+        "/authorize" => {
+            let (Some(redirect_uri), Some(challenge)) = (query.get("redirect_uri"), query.get("code_challenge")) else {
+                respond(stream, "400 Bad Request", "text/plain", "Missing redirect_uri or code_challenge");
+                return;
+            };
+            let mut code = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut code);
+            let code = URL_SAFE_NO_PAD.encode(code);
+            state.codes.insert(code.clone(), (challenge.clone(), redirect_uri.clone()));
+
+            let mut location = match Url::parse(redirect_uri) {
+                Ok(location) => location,
+                Err(_) => {
+                    respond(stream, "400 Bad Request", "text/plain", "Invalid redirect_uri");
+                    return;
+                },
+            };
+            location.query_pairs_mut()
+                .append_pair("code", &code)
+                .append_pair("state", query.get("state").map(String::as_str).unwrap_or_default());
+            respond_with(stream, "302 Found", &[("Location", location.as_str())], "");
+        },
+        "/token" => {
+            let is_valid = form.get("grant_type").map(String::as_str) == Some("authorization_code")
+                && state.take_code(form.get("code"), form.get("code_verifier"), form.get("redirect_uri"));
+            if !is_valid {
+                respond(stream, "400 Bad Request", "application/json", r#"{"error":"invalid_grant"}"#);
+                return;
+            }
+            let mut token = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut token);
+            let token = URL_SAFE_NO_PAD.encode(token);
+            state.access_tokens.insert(token.clone());
+            let body = serde_json::json!({ "access_token": token, "token_type": "Bearer", "expires_in": 3600 });
+            respond(stream, "200 OK", "application/json", &body.to_string());
+        },
+        "/session" => {
+            let request = serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default();
+            let get = |key: &str| request.get(key).and_then(|value| value.as_str()).map(str::to_string);
+            if let Some(session) = get("session") {
+                serve_mock_session(stream, state, user_id, session, get("action"), get("name"));
+                return;
+            }
+            // The code of a provider that wants a secret, exchanged here like the session function does.
+            let is_valid = match get("code") {
+                Some(code) => state.take_code(Some(&code), get("code_verifier").as_ref(), get("redirect_uri").as_ref()),
+                None => get("access_token").is_some_and(|token| state.access_tokens.contains(&token)),
+            };
+            if !is_valid {
+                respond(stream, "401 Unauthorized", "application/json", r#"{"error":"invalid_token"}"#);
+                return;
+            }
+            let mut session = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut session);
+            let session = URL_SAFE_NO_PAD.encode(session);
+            state.sessions.insert(session.clone(), None);
+            let user = LoginUser {
+                user_id: user_id.to_string(),
+                name: None,
+                session: Some(session),
+            };
+            let body = serde_json::to_string(&user).unwrap_or_default();
+            respond(stream, "200 OK", "application/json", &body);
+        },
+        _ => respond(stream, "404 Not Found", "text/plain", "Not found"),
+    }
+}
+
+fn serve_mock_session(stream: &mut TcpStream, state: &mut MockState, user_id: &str, session: String, action: Option<String>, name: Option<String>) {
+    let Some(chosen) = state.sessions.get_mut(&session) else {
+        respond(stream, "401 Unauthorized", "application/json", r#"{"error":"invalid_session"}"#);
+        return;
+    };
+    match action.as_deref() {
+        Some("name") => *chosen = name,
+        Some("restore") => {
+            let user = LoginUser {
+                user_id: user_id.to_string(),
+                name: chosen.clone(),
+                session: Some(session),
+            };
+            respond(stream, "200 OK", "application/json", &serde_json::to_string(&user).unwrap_or_default());
+            return;
+        },
+        Some("end") => {
+            state.sessions.remove(&session);
+        },
+        _ => {
+            respond(stream, "400 Bad Request", "application/json", r#"{"error":"unknown_action"}"#);
+            return;
+        },
+    }
+    respond(stream, "200 OK", "application/json", "{}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    fn login_to(mock: &MockProvider, state: &str) -> BackendResult<LoginUser> {
+        let request = LoginRequest::new(state.to_string(), mock.get_provider(), mock.get_config());
+        block_on(login_with(request, Arc::new(Mutex::new(HashSet::new())), MockProvider::visit))
+    }
+
+    #[test]
+    fn challenge_is_the_base64url_sha256_of_the_verifier() {
+        // The example of RFC 7636, appendix B.
+        assert_eq!(get_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+
+    #[test]
+    fn login_with_the_mock_provider_opens_a_session() {
+        let mock = MockProvider::start("mock-user").unwrap();
+        let user = login_to(&mock, "mock.state").unwrap();
+        assert_eq!(user.user_id, "mock-user");
+        assert_eq!(user.name, None);
+        assert!(user.session.is_some());
+    }
+
+    #[test]
+    fn login_can_be_cancelled() {
+        let mock = MockProvider::start("mock-user").unwrap();
+        let request = LoginRequest::new("mock.state".to_string(), mock.get_provider(), mock.get_config());
+        let cancelled = Arc::new(Mutex::new(HashSet::from(["mock.state".to_string()])));
+        // Nobody follows the login page, so only the cancel ends the wait.
+        let result = block_on(login_with(request, cancelled, |_| {}));
+        assert_eq!(result.unwrap_err(), BackendError::Connection("Login cancelled".to_string()));
+    }
+
+    const REDIRECT_URI: &str = "http://127.0.0.1:1/";
+
+    // The redirect goes nowhere, so the code is read from where it points.
+    fn get_code(mock: &MockProvider, pkce: &Pkce) -> String {
+        let url = auth::get_login_url(&mock.get_provider(), "state", REDIRECT_URI, &[
+            ("code_challenge", pkce.challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ]);
+        let agent = ureq::AgentBuilder::new().redirects(0).build();
+        let location = agent.get(&url).call().unwrap().header("Location").unwrap().to_string();
+        Url::parse(&location).unwrap().query_pairs()
+            .find(|(key, _)| key == "code")
+            .map(|(_, code)| code.to_string())
+            .unwrap()
+    }
+
+    #[test]
+    fn code_is_exchanged_only_with_its_verifier() {
+        let mock = MockProvider::start("mock-user").unwrap();
+        let provider = mock.get_provider();
+        let pkce = Pkce::new();
+        let code = get_code(&mock, &pkce);
+
+        assert!(exchange_code(&provider, &code, &Pkce::new().verifier, REDIRECT_URI).is_err());
+        // The failed attempt used the code up.
+        assert!(exchange_code(&provider, &code, &pkce.verifier, REDIRECT_URI).is_err());
+    }
+
+    #[test]
+    fn code_of_a_provider_with_a_secret_is_exchanged_by_the_session_endpoint() {
+        let mock = MockProvider::start("mock-user").unwrap();
+        let provider = IdentityProvider::GitHub { client_id: "github-client".to_string() };
+        assert!(provider.is_secret_required() && !mock.get_provider().is_secret_required());
+        let session_endpoint = mock.get_config().session_endpoint;
+        let pkce = Pkce::new();
+        let code = get_code(&mock, &pkce);
+
+        assert!(sign_in_with_code(&session_endpoint, &provider, &code, &Pkce::new().verifier, REDIRECT_URI).is_err());
+        let code = get_code(&mock, &pkce);
+        let user = sign_in_with_code(&session_endpoint, &provider, &code, &pkce.verifier, REDIRECT_URI).unwrap();
+        assert_eq!(user.user_id, "mock-user");
+        assert!(user.session.is_some());
+    }
+
+    #[test]
+    fn session_backend_names_restores_and_ends_the_session() {
+        let mock = MockProvider::start("mock-user").unwrap();
+        let backend = SessionBackend::new(&mock.get_config(), Arc::new(LocalBackend::new("Local")));
+        let session = login_to(&mock, "mock.state").unwrap().session.unwrap();
+
+        block_on(backend.claim_name(session.clone(), "Mocker".to_string())).unwrap();
+        let restored = block_on(backend.restore_session(session.clone())).unwrap().unwrap();
+        assert_eq!((restored.user_id.as_str(), restored.name.as_deref()), ("mock-user", Some("Mocker")));
+
+        block_on(backend.end_session(session.clone())).unwrap();
+        assert_eq!(block_on(backend.restore_session(session.clone())).unwrap(), None);
+        assert!(block_on(backend.claim_name(session, "Mocker".to_string())).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+use serde::{Serialize, Deserialize};
+
+use crate::core::verify::Submission;
+use crate::system::backend::{BackendResource, BackendResult};
+use crate::system::firestore::{platform, LoginDone, RankingDataResource};
+use crate::system::guest::{self, GuestResource};
+use crate::system::network::{BackendTask, NetworkResource};
+use crate::utils::storage;
+
+const OUTBOX_KEY: &str = "outbox";
+const FAILED_KEY: &str = "outbox_failed";
+const MIN_RETRY_SEC: f64 = 2.0;
+const MAX_RETRY_SEC: f64 = 300.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub submission: Submission,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// Wins that have not reached the ranking backend yet. Every entry is saved before it is sent and
+/// removed only once the backend accepts it, so a crash or a closed tab loses nothing. Entries the
+/// backend rejects for good are set aside in `failed`, so they do not hold up the ones after them.
+#[derive(Resource)]
+pub struct OutboxResource {
+    pub entries: Vec<OutboxEntry>,
+    pub failed: Vec<OutboxEntry>,
+    is_sending: bool,
+    result: Arc<Mutex<Option<BackendResult<()>>>>,
+    retry_at: f64,
+}
+
+impl Default for OutboxResource {
+    fn default() -> Self {
+        Self {
+            entries: storage::load_yaml::<Vec<OutboxEntry>>(OUTBOX_KEY).unwrap_or_default(),
+            failed: storage::load_yaml::<Vec<OutboxEntry>>(FAILED_KEY).unwrap_or_default(),
+            is_sending: false,
+            result: Arc::new(Mutex::new(None)),
+            retry_at: 0.0,
+        }
+    }
+}
+
+impl OutboxResource {
+    pub fn push(&mut self, submission: Submission) {
+        self.entries.push(OutboxEntry {
+            submission,
+            attempts: 0,
+            last_error: None,
+        });
+        self.save();
+    }
+
+    pub fn retry_now(&mut self) {
+        self.retry_at = 0.0;
+    }
+
+    /// Queues the rejected entries again, e.g. once the rules that refused them are fixed.
+    pub fn retry_failed(&mut self) {
+        for mut entry in self.failed.drain(..) {
+            entry.attempts = 0;
+            entry.last_error = None;
+            self.entries.push(entry);
+        }
+        self.retry_at = 0.0;
+        self.save();
+    }
+
+    pub fn is_sending(&self) -> bool {
+        self.is_sending
+    }
+
+    pub fn get_last_error(&self) -> Option<&str> {
+        self.entries.first()?.last_error.as_deref()
+    }
+
+    pub fn get_retry_in(&self, now: f64) -> f64 {
+        (self.retry_at - now).max(0.0)
+    }
+
+    fn save(&self) {
+        for (key, entries) in [(OUTBOX_KEY, &self.entries), (FAILED_KEY, &self.failed)] {
+            if let Err(e) = storage::save_yaml(key, entries) {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    // 2s, 4s, 8s, ... up to five minutes between attempts at the same entry.
+    fn get_retry_delay(attempts: u32) -> f64 {
+        (MIN_RETRY_SEC * 2f64.powi(attempts.saturating_sub(1).min(16) as i32)).min(MAX_RETRY_SEC)
+    }
+}
+
+// Sends the oldest entry once logged in, or as the guest while nobody is, one at a time so the
+// order of wins is kept.
+pub fn flush_outbox(
+    mut outbox: ResMut<OutboxResource>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, guest): (Res<BackendResource>, Res<GuestResource>),
+    (login_done, network): (Res<LoginDone>, Res<NetworkResource>),
+    time: Res<Time>,
+    mut ranking_data: ResMut<RankingDataResource>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    if outbox.is_sending {
+        let Some(result) = outbox.result.lock().unwrap().take() else {
+            return;
+        };
+        outbox.is_sending = false;
+        match result {
+            Ok(()) => {
+                let entry = outbox.entries.remove(0);
+                ranking_data.expire(Some(&entry.submission.difficulty));
+            },
+            Err(e) if !e.is_retryable() => {
+                let mut entry = outbox.entries.remove(0);
+                warn!("{} win with seed {} was rejected: {}", entry.submission.difficulty, entry.submission.seed, e);
+                entry.attempts += 1;
+                entry.last_error = Some(e.to_string());
+                outbox.failed.push(entry);
+            },
+            Err(e) => {
+                let entry = &mut outbox.entries[0];
+                entry.attempts += 1;
+                entry.last_error = Some(e.to_string());
+                let delay = OutboxResource::get_retry_delay(entry.attempts);
+                outbox.retry_at = now + delay;
+            },
+        }
+        outbox.save();
+        return;
+    }
+
+    if outbox.entries.is_empty() || now < outbox.retry_at {
+        return;
+    }
+    let is_guest = guest::is_guest_active(&backend, &login_done);
+    let (id, name) = if is_guest {
+        // Until the guest is registered, anyone could claim the id its entries show.
+        if !guest.guest.is_registered {
+            return;
+        }
+        (guest.guest.id.clone(), guest.guest.handle.clone())
+    } else {
+        if !*login_done.done.lock().unwrap() {
+            return;
+        }
+        let Some(id) = login_done.id.lock().unwrap().clone() else {
+            return;
+        };
+        // Entries carry the display name, so they wait until one is chosen.
+        let Some(name) = login_done.name.lock().unwrap().clone() else {
+            return;
+        };
+        (id, name)
+    };
+
+    let ranking = backend.ranking.clone();
+    let submission = outbox.entries[0].submission.clone();
+    let result = outbox.result.clone();
+    let reporter = network.reporter.clone();
+    outbox.is_sending = true;
+    runtime.spawn_background_task(move |_ctx| async move {
+        let submitted = ranking.submit(id, name, is_guest, submission).await;
+        reporter.report(BackendTask::Submit, &submitted);
+        *result.lock().unwrap() = Some(submitted);
+    });
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone};
+
+/// Unix seconds from `start` up to, but not including, `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RankingRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl RankingRange {
+    pub fn contains(&self, timestamp: u64) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RankingPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+    #[default]
+    AllTime,
+}
+
+impl RankingPeriod {
+    pub fn get_name(&self) -> &str {
+        match self {
+            RankingPeriod::Daily => "daily",
+            RankingPeriod::Weekly => "weekly",
+            RankingPeriod::Monthly => "monthly",
+            RankingPeriod::AllTime => "all time",
+        }
+    }
+
+    /// The period `offset` periods before the one holding `now`, with its boundaries at local
+    /// midnight. Weeks start on Monday. All time has no range.
+    pub fn get_range(&self, offset: u32, now: DateTime<Local>) -> Option<RankingRange> {
+        let (first, next) = self.get_days(offset, now.date_naive())?;
+        Some(RankingRange {
+            start: to_timestamp(first),
+            end: to_timestamp(next),
+        })
+    }
+
+    pub fn get_label(&self, offset: u32, now: DateTime<Local>) -> String {
+        let Some((first, next)) = self.get_days(offset, now.date_naive()) else {
+            return "All time".to_string();
+        };
+        match (self, offset) {
+            (RankingPeriod::Daily, 0) => "Today".to_string(),
+            (RankingPeriod::Daily, 1) => "Yesterday".to_string(),
+            (RankingPeriod::Weekly, 0) => "This week".to_string(),
+            (RankingPeriod::Weekly, 1) => "Last week".to_string(),
+            (RankingPeriod::Monthly, 0) => "This month".to_string(),
+            (RankingPeriod::Monthly, 1) => "Last month".to_string(),
+            (RankingPeriod::Daily, _) => first.format("%Y-%m-%d").to_string(),
+            (RankingPeriod::Monthly, _) => first.format("%Y-%m").to_string(),
+            _ => format!("{} ~ {}", first.format("%Y-%m-%d"), (next - Duration::days(1)).format("%m-%d")),
+        }
+    }
+
+    // The first day of the period and the first day of the one after it.
+    fn get_days(&self, offset: u32, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match self {
+            RankingPeriod::Daily => {
+                let first = today - Duration::days(offset as i64);
+                Some((first, first + Duration::days(1)))
+            },
+            RankingPeriod::Weekly => {
+                let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+                let first = monday - Duration::weeks(offset as i64);
+                Some((first, first + Duration::weeks(1)))
+            },
+            RankingPeriod::Monthly => {
+                let month = today.year() * 12 + today.month0() as i32 - offset as i32;
+                Some((get_first_of_month(month)?, get_first_of_month(month + 1)?))
+            },
+            RankingPeriod::AllTime => None,
+        }
+    }
+}
+
+// Months counted from year 0, so that `month / 12` is the year.
+fn get_first_of_month(month: i32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, 1)
+}
+
+// Where midnight is skipped by a daylight saving change, the day starts at the first hour it has.
+fn to_timestamp(date: NaiveDate) -> u64 {
+    (0..3)
+        .find_map(|hour| Local.from_local_datetime(&date.and_hms_opt(hour, 0, 0)?).earliest())
+        .map_or(0, |datetime| datetime.timestamp().max(0) as u64)
+}
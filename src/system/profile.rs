@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::system::backend::{BackendError, BackendResource, BackendResult, RankingBest, RankingQuery};
+use crate::system::difficulty::Difficulty;
+use crate::system::firestore::{platform, RankingData};
+use crate::system::network::{BackendTask, NetworkResource};
+
+// Opening the same profile again within this many seconds shows the cached one.
+const PROFILE_CACHE_TTL_SEC: f64 = 60.0;
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ProfileResource>()
+            .add_event::<ProfileRequestEvent>()
+            .add_systems(Update, (read_profile, poll_profile));
+    }
+}
+
+/// Opens the profile window of a player, fetching the profile unless it is cached.
+#[derive(Event, Debug, Clone)]
+pub struct ProfileRequestEvent(pub String);
+
+#[derive(Debug, Clone, Default)]
+pub struct PlayerProfile {
+    /// Every entry of the player, oldest first.
+    pub entries: Vec<RankingData>,
+    /// The all-time best and rank on each difficulty the player has entries on.
+    pub bests: Vec<RankingBest>,
+}
+
+type ProfileResult = (String, BackendResult<PlayerProfile>);
+
+#[derive(Resource, Default)]
+pub struct ProfileResource {
+    /// The player in the profile window, which is closed while this is `None`.
+    pub id: Option<String>,
+    profiles: HashMap<String, (PlayerProfile, f64)>,
+    loading: HashSet<String>,
+    results: Arc<Mutex<Vec<ProfileResult>>>,
+    error: Option<(String, BackendError)>,
+}
+
+impl ProfileResource {
+    pub fn get_profile(&self, id: &str) -> Option<&PlayerProfile> {
+        self.profiles.get(id).map(|(profile, _)| profile)
+    }
+
+    pub fn is_loading(&self, id: &str) -> bool {
+        self.loading.contains(id)
+    }
+
+    pub fn get_error(&self, id: &str) -> Option<&BackendError> {
+        self.error.as_ref().filter(|(error_id, _)| error_id == id).map(|(_, error)| error)
+    }
+
+    pub fn close(&mut self) {
+        self.id = None;
+    }
+
+    fn is_fresh(&self, id: &str, now: f64) -> bool {
+        self.profiles.get(id).is_some_and(|(_, fetched_at)| now - fetched_at < PROFILE_CACHE_TTL_SEC)
+    }
+}
+
+pub fn read_profile(
+    mut request_events: EventReader<ProfileRequestEvent>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, network): (Res<BackendResource>, Res<NetworkResource>),
+    mut profile: ResMut<ProfileResource>,
+    time: Res<Time>,
+) {
+    for ProfileRequestEvent(id) in request_events.iter() {
+        profile.id = Some(id.clone());
+        if profile.loading.contains(id) || profile.is_fresh(id, time.elapsed_seconds_f64()) {
+            continue;
+        }
+
+        profile.loading.insert(id.clone());
+        profile.error = None;
+        let ranking = backend.ranking.clone();
+        let reporter = network.reporter.clone();
+        let results = profile.results.clone();
+        let id = id.clone();
+
+        runtime.spawn_background_task(move |_ctx| async move {
+            let fetched = async {
+                let entries = ranking.fetch_user(id.clone()).await?;
+                let mut bests = Vec::new();
+                for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+                    let difficulty = difficulty.to_string();
+                    if !entries.iter().any(|data| data.difficulty == difficulty) {
+                        continue;
+                    }
+                    let query = RankingQuery {
+                        difficulty,
+                        is_verified_only: false,
+                        range: None,
+                    };
+                    bests.extend(ranking.fetch_best(query, id.clone()).await?);
+                }
+                Ok::<_, BackendError>(PlayerProfile { entries, bests })
+            }.await;
+            reporter.report(BackendTask::Fetch, &fetched);
+            results.lock().unwrap().push((id, fetched));
+        });
+    }
+}
+
+pub fn poll_profile(
+    mut profile: ResMut<ProfileResource>,
+    time: Res<Time>,
+) {
+    let results = std::mem::take(&mut *profile.results.lock().unwrap());
+    for (id, result) in results {
+        profile.loading.remove(&id);
+        match result {
+            Ok(fetched) => {
+                profile.profiles.insert(id, (fetched, time.elapsed_seconds_f64()));
+            },
+            Err(e) => profile.error = Some((id, e)),
+        }
+    }
+}
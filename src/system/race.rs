@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+
+use crate::component::cell::{Cell, CellState};
+use crate::net::protocol::{ClientMessage, ServerMessage, PlayerStatus};
+use crate::system::difficulty::Difficulty;
+use crate::system::egui::IsLobbyOpen;
+use crate::system::lobby::{LobbyResource, ServerEvent};
+use crate::system::seed::Seed;
+use crate::system::state::GameState;
+
+pub struct RacePlugin;
+
+impl Plugin for RacePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RaceResource>()
+            .add_systems(Update, (
+                poll_race,
+                report_progress.after(poll_race),
+            ));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceStatus {
+    Idle,
+    Racing,
+    Finished,
+}
+
+#[derive(Resource)]
+pub struct RaceResource {
+    pub status: RaceStatus,
+    pub winner: Option<u32>,
+    last_sent: Option<(u32, PlayerStatus)>,
+}
+
+impl Default for RaceResource {
+    fn default() -> Self {
+        Self {
+            status: RaceStatus::Idle,
+            winner: None,
+            last_sent: None,
+        }
+    }
+}
+
+impl RaceResource {
+    pub fn is_in_race(&self) -> bool {
+        self.status == RaceStatus::Racing || self.status == RaceStatus::Finished
+    }
+
+    /// The race is still on, so its board may not be changed.
+    pub fn is_racing(&self) -> bool {
+        self.status == RaceStatus::Racing
+    }
+}
+
+pub fn poll_race(
+    mut race: ResMut<RaceResource>,
+    lobby: Res<LobbyResource>,
+    mut server_events: EventReader<ServerEvent>,
+    mut difficulty: ResMut<Difficulty>,
+    mut seed: ResMut<Seed>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut is_lobby_open: ResMut<IsLobbyOpen>,
+) {
+    for ServerEvent(message) in server_events.iter() {
+        match message {
+            ServerMessage::RaceStart { difficulty: race_difficulty, seed: race_seed } => {
+                if let Some(race_difficulty) = Difficulty::from_string(race_difficulty) {
+                    *difficulty = race_difficulty;
+                }
+                seed.start_session(*race_seed);
+                race.status = RaceStatus::Racing;
+                race.winner = None;
+                race.last_sent = None;
+                is_lobby_open.0 = false;
+                next_state.set(GameState::Init);
+            },
+            ServerMessage::RaceOver { winner } => {
+                race.status = RaceStatus::Finished;
+                race.winner = *winner;
+                seed.end_session();
+            },
+            ServerMessage::CoopStart { .. } => {
+                race.status = RaceStatus::Idle;
+            },
+            _ => {},
+        }
+    }
+
+    if !lobby.is_connected() && race.status != RaceStatus::Idle {
+        race.status = RaceStatus::Idle;
+        seed.end_session();
+    }
+}
+
+pub fn report_progress(
+    mut race: ResMut<RaceResource>,
+    lobby: Res<LobbyResource>,
+    q_cells: Query<&Cell>,
+    current_state: Res<State<GameState>>,
+) {
+    if race.status != RaceStatus::Racing {
+        return;
+    }
+
+    let status = match current_state.get() {
+        GameState::Init => return,
+        GameState::Ready | GameState::Playing => PlayerStatus::Playing,
+        GameState::Win => PlayerStatus::Cleared,
+        GameState::Defeated => PlayerStatus::Dead,
+    };
+    // The previous game's end state can still be current on the frame the race starts.
+    if race.last_sent.is_none() && status != PlayerStatus::Playing {
+        return;
+    }
+
+    let mut num_safe = 0;
+    let mut num_revealed = 0;
+    for cell in q_cells.iter() {
+        if cell.is_mine {
+            continue;
+        }
+        num_safe += 1;
+        if cell.state == CellState::Revealed {
+            num_revealed += 1;
+        }
+    }
+    let percent = if num_safe == 0 { 0 } else { num_revealed * 100 / num_safe };
+
+    if race.last_sent == Some((percent, status)) {
+        return;
+    }
+    if let Some((_, last_status)) = race.last_sent {
+        if last_status != PlayerStatus::Playing {
+            return;
+        }
+    }
+
+    lobby.send(&ClientMessage::Progress { percent: percent as f32, status });
+    race.last_sent = Some((percent, status));
+}
@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use crate::core::rules::{Action, Move};
+use crate::system::state::GameState;
+use crate::system::timer::platform::Timer;
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MoveEvent {
+    pub x: u32,
+    pub y: u32,
+    pub action: Action,
+}
+
+#[derive(Resource, Default)]
+pub struct MoveLog {
+    pub moves: Vec<Move>,
+}
+
+pub fn reset(
+    mut move_log: ResMut<MoveLog>,
+) {
+    move_log.moves.clear();
+}
+
+pub fn record(
+    mut move_events: EventReader<MoveEvent>,
+    mut move_log: ResMut<MoveLog>,
+    timer: Res<Timer>,
+    current_state: Res<State<GameState>>,
+) {
+    let is_recording = *current_state == GameState::Ready || *current_state == GameState::Playing;
+    for event in move_events.iter() {
+        if !is_recording {
+            continue;
+        }
+        move_log.moves.push(Move {
+            time_ms: timer.get_milli_sec() as u64,
+            x: event.x,
+            y: event.y,
+            action: event.action,
+        });
+    }
+}
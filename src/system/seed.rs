@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Seed {
+    pub value: u64,
+    pub is_fixed: bool,
+    // The seed of the player from before a race or co-op game, and whether that game is over.
+    saved: Option<(u64, bool)>,
+    is_session_over: bool,
+}
+
+impl Default for Seed {
+    fn default() -> Self {
+        Self {
+            value: new_seed(),
+            is_fixed: false,
+            saved: None,
+            is_session_over: false,
+        }
+    }
+}
+
+impl Seed {
+    pub fn fixed(value: u64) -> Self {
+        Self {
+            value,
+            is_fixed: true,
+            ..default()
+        }
+    }
+
+    /// Plays the seed of a race or co-op game. The first one keeps the player's own seed.
+    pub fn start_session(&mut self, value: u64) {
+        if self.saved.is_none() {
+            self.saved = Some((self.value, self.is_fixed));
+        }
+        self.value = value;
+        self.is_fixed = true;
+        self.is_session_over = false;
+    }
+
+    /// The player's own seed is back from the next game on. The current one may still be won,
+    /// and is submitted with the seed it was played with.
+    pub fn end_session(&mut self) {
+        self.is_session_over = self.saved.is_some();
+    }
+}
+
+// Kept within u32 so the seed survives JSON and Firestore integers unchanged.
+pub fn new_seed() -> u64 {
+    rand::random::<u32>() as u64
+}
+
+pub fn roll(
+    mut seed: ResMut<Seed>,
+) {
+    if seed.is_session_over {
+        if let Some((value, is_fixed)) = seed.saved.take() {
+            seed.value = value;
+            seed.is_fixed = is_fixed;
+        }
+        seed.is_session_over = false;
+    }
+    if !seed.is_fixed {
+        seed.value = new_seed();
+    }
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::system::backend::BackendResource;
+use crate::system::firestore::{platform, LoginDone, RankingDataResource};
+use crate::system::name::NameResource;
+use crate::system::state::LoginState;
+use crate::utils::storage;
+
+const SESSION_KEY: &str = "session";
+
+pub struct SessionPlugin;
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<LogoutEvent>()
+            .add_systems(Update, logout);
+    }
+}
+
+/// Forgets the logged in user here and on the backend.
+#[derive(Event, Debug, Clone)]
+pub struct LogoutEvent;
+
+/// The login of the last launch, restored at startup while the backend still knows the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub user_id: String,
+    pub token: String,
+}
+
+pub fn load() -> Option<Session> {
+    storage::load_yaml::<Session>(SESSION_KEY)
+}
+
+// The token is as good as the login itself, so other users of the machine must not read it.
+pub fn save(session: &Session) {
+    if let Err(e) = storage::save_private_yaml(SESSION_KEY, session) {
+        warn!("{}", e);
+    }
+}
+
+pub fn clear() {
+    storage::platform::remove(SESSION_KEY);
+}
+
+pub fn logout(
+    mut logout_events: EventReader<LogoutEvent>,
+    runtime: ResMut<platform::TasksRuntime>,
+    (backend, login_done): (Res<BackendResource>, Res<LoginDone>),
+    (mut name, mut ranking_data): (ResMut<NameResource>, ResMut<RankingDataResource>),
+    mut next_state: ResMut<NextState<LoginState>>,
+) {
+    if logout_events.iter().count() == 0 || !*login_done.done.lock().unwrap() {
+        return;
+    }
+
+    info!("logout");
+    clear();
+    if let Some(token) = login_done.session.lock().unwrap().take() {
+        let auth = backend.auth.clone();
+        runtime.spawn_background_task(move |_ctx| async move {
+            // The session still expires on the backend, so a failure is only logged.
+            if let Err(e) = auth.end_session(token).await {
+                warn!("Unable to end the session: {}", e);
+            }
+        });
+    }
+    *login_done.id.lock().unwrap() = None;
+    *login_done.name.lock().unwrap() = None;
+    *login_done.done.lock().unwrap() = false;
+    next_state.set(LoginState::Not);
+
+    name.is_open = false;
+    // The best entry shown in each view belongs to the user who left.
+    ranking_data.expire(None);
+}
@@ -0,0 +1,278 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Serialize, Deserialize};
+
+use crate::asset::skin;
+use crate::system::difficulty::Difficulty;
+use crate::system::egui::{IsRankingOpen, IsSettingsOpen};
+use crate::system::mouse::{InputMode, RestartEvent};
+use crate::system::race::RaceResource;
+use crate::system::state::GameState;
+use crate::utils::storage;
+
+const SETTINGS_KEY: &str = "settings";
+
+pub struct SettingsPlugin;
+
+// Loaded while the app is built, so the first board is laid out for the saved difficulty.
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = storage::load_yaml::<Settings>(SETTINGS_KEY).unwrap_or_default();
+        // A custom size edited out of bounds by hand falls back to the default.
+        let difficulty = Difficulty::from_string(&settings.difficulty.to_string()).unwrap_or_default();
+        app
+            .insert_resource(difficulty)
+            .insert_resource(settings)
+            .init_resource::<KeyRebinding>()
+            .add_systems(Update, (
+                remember_difficulty,
+                save_settings.after(remember_difficulty),
+                (keyboard_system, rebind_key).chain(),
+            ));
+    }
+}
+
+/// Preferences kept between launches, in `./save/settings.yaml` or local storage on the web.
+#[derive(Debug, Clone, Resource, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The difficulty of the last game, started again on the next launch.
+    pub difficulty: Difficulty,
+    /// Right clicks go from a flag to a question mark before the cell is hidden again.
+    pub is_question_marks: bool,
+    /// The first click opens an area. Such games do not count for the rankings or the bests.
+    pub is_safe_start: bool,
+    pub theme: Theme,
+    /// The id of a skin pack in `assets/skins`, or `classic`.
+    pub skin: String,
+    pub is_sound: bool,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::Hard,
+            is_question_marks: false,
+            is_safe_start: false,
+            theme: Theme::Dark,
+            skin: skin::DEFAULT_SKIN.to_string(),
+            is_sound: true,
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+
+    pub fn get_visuals(&self) -> egui::Visuals {
+        let visuals = match self {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+        };
+        egui::Visuals {
+            window_rounding: 0.0.into(),
+            ..visuals
+        }
+    }
+
+    pub fn get_clear_color(&self) -> Color {
+        match self {
+            Theme::Dark => Color::rgb(0.1, 0.1, 0.1),
+            Theme::Light => Color::rgb(0.9, 0.9, 0.9),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    NewGame,
+    Easy,
+    Normal,
+    Hard,
+    Ranking,
+    Settings,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 6] = [
+        KeyAction::NewGame,
+        KeyAction::Easy,
+        KeyAction::Normal,
+        KeyAction::Hard,
+        KeyAction::Ranking,
+        KeyAction::Settings,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            KeyAction::NewGame => "New Game",
+            KeyAction::Easy => "Easy",
+            KeyAction::Normal => "Normal",
+            KeyAction::Hard => "Hard",
+            KeyAction::Ranking => "Ranking",
+            KeyAction::Settings => "Settings",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub new_game: KeyCode,
+    pub easy: KeyCode,
+    pub normal: KeyCode,
+    pub hard: KeyCode,
+    pub ranking: KeyCode,
+    pub settings: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            new_game: KeyCode::F2,
+            easy: KeyCode::Key1,
+            normal: KeyCode::Key2,
+            hard: KeyCode::Key3,
+            ranking: KeyCode::F3,
+            settings: KeyCode::F4,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: KeyAction) -> KeyCode {
+        match action {
+            KeyAction::NewGame => self.new_game,
+            KeyAction::Easy => self.easy,
+            KeyAction::Normal => self.normal,
+            KeyAction::Hard => self.hard,
+            KeyAction::Ranking => self.ranking,
+            KeyAction::Settings => self.settings,
+        }
+    }
+
+    fn get_mut(&mut self, action: KeyAction) -> &mut KeyCode {
+        match action {
+            KeyAction::NewGame => &mut self.new_game,
+            KeyAction::Easy => &mut self.easy,
+            KeyAction::Normal => &mut self.normal,
+            KeyAction::Hard => &mut self.hard,
+            KeyAction::Ranking => &mut self.ranking,
+            KeyAction::Settings => &mut self.settings,
+        }
+    }
+
+    /// Binds the key to the action. An action that had the key before takes the old key of this
+    /// one, so no key does two things.
+    pub fn set(&mut self, action: KeyAction, key: KeyCode) {
+        let old_key = self.get(action);
+        if let Some(other) = KeyAction::ALL.into_iter().find(|other| *other != action && self.get(*other) == key) {
+            *self.get_mut(other) = old_key;
+        }
+        *self.get_mut(action) = key;
+    }
+}
+
+/// The action whose key the settings window is waiting for.
+#[derive(Resource, Default)]
+pub struct KeyRebinding(pub Option<KeyAction>);
+
+// Only the difficulty of a solo game is the one to start with next time, not that of a race or of
+// a co-op or stream session.
+pub fn remember_difficulty(
+    difficulty: Res<Difficulty>,
+    mut settings: ResMut<Settings>,
+    (input_mode, race): (Res<InputMode>, Res<RaceResource>),
+) {
+    let is_solo = *input_mode == InputMode::Local && !race.is_in_race();
+    if is_solo && settings.difficulty != *difficulty {
+        settings.difficulty = *difficulty;
+    }
+}
+
+pub fn save_settings(
+    settings: Res<Settings>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    if let Err(e) = storage::save_yaml(SETTINGS_KEY, &*settings) {
+        warn!("{}", e);
+    }
+}
+
+pub fn keyboard_system(
+    keys: Res<Input<KeyCode>>,
+    mut contexts: EguiContexts,
+    (settings, rebinding): (Res<Settings>, Res<KeyRebinding>),
+    (mut is_ranking_open, mut is_settings_open): (ResMut<IsRankingOpen>, ResMut<IsSettingsOpen>),
+    (mut difficulty, mut game_state, mut restart_events): (ResMut<Difficulty>, ResMut<NextState<GameState>>, EventWriter<RestartEvent>),
+    (input_mode, race): (Res<InputMode>, Res<RaceResource>),
+) {
+    // Keys typed into a text field, e.g. the display name, are not shortcuts.
+    if rebinding.0.is_some() || contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    for action in KeyAction::ALL {
+        if !keys.just_pressed(settings.key_bindings.get(action)) {
+            continue;
+        }
+        match action {
+            // The same as a click on the smiley.
+            KeyAction::NewGame => match *input_mode {
+                InputMode::Local => game_state.set(GameState::Ready),
+                InputMode::Remote => restart_events.send(RestartEvent),
+                InputMode::Disabled => {},
+            },
+            // Like the difficulty menu, only in a solo game.
+            KeyAction::Easy | KeyAction::Normal | KeyAction::Hard => {
+                if *input_mode != InputMode::Local || race.is_racing() {
+                    continue;
+                }
+                *difficulty = match action {
+                    KeyAction::Easy => Difficulty::Easy,
+                    KeyAction::Normal => Difficulty::Normal,
+                    _ => Difficulty::Hard,
+                };
+                game_state.set(GameState::Init);
+            },
+            KeyAction::Ranking => is_ranking_open.0 = !is_ranking_open.0,
+            KeyAction::Settings => is_settings_open.0 = !is_settings_open.0,
+        }
+    }
+}
+
+// Escape keeps the old key.
+pub fn rebind_key(
+    keys: Res<Input<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<KeyRebinding>,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    let Some(key) = keys.get_just_pressed().next().copied() else {
+        return;
+    };
+    if key != KeyCode::Escape {
+        settings.key_bindings.set(action, key);
+    }
+    rebinding.0 = None;
+}
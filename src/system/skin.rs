@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use std::time::SystemTime;
+
+use crate::asset::loader::{self, TextureAtlasResource};
+use crate::asset::skin::{self, SkinResource};
+use crate::system::settings::Settings;
+
+const RELOAD_INTERVAL_SECS: f32 = 1.0;
+
+pub struct SkinPlugin;
+
+// The skin of the settings is loaded by `loader::setup`, this follows the changes.
+impl Plugin for SkinPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SkinWatch>()
+            .add_systems(Update, switch_skin.run_if(resource_changed::<Settings>()));
+        // The web build has no files to watch.
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(Update, hot_reload.after(switch_skin));
+    }
+}
+
+#[derive(Resource)]
+pub struct SkinWatch {
+    timer: Timer,
+    /// Of `assets/skins`, when the menu was last listed.
+    list_stamp: Option<SystemTime>,
+}
+
+impl Default for SkinWatch {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(RELOAD_INTERVAL_SECS, TimerMode::Repeating),
+            list_stamp: None,
+        }
+    }
+}
+
+pub fn switch_skin(
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    (mut images, mut texture_atlases, mut texture_atlas_resource): (ResMut<Assets<Image>>, ResMut<Assets<TextureAtlas>>, ResMut<TextureAtlasResource>),
+    mut skin_resource: ResMut<SkinResource>,
+) {
+    if settings.skin == skin_resource.current {
+        return;
+    }
+    loader::load_skin(&settings.skin, &asset_server, &mut images, &mut texture_atlases, &mut texture_atlas_resource, &mut skin_resource);
+}
+
+// Polls the files of the current skin, so a designer sees each save without restarting, and
+// picks up skins added to `assets/skins` for the menu.
+pub fn hot_reload(
+    time: Res<Time>,
+    mut watch: ResMut<SkinWatch>,
+    asset_server: Res<AssetServer>,
+    (mut images, mut texture_atlases, mut texture_atlas_resource): (ResMut<Assets<Image>>, ResMut<Assets<TextureAtlas>>, ResMut<TextureAtlasResource>),
+    mut skin_resource: ResMut<SkinResource>,
+) {
+    if !watch.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    // Listing reads every manifest, so only when a skin comes or goes.
+    let list_stamp = skin::platform::get_list_stamp();
+    if list_stamp != watch.list_stamp {
+        watch.list_stamp = list_stamp;
+        let skins = skin::platform::list();
+        if skins != skin_resource.skins {
+            skin_resource.skins = skins;
+        }
+    }
+    let id = skin_resource.current.clone();
+    if id == skin::DEFAULT_SKIN || skin::platform::get_stamp(&id) == skin_resource.stamp {
+        return;
+    }
+    info!("Reloading skin {}", id);
+    loader::load_skin(&id, &asset_server, &mut images, &mut texture_atlases, &mut texture_atlas_resource, &mut skin_resource);
+}
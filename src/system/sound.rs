@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy::audio::{AudioBundle, AudioSource, PlaybackSettings};
+
+use crate::core::rules::Action;
+use crate::system::replay::MoveEvent;
+use crate::system::settings::Settings;
+use crate::system::state::GameState;
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Sounds>()
+            .add_systems(Update, play_move_sounds)
+            .add_systems(OnEnter(GameState::Win), play_win_sound)
+            .add_systems(OnEnter(GameState::Defeated), play_defeat_sound);
+    }
+}
+
+#[derive(Resource)]
+pub struct Sounds {
+    reveal: Handle<AudioSource>,
+    flag: Handle<AudioSource>,
+    win: Handle<AudioSource>,
+    defeat: Handle<AudioSource>,
+}
+
+// Loaded up front, so the first click is not silent while its sound loads.
+impl FromWorld for Sounds {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            reveal: asset_server.load("sounds/reveal.wav"),
+            flag: asset_server.load("sounds/flag.wav"),
+            win: asset_server.load("sounds/win.wav"),
+            defeat: asset_server.load("sounds/defeat.wav"),
+        }
+    }
+}
+
+fn play(commands: &mut Commands, settings: &Settings, sound: &Handle<AudioSource>) {
+    if !settings.is_sound {
+        return;
+    }
+    commands.spawn(AudioBundle {
+        source: sound.clone(),
+        settings: PlaybackSettings::DESPAWN,
+    });
+}
+
+// Every move of the mouse or the bot goes out as a `MoveEvent`, so the sounds follow the player
+// and not the updates of other players in co-op.
+pub fn play_move_sounds(
+    mut commands: Commands,
+    mut move_events: EventReader<MoveEvent>,
+    sounds: Res<Sounds>,
+    settings: Res<Settings>,
+) {
+    // One sound for a frame of moves, e.g. a fast bot.
+    let Some(event) = move_events.iter().last() else {
+        return;
+    };
+    let sound = match event.action {
+        Action::Reveal | Action::Chord => &sounds.reveal,
+        Action::Flag => &sounds.flag,
+    };
+    play(&mut commands, &settings, sound);
+}
+
+pub fn play_win_sound(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    settings: Res<Settings>,
+) {
+    play(&mut commands, &settings, &sounds.win);
+}
+
+pub fn play_defeat_sound(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    settings: Res<Settings>,
+) {
+    play(&mut commands, &settings, &sounds.defeat);
+}
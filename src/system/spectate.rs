@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::component::cell::{Cell, CellState};
+use crate::component::grid::Grid;
+use crate::core::rules::TileState;
+use crate::net::protocol::{ClientMessage, ServerMessage, PlayerStatus, StreamFrame, TileUpdate};
+use crate::system::difficulty::Difficulty;
+use crate::system::lobby::{LobbyResource, ServerEvent};
+use crate::system::mouse::InputMode;
+use crate::system::state::GameState;
+use crate::system::timer::platform::Timer;
+
+pub struct SpectatePlugin;
+
+impl Plugin for SpectatePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SpectateResource>()
+            .add_systems(OnEnter(GameState::Init), enter_board)
+            .add_systems(OnEnter(GameState::Ready), restart_stream)
+            .add_systems(Update, (
+                stream_board,
+                poll_spectate,
+                apply_stream.after(poll_spectate)
+                    .run_if(not(in_state(GameState::Init))),
+            ));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SpectateResource {
+    pub is_streaming: bool,
+    pub watching: Option<u32>,
+    is_resetting: bool,
+    is_reset_pending: bool,
+    sent: HashMap<(u32, u32), TileState>,
+    last_sec: u64,
+    last_status: Option<PlayerStatus>,
+    pending: Vec<TileUpdate>,
+    time_ms: Option<u64>,
+    status: Option<PlayerStatus>,
+}
+
+impl SpectateResource {
+    pub fn set_streaming(&mut self, lobby: &LobbyResource, is_streaming: bool) {
+        self.is_streaming = is_streaming;
+        if is_streaming {
+            self.is_reset_pending = true;
+        } else {
+            lobby.send(&ClientMessage::StopStream);
+        }
+    }
+
+    pub fn watch(&mut self, lobby: &LobbyResource, id: Option<u32>) {
+        self.watching = id;
+        self.pending.clear();
+        self.time_ms = None;
+        self.status = None;
+        lobby.send(&ClientMessage::Spectate { id });
+    }
+}
+
+fn get_status(state: &GameState) -> PlayerStatus {
+    match state {
+        GameState::Init | GameState::Ready => PlayerStatus::Waiting,
+        GameState::Playing => PlayerStatus::Playing,
+        GameState::Win => PlayerStatus::Cleared,
+        GameState::Defeated => PlayerStatus::Dead,
+    }
+}
+
+fn get_game_state(status: PlayerStatus) -> GameState {
+    match status {
+        PlayerStatus::Waiting => GameState::Ready,
+        PlayerStatus::Playing => GameState::Playing,
+        PlayerStatus::Cleared => GameState::Win,
+        PlayerStatus::Dead => GameState::Defeated,
+    }
+}
+
+// The board being watched is rebuilt through `GameState::Init`; any other new board is played locally again.
+pub fn enter_board(
+    mut spectate: ResMut<SpectateResource>,
+    lobby: Res<LobbyResource>,
+    mut input_mode: ResMut<InputMode>,
+) {
+    if spectate.is_resetting {
+        spectate.is_resetting = false;
+        *input_mode = InputMode::Disabled;
+        return;
+    }
+
+    if spectate.watching.is_some() {
+        spectate.watch(&lobby, None);
+    }
+    if *input_mode == InputMode::Disabled {
+        *input_mode = InputMode::Local;
+    }
+}
+
+pub fn restart_stream(
+    mut spectate: ResMut<SpectateResource>,
+) {
+    spectate.is_reset_pending = true;
+}
+
+pub fn stream_board(
+    mut spectate: ResMut<SpectateResource>,
+    lobby: Res<LobbyResource>,
+    difficulty: Res<Difficulty>,
+    timer: Res<Timer>,
+    current_state: Res<State<GameState>>,
+    q_cells: Query<&Cell>,
+) {
+    if !spectate.is_streaming || spectate.watching.is_some() {
+        return;
+    }
+    // A new connection starts with an empty cache on the relay, so it needs a full board.
+    if !lobby.is_connected() {
+        spectate.is_reset_pending = true;
+        return;
+    }
+    if *current_state == GameState::Init {
+        return;
+    }
+
+    let is_reset = spectate.is_reset_pending;
+    if is_reset {
+        spectate.is_reset_pending = false;
+        spectate.sent.clear();
+        spectate.last_status = None;
+    }
+
+    let status = get_status(current_state.get());
+    let is_over = status == PlayerStatus::Cleared || status == PlayerStatus::Dead;
+    let mut tiles = Vec::new();
+    for cell in q_cells.iter() {
+        let state = cell.state.to_tile();
+        let last_state = spectate.sent.get(&(cell.x, cell.y)).copied().unwrap_or(TileState::Hidden);
+        if state == last_state {
+            continue;
+        }
+        spectate.sent.insert((cell.x, cell.y), state);
+
+        let is_shown = matches!(state, TileState::Revealed | TileState::WrongFlagged | TileState::Exploded) || is_over;
+        tiles.push(TileUpdate {
+            x: cell.x,
+            y: cell.y,
+            state,
+            is_mine: is_shown && cell.is_mine,
+            num_mines_around: if is_shown { cell.num_mines_around } else { 0 },
+            flagged_by: None,
+        });
+    }
+
+    let sec = timer.get_sec();
+    let is_tick = sec != spectate.last_sec;
+    let is_status_changed = spectate.last_status != Some(status);
+    if !is_reset && tiles.is_empty() && !is_tick && !is_status_changed {
+        return;
+    }
+    spectate.last_sec = sec;
+    spectate.last_status = Some(status);
+
+    lobby.send(&ClientMessage::Stream {
+        frame: StreamFrame {
+            difficulty: difficulty.to_string(),
+            is_reset,
+            time_ms: timer.get_milli_sec() as u64,
+            status,
+            tiles,
+        },
+    });
+}
+
+pub fn poll_spectate(
+    mut spectate: ResMut<SpectateResource>,
+    lobby: Res<LobbyResource>,
+    mut server_events: EventReader<ServerEvent>,
+    mut difficulty: ResMut<Difficulty>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for ServerEvent(message) in server_events.iter() {
+        match message {
+            ServerMessage::Stream { id, frame } if spectate.watching == Some(*id) => {
+                if frame.is_reset {
+                    if let Some(stream_difficulty) = Difficulty::from_string(&frame.difficulty) {
+                        *difficulty = stream_difficulty;
+                    }
+                    spectate.pending.clear();
+                    spectate.is_resetting = true;
+                    next_state.set(GameState::Init);
+                }
+                spectate.pending.extend(frame.tiles.iter().cloned());
+                spectate.time_ms = Some(frame.time_ms);
+                spectate.status = Some(frame.status);
+            },
+            ServerMessage::StreamEnd { id } if spectate.watching == Some(*id) => {
+                spectate.watching = None;
+                next_state.set(GameState::Init);
+            },
+            ServerMessage::RaceStart { .. } | ServerMessage::CoopStart { .. } if spectate.watching.is_some() => {
+                spectate.watch(&lobby, None);
+            },
+            _ => {},
+        }
+    }
+
+    if spectate.watching.is_some() && !lobby.is_connected() {
+        spectate.watching = None;
+        next_state.set(GameState::Init);
+    }
+}
+
+pub fn apply_stream(
+    mut spectate: ResMut<SpectateResource>,
+    grid: Res<Grid>,
+    mut q_cells: Query<&mut Cell>,
+    mut timer: ResMut<Timer>,
+    current_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if spectate.watching.is_none() || spectate.is_resetting {
+        return;
+    }
+
+    for tile in spectate.pending.drain(..) {
+        let Some(entity) = grid.find_cell(tile.x, tile.y) else {
+            continue;
+        };
+        if let Ok(mut cell) = q_cells.get_mut(entity) {
+            cell.change_mine(tile.is_mine, tile.num_mines_around);
+            cell.state = CellState::from_tile(tile.state);
+        }
+    }
+
+    if let Some(time_ms) = spectate.time_ms.take() {
+        timer.set_milli_sec(time_ms);
+    }
+    if let Some(status) = spectate.status {
+        let state = get_game_state(status);
+        if *current_state != state {
+            next_state.set(state);
+        }
+    }
+}
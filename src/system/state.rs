@@ -39,11 +39,17 @@ pub enum RankingWindowState {
 }
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
-pub enum DataReadingState {
+pub enum LobbyWindowState {
     #[default]
-    Idle,
-    Ready,
-    Done,
+    Closed,
+    Opened,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum SettingsWindowState {
+    #[default]
+    Closed,
+    Opened,
 }
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
@@ -57,6 +63,11 @@ pub enum LoginPopupState {
 pub enum LoginState {
     #[default]
     Not,
+    /// Waiting for the login started in the browser.
+    Pending,
+    TimedOut,
+    Cancelled,
+    Failed,
     Done,
 }
 
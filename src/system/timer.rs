@@ -63,6 +63,14 @@ pub mod platform {
         pub fn reset(&mut self) {
             self.state = TimerState::Stopped { elapsed: Duration::new(0, 0) };
         }
+
+        pub fn set_milli_sec(&mut self, milli_sec: u64) {
+            let elapsed = Duration::from_millis(milli_sec);
+            self.state = match &self.state {
+                TimerState::Stopped { .. } => TimerState::Stopped { elapsed },
+                TimerState::Running { .. } => TimerState::Running { start: Instant::now(), elapsed },
+            };
+        }
     }
 }
 #[cfg(target_arch = "wasm32")]
@@ -129,5 +137,13 @@ pub mod platform {
         pub fn reset(&mut self) {
             self.state = TimerState::Stopped { elapsed: 0.0 };
         }
+
+        pub fn set_milli_sec(&mut self, milli_sec: u64) {
+            let elapsed = milli_sec as f64;
+            self.state = match &self.state {
+                TimerState::Stopped { .. } => TimerState::Stopped { elapsed },
+                TimerState::Running { .. } => TimerState::Running { start: Date::now(), elapsed },
+            };
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+pub fn load_yaml<T>(key: &str) -> Option<T>
+where T: DeserializeOwned {
+    let s = platform::load(key)?;
+    serde_yaml::from_str(&s).ok()
+}
+
+pub fn save_yaml<T>(key: &str, value: &T) -> Result<(), String>
+where T: Serialize {
+    let s = serde_yaml::to_string(value).map_err(|e| format!("Unable to serialize {}: {}", key, e))?;
+    platform::save(key, &s)
+}
+
+/// Like `save_yaml`, but readable only by the current user where the platform allows it.
+pub fn save_private_yaml<T>(key: &str, value: &T) -> Result<(), String>
+where T: Serialize {
+    let s = serde_yaml::to_string(value).map_err(|e| format!("Unable to serialize {}: {}", key, e))?;
+    platform::save_private(key, &s)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    const SAVE_PATH: &str = "./save/";
+
+    fn get_path(key: &str) -> PathBuf {
+        PathBuf::from(SAVE_PATH).join(format!("{}.yaml", key))
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        fs::read_to_string(get_path(key)).ok()
+    }
+
+    pub fn save(key: &str, value: &str) -> Result<(), String> {
+        fs::create_dir_all(SAVE_PATH).map_err(|e| format!("Unable to create {}: {}", SAVE_PATH, e))?;
+        let path = get_path(key);
+        fs::write(&path, value).map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
+
+    #[cfg(unix)]
+    pub fn save_private(key: &str, value: &str) -> Result<(), String> {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        fs::create_dir_all(SAVE_PATH).map_err(|e| format!("Unable to create {}: {}", SAVE_PATH, e))?;
+        let path = get_path(key);
+        let write = || {
+            let mut file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&path)?;
+            // The mode applies only to new files.
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+            file.write_all(value.as_bytes())
+        };
+        write().map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
+
+    #[cfg(not(unix))]
+    pub fn save_private(key: &str, value: &str) -> Result<(), String> {
+        save(key, value)
+    }
+
+    pub fn remove(key: &str) {
+        let _ = fs::remove_file(get_path(key));
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub mod platform {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        fn load_storage_js(key: &str) -> Option<String>;
+        fn save_storage_js(key: &str, value: &str) -> bool;
+        fn remove_storage_js(key: &str);
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        load_storage_js(key)
+    }
+
+    pub fn save(key: &str, value: &str) -> Result<(), String> {
+        if save_storage_js(key, value) {
+            Ok(())
+        } else {
+            Err(format!("Unable to write {} to local storage", key))
+        }
+    }
+
+    // Local storage is already private to the origin.
+    pub fn save_private(key: &str, value: &str) -> Result<(), String> {
+        save(key, value)
+    }
+
+    pub fn remove(key: &str) {
+        remove_storage_js(key);
+    }
+}
@@ -30,4 +30,10 @@ pub fn load_yaml_from_string<T>(str: impl AsRef<str>) -> T
 where T: DeserializeOwned {
     let target: T = serde_yaml::from_str(str.as_ref()).expect(&format!("Unable to parse {}", str.as_ref()));
     target
-}
\ No newline at end of file
+}
+
+/// Like `load_yaml_from_string`, but returns the error, e.g. for a file written by hand.
+pub fn parse_yaml<T>(str: impl AsRef<str>) -> Result<T, String>
+where T: DeserializeOwned {
+    serde_yaml::from_str(str.as_ref()).map_err(|e| e.to_string())
+}